@@ -3,7 +3,7 @@
 use anyhow::Result;
 use std::path::PathBuf;
 
-use crate::config::Config;
+use crate::config::{Config, Layers};
 use crate::team::{SupportTeam, Task, TaskProgress};
 
 /// Focus areas in the UI
@@ -98,9 +98,9 @@ impl App {
         _output_path: Option<String>,
         config_path: Option<String>,
     ) -> Result<Self> {
-        let config = Config::load(config_path.as_deref())?;
-        let team = SupportTeam::new(&config);
         let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let config = Layers::load(config_path.as_deref(), &cwd)?.config()?;
+        let team = SupportTeam::new(&config);
 
         let mut app = Self {
             config,
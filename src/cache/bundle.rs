@@ -0,0 +1,190 @@
+//! Cache bundles - entries packed up to move between machines
+//!
+//! A bundle is gzip-compressed JSON Lines: a header, then one entry per line
+//! with its key. Entries keep their creation time and TTL, so an imported
+//! answer expires when the original would have. A key must be the digest of
+//! its entry's fields, so a bundle can't file an answer under another request.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::{now_secs, CacheEntry, StoredEntry, CACHE_VERSION};
+
+/// Marks a file as a cache bundle
+const BUNDLE_FORMAT: &str = "workyterm-cache-bundle";
+
+#[derive(Serialize, Deserialize)]
+struct BundleHeader {
+    format: String,
+    /// Key scheme of the entries; keys from another scheme never match
+    cache_version: u32,
+    exported_at: u64,
+    entries: usize,
+}
+
+#[derive(Serialize)]
+struct BundleLine<'a> {
+    key: &'a str,
+    entry: &'a CacheEntry,
+}
+
+#[derive(Deserialize)]
+struct BundleEntry {
+    key: String,
+    entry: CacheEntry,
+}
+
+/// What importing a bundle did
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    /// Entries that weren't cached yet
+    pub added: usize,
+    /// Cached entries replaced by a newer one from the bundle
+    pub updated: usize,
+    /// Bundle entries skipped because the cached one is as new or newer
+    pub kept: usize,
+}
+
+/// Write `entries` to a bundle at `path`
+pub fn write_bundle(path: &Path, entries: &[StoredEntry]) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = GzEncoder::new(BufWriter::new(file), Compression::default());
+
+    let header = BundleHeader {
+        format: BUNDLE_FORMAT.to_string(),
+        cache_version: CACHE_VERSION,
+        exported_at: now_secs(),
+        entries: entries.len(),
+    };
+    serde_json::to_writer(&mut out, &header)?;
+    out.write_all(b"\n")?;
+    for stored in entries {
+        let line = BundleLine {
+            key: &stored.key,
+            entry: &stored.entry,
+        };
+        serde_json::to_writer(&mut out, &line)?;
+        out.write_all(b"\n")?;
+    }
+    out.finish()?.flush()?;
+    Ok(())
+}
+
+/// Read the entries of the bundle at `path`
+pub fn read_bundle(path: &Path) -> Result<Vec<StoredEntry>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut lines = BufReader::new(GzDecoder::new(file)).lines();
+
+    let header: BundleHeader = lines
+        .next()
+        .transpose()?
+        .and_then(|line| serde_json::from_str(&line).ok())
+        .filter(|header: &BundleHeader| header.format == BUNDLE_FORMAT)
+        .ok_or_else(|| anyhow::anyhow!("{} is not a cache bundle", path.display()))?;
+    if header.cache_version != CACHE_VERSION {
+        anyhow::bail!(
+            "{} holds cache version {} entries; this workyterm uses version {}",
+            path.display(),
+            header.cache_version,
+            CACHE_VERSION
+        );
+    }
+
+    let mut entries = Vec::with_capacity(header.entries);
+    for (n, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let BundleEntry { key, entry } = serde_json::from_str(&line)
+            .with_context(|| format!("Bad entry on line {} of the bundle", n + 2))?;
+        // The key must be the one the entry answers, or the bundle could
+        // slip an answer in under another request's key; being a digest, it
+        // is also a safe file name for the files backend
+        if key != entry.cache_key().digest_in(&entry.namespace) {
+            anyhow::bail!(
+                "Bad key on line {} of the bundle: {:?} isn't the key of its entry",
+                n + 2,
+                key
+            );
+        }
+        entries.push(StoredEntry {
+            key,
+            bytes: line.len() as u64,
+            entry,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// An entry for `query`, under its own key
+    fn stored(query: &str, response: &str) -> StoredEntry {
+        let entry = CacheEntry {
+            version: CACHE_VERSION,
+            namespace: "research".to_string(),
+            query: query.to_string(),
+            request: query.to_string(),
+            provider: "ollama".to_string(),
+            model: String::new(),
+            temperature: None,
+            max_tokens: None,
+            task_type: "research".to_string(),
+            template: String::new(),
+            strategy: String::new(),
+            response: response.to_string(),
+            created_at: 1_700_000_000,
+            accessed_at: 1_700_000_100,
+            ttl_secs: 86_400,
+            embedding: None,
+            files: Vec::new(),
+        };
+        StoredEntry {
+            key: entry.cache_key().digest_in(&entry.namespace),
+            bytes: 0,
+            entry,
+        }
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("answers.bundle");
+
+        let entries = vec![stored("q1", "one"), stored("q2", "two")];
+        write_bundle(&path, &entries).unwrap();
+        let read = read_bundle(&path).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].key, entries[1].key);
+        assert_eq!(read[1].entry.response, "two");
+        assert_eq!(read[1].entry.namespace, "research");
+        assert_eq!((read[0].entry.created_at, read[0].entry.ttl_secs), (1_700_000_000, 86_400));
+
+        // Plain files and keys that aren't digests are refused
+        fs::write(&path, "{}").unwrap();
+        assert!(read_bundle(&path).is_err());
+        let escape = StoredEntry { key: "../../escape".to_string(), ..stored("q", "x") };
+        write_bundle(&path, &[escape]).unwrap();
+        assert!(read_bundle(&path).is_err());
+
+        // So are entries filed under another request's key, or another namespace's
+        let forged = StoredEntry { key: entries[0].key.clone(), ..stored("q2", "forged") };
+        write_bundle(&path, &[forged]).unwrap();
+        assert!(read_bundle(&path).is_err());
+        let mut moved = stored("q1", "moved");
+        moved.entry.namespace = "default".to_string();
+        write_bundle(&path, &[moved]).unwrap();
+        assert!(read_bundle(&path).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! One JSON file per cache entry
+//!
+//! The original layout: easy to inspect by hand, but listing, stats and
+//! eviction read every file.
+
+use anyhow::Result;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{CacheEntry, CacheStore, Codec, StoredEntry};
+
+/// Entries stored as `<key>.json` in a directory
+pub struct FileStore {
+    dir: PathBuf,
+    codec: Arc<Codec>,
+}
+
+impl FileStore {
+    pub fn new(dir: PathBuf, codec: Arc<Codec>) -> Self {
+        Self { dir, codec }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Keys and paths of the entry files
+    fn files(&self) -> Result<Vec<(String, PathBuf)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != "json") {
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            files.push((key.to_string(), path));
+        }
+        Ok(files)
+    }
+
+    fn read(&self, key: String, path: &Path) -> Option<StoredEntry> {
+        let content = fs::read_to_string(path).ok()?;
+        let entry = self.codec.open(&key, &content).ok()?;
+        Some(StoredEntry {
+            key,
+            bytes: content.len() as u64,
+            entry,
+        })
+    }
+}
+
+impl CacheStore for FileStore {
+    fn load(&self, key: &str) -> Result<Option<StoredEntry>> {
+        Ok(self.read(key.to_string(), &self.path(key)))
+    }
+
+    fn save(&self, key: &str, entry: &CacheEntry) -> Result<()> {
+        // Write beside the entry and rename over it, so readers never see half a file
+        let path = self.path(key);
+        let temp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        let data = if self.codec.is_plain() {
+            serde_json::to_string_pretty(entry)?
+        } else {
+            self.codec.seal(key, entry)?
+        };
+        fs::write(&temp, data)?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    fn touch(&self, key: &str, accessed_at: u64) -> Result<()> {
+        if let Some(mut stored) = self.load(key)? {
+            stored.entry.accessed_at = accessed_at;
+            self.save(key, &stored.entry)?;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<bool> {
+        match fs::remove_file(self.path(key)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn entries(&self) -> Result<Vec<StoredEntry>> {
+        Ok(self
+            .files()?
+            .into_iter()
+            .filter_map(|(key, path)| self.read(key, &path))
+            .collect())
+    }
+
+    fn raw_entries(&self) -> Result<Vec<(String, String)>> {
+        let mut raw = Vec::new();
+        for (key, path) in self.files()? {
+            raw.push((key, fs::read_to_string(&path)?));
+        }
+        Ok(raw)
+    }
+
+    fn clear(&self) -> Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        let mut count = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                fs::remove_file(&path)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn location(&self) -> &Path {
+        &self.dir
+    }
+}
@@ -0,0 +1,1492 @@
+//! Response cache for repeated queries
+//!
+//! Cache with TTL support to avoid redundant API calls. Entries live in a
+//! single SQLite file by default, or one JSON file each (`[cache] backend`).
+//!
+//! Entries are keyed by a SHA-256 over everything that shapes the answer: the
+//! rendered prompt, provider, model settings, task type, prompt template and
+//! sampling strategy. Changing any of them is a cache miss, unless the opt-in
+//! semantic layer finds a close enough earlier request. Files pulled in with
+//! `@path` are keyed by content hash and rechecked on every lookup, so editing
+//! one invalidates answers about it.
+//!
+//! In offline mode the cache answers alone and a miss is an error; in record
+//! mode every answer is fetched fresh and written, so a cache directory can
+//! serve as a fixture store for scripts.
+//!
+//! Namespaces partition the cache, e.g. per project. A namespace's entries
+//! can be exported to a compressed bundle and imported on another machine.
+//!
+//! Entries can be compressed and encrypted at rest (`[cache] compress`,
+//! `[cache.encryption]`); stores hold whatever the `Codec` produces.
+
+mod bundle;
+mod files;
+mod seal;
+mod semantic;
+mod sqlite;
+
+pub use bundle::*;
+pub use files::*;
+pub use seal::*;
+pub use semantic::*;
+pub use sqlite::*;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{CacheBackend, EncryptionConfig, SemanticCacheConfig};
+use crate::llm::ModelSettings;
+
+/// Version of the key scheme and entry format
+///
+/// Bumping it invalidates every existing entry the next time the cache opens.
+pub const CACHE_VERSION: u32 = 2;
+
+/// File in the cache directory recording `CACHE_VERSION`
+const VERSION_FILE: &str = "VERSION";
+
+/// File in the cache directory marking that plain entries have been encrypted
+const SEALED_FILE: &str = "SEALED";
+
+/// Namespace used unless configured or given with `--cache-ns`
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Everything that decides a response, hashed into the cache key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CacheKey {
+    /// The prompt exactly as sent to the provider
+    pub prompt: String,
+    pub provider: String,
+    pub settings: ModelSettings,
+    pub task_type: String,
+    /// Name and content hash of the prompt template
+    pub template: String,
+    /// `single`, `vote-N` or `best-of-N`
+    pub strategy: String,
+    /// Files the request referenced, with their content hashes
+    pub files: Vec<FileRef>,
+}
+
+impl CacheKey {
+    /// The key for a request that referenced `files`
+    pub fn with_files(mut self, files: Vec<FileRef>) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Hex SHA-256 of the key's fields, in the default namespace
+    #[cfg(test)]
+    pub fn digest(&self) -> String {
+        self.digest_in(DEFAULT_NAMESPACE)
+    }
+
+    /// Hex SHA-256 of the key's fields within `namespace`
+    ///
+    /// Each field is length-prefixed so values can't run into each other.
+    /// The default namespace adds nothing, so its keys predate namespaces.
+    pub fn digest_in(&self, namespace: &str) -> String {
+        self.hash(namespace, Some(&self.prompt))
+    }
+
+    /// Hex SHA-256 of every field but the prompt within `namespace`
+    ///
+    /// Requests that share it may share an answer when their prompts are
+    /// similar enough.
+    pub fn setup_digest_in(&self, namespace: &str) -> String {
+        self.hash(namespace, None)
+    }
+
+    fn hash(&self, namespace: &str, prompt: Option<&str>) -> String {
+        let temperature = self.settings.temperature.map(|t| t.to_bits().to_string());
+        let max_tokens = self.settings.max_tokens.map(|n| n.to_string());
+        let fields = [
+            &self.provider,
+            &self.settings.model,
+            temperature.as_deref().unwrap_or("-"),
+            max_tokens.as_deref().unwrap_or("-"),
+            &self.task_type,
+            &self.template,
+            &self.strategy,
+        ];
+
+        let mut hasher = Sha256::new();
+        let scheme = if prompt.is_some() { "cache" } else { "setup" };
+        hasher.update(format!("workyterm-{}-v{}\0", scheme, CACHE_VERSION));
+        if namespace != DEFAULT_NAMESPACE {
+            hasher.update(b"ns\0");
+            hasher.update((namespace.len() as u64).to_le_bytes());
+            hasher.update(namespace.as_bytes());
+        }
+        for field in prompt.into_iter().chain(fields) {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+        // Like namespaces, files only extend keys that have them
+        for file in &self.files {
+            hasher.update(b"file\0");
+            for field in [&file.path, &file.sha256] {
+                hasher.update((field.len() as u64).to_le_bytes());
+                hasher.update(field.as_bytes());
+            }
+        }
+        hex(&hasher.finalize())
+    }
+}
+
+/// A file an `@path` reference pulled into a request, and what it held
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRef {
+    /// The path as referenced, relative to the working directory
+    pub path: String,
+    /// Hex SHA-256 of the content that was read
+    pub sha256: String,
+}
+
+impl FileRef {
+    pub fn new(path: &str, content: &[u8]) -> Self {
+        Self {
+            path: path.to_string(),
+            sha256: hex(&Sha256::digest(content)),
+        }
+    }
+
+    /// Whether the file still holds the content that was read
+    pub fn is_current(&self) -> bool {
+        fs::read(&self.path).is_ok_and(|content| hex(&Sha256::digest(content)) == self.sha256)
+    }
+}
+
+/// Lowercase hex of some bytes
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Cache entry with metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Key scheme the entry was written with; entries from before versioning read as 0
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    /// The rendered prompt
+    pub query: String,
+    /// What the user asked, before templates and file expansion
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub request: String,
+    #[serde(default)]
+    pub provider: String,
+    pub model: String,
+    /// Sampling settings the response was generated with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub task_type: String,
+    /// Template version and sampling strategy that produced the response
+    #[serde(default)]
+    pub template: String,
+    #[serde(default)]
+    pub strategy: String,
+    pub response: String,
+    pub created_at: u64,
+    /// Last time the entry was read or written; 0 for entries never read
+    #[serde(default)]
+    pub accessed_at: u64,
+    pub ttl_secs: u64,
+    /// The request's embedding, when the semantic cache is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Embedding>,
+    /// Files the request referenced; the entry is stale once any changes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileRef>,
+}
+
+/// How a cached answer matched the request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HitKind {
+    /// Same key
+    Exact,
+    /// A similar earlier request, with its cosine similarity
+    Semantic(f32),
+    /// The same request answered through another provider or setup (offline only)
+    Request,
+}
+
+/// Whether the cache may be bypassed or written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Answer from the cache when possible, store fresh answers
+    #[default]
+    Normal,
+    /// Only answer from the cache, expired entries included; never write
+    Offline,
+    /// Never answer from the cache, always store fresh answers
+    Record,
+}
+
+/// An offline request that has no cached answer
+#[derive(Debug, thiserror::Error)]
+#[error("No cached answer for this request (offline)")]
+pub struct CacheMiss;
+
+/// A cached answer
+#[derive(Debug, Clone)]
+pub struct CacheHit {
+    pub response: String,
+    pub kind: HitKind,
+}
+
+impl CacheEntry {
+    /// Check if entry has expired
+    pub fn is_expired(&self) -> bool {
+        now_secs() > self.created_at.saturating_add(self.ttl_secs)
+    }
+
+    /// Whether the entry was produced the way `key` would produce an answer:
+    /// same provider, model settings, task type, template and strategy
+    pub fn same_setup(&self, key: &CacheKey) -> bool {
+        self.provider == key.provider
+            && self.model == key.settings.model
+            && self.temperature == key.settings.temperature
+            && self.max_tokens == key.settings.max_tokens
+            && self.task_type == key.task_type
+            && self.template == key.template
+            && self.strategy == key.strategy
+    }
+
+    /// The key of the request the entry answers
+    pub fn cache_key(&self) -> CacheKey {
+        CacheKey {
+            prompt: self.query.clone(),
+            provider: self.provider.clone(),
+            settings: ModelSettings {
+                model: self.model.clone(),
+                temperature: self.temperature,
+                max_tokens: self.max_tokens,
+            },
+            task_type: self.task_type.clone(),
+            template: self.template.clone(),
+            strategy: self.strategy.clone(),
+            files: self.files.clone(),
+        }
+    }
+
+    /// `CacheKey::setup_digest_in` for the key the entry answers; `None` for
+    /// entries written under another key scheme
+    pub fn setup_digest(&self) -> Option<String> {
+        (self.version == CACHE_VERSION).then(|| self.cache_key().setup_digest_in(&self.namespace))
+    }
+
+    /// Whether every referenced file is unchanged since the answer was cached
+    pub fn files_current(&self) -> bool {
+        self.files.iter().all(FileRef::is_current)
+    }
+}
+
+fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.to_string()
+}
+
+/// Seconds since the Unix epoch
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// An entry together with its key and size, as held by a store
+#[derive(Debug)]
+pub struct StoredEntry {
+    /// The entry's key digest
+    pub key: String,
+    /// Size in the store
+    pub bytes: u64,
+    pub entry: CacheEntry,
+}
+
+impl StoredEntry {
+    /// When the entry was last read or written, for LRU eviction
+    pub fn last_used(&self) -> u64 {
+        self.entry.accessed_at.max(self.entry.created_at)
+    }
+}
+
+/// Where cache entries live
+///
+/// Stores only keep entries; expiry, versions and limits are decided by
+/// `ResponseCache`. The provided methods work from `entries()`; indexed
+/// stores override them.
+pub trait CacheStore: Send + Sync {
+    /// The entry stored under `key`
+    fn load(&self, key: &str) -> Result<Option<StoredEntry>>;
+
+    /// Store an entry, replacing any under the same key
+    fn save(&self, key: &str, entry: &CacheEntry) -> Result<()>;
+
+    /// Record that an entry was read at `accessed_at`
+    fn touch(&self, key: &str, accessed_at: u64) -> Result<()>;
+
+    /// Remove an entry; `false` if there was none
+    fn delete(&self, key: &str) -> Result<bool>;
+
+    /// Every stored entry
+    fn entries(&self) -> Result<Vec<StoredEntry>>;
+
+    /// Every key with its stored text, including entries that don't decode
+    fn raw_entries(&self) -> Result<Vec<(String, String)>>;
+
+    /// Remove every entry, returning how many there were
+    fn clear(&self) -> Result<usize>;
+
+    /// Keys and embeddings of the entries whose setup digest is `setup`;
+    /// only live entries unless `include_expired`
+    fn embeddings(&self, setup: &str, include_expired: bool) -> Result<Vec<(String, Embedding)>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|stored| include_expired || !stored.entry.is_expired())
+            .filter(|stored| stored.entry.setup_digest().as_deref() == Some(setup))
+            .filter_map(|stored| Some((stored.key, stored.entry.embedding?)))
+            .collect())
+    }
+
+    /// The file or directory holding the entries
+    fn location(&self) -> &Path;
+
+    /// Problems with the store itself, beyond single entries
+    fn integrity_check(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Keys starting with `prefix`
+    fn keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .map(|stored| stored.key)
+            .filter(|key| key.starts_with(prefix))
+            .collect())
+    }
+
+    /// Entry count, expired entries and size
+    fn stats(&self) -> Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for stored in self.entries()? {
+            stats.total_entries += 1;
+            stats.total_bytes += stored.bytes as usize;
+            if stored.entry.is_expired() {
+                stats.expired_entries += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Remove expired entries, returning how many
+    fn remove_expired(&self) -> Result<usize> {
+        let mut count = 0;
+        for stored in self.entries()? {
+            if stored.entry.is_expired() && self.delete(&stored.key)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Drop least recently used entries until both limits hold (0 = no limit)
+    fn evict(&self, max_entries: usize, max_bytes: u64) -> Result<usize> {
+        let mut entries = self.entries()?;
+        entries.sort_by(|a, b| {
+            a.last_used()
+                .cmp(&b.last_used())
+                .then(a.entry.created_at.cmp(&b.entry.created_at))
+                .then(a.key.cmp(&b.key))
+        });
+
+        let mut count = entries.len();
+        let mut bytes: u64 = entries.iter().map(|e| e.bytes).sum();
+        let mut evicted = 0;
+        for stored in entries {
+            let over_count = max_entries > 0 && count > max_entries;
+            let over_bytes = max_bytes > 0 && bytes > max_bytes;
+            if !over_count && !over_bytes {
+                break;
+            }
+            self.delete(&stored.key)?;
+            count -= 1;
+            bytes -= stored.bytes;
+            evicted += 1;
+        }
+        Ok(evicted)
+    }
+}
+
+/// Response cache over a `CacheStore`
+pub struct ResponseCache {
+    cache_dir: PathBuf,
+    default_ttl: Duration,
+    enabled: bool,
+    /// Most entries kept before the least recently used are evicted (0 = no limit)
+    max_entries: usize,
+    /// Most bytes kept before the least recently used are evicted (0 = no limit)
+    max_bytes: u64,
+    backend: CacheBackend,
+    store: Box<dyn CacheStore>,
+    /// How entries are encoded at rest
+    codec: Arc<Codec>,
+    /// Near-duplicate matching, when enabled
+    semantic: Option<SemanticCache>,
+    mode: CacheMode,
+    /// Partition reads and writes go to
+    namespace: String,
+}
+
+impl ResponseCache {
+    /// Create a new cache instance
+    pub fn new(enabled: bool, ttl_secs: u64) -> Self {
+        let cache_dir = dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("/tmp"))
+            .join("workyterm");
+        Self::with_dir(cache_dir, enabled, ttl_secs)
+    }
+
+    /// Create a cache stored in `cache_dir`
+    pub fn with_dir(cache_dir: PathBuf, enabled: bool, ttl_secs: u64) -> Self {
+        let backend = CacheBackend::default();
+        let codec = Arc::new(Codec::plain());
+        Self {
+            store: open_store(backend, &cache_dir, codec.clone()),
+            codec,
+            cache_dir,
+            default_ttl: Duration::from_secs(ttl_secs),
+            enabled,
+            max_entries: 0,
+            max_bytes: 0,
+            backend,
+            semantic: None,
+            mode: CacheMode::Normal,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+        }
+    }
+
+    /// Cap the cache size; 0 leaves a dimension unlimited
+    pub fn with_limits(mut self, max_entries: usize, max_bytes: u64) -> Self {
+        self.max_entries = max_entries;
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Keep entries in another kind of store
+    pub fn with_backend(mut self, backend: CacheBackend) -> Self {
+        self.store = open_store(backend, &self.cache_dir, self.codec.clone());
+        self.backend = backend;
+        self
+    }
+
+    /// Compress and/or encrypt entries written from now on
+    ///
+    /// Fails if encryption is on and its key can't be loaded or derived.
+    pub fn with_sealing(mut self, compress: bool, encryption: &EncryptionConfig) -> Result<Self> {
+        self.codec = Arc::new(Codec::from_config(compress, encryption, &self.cache_dir)?);
+        if self.cache_dir.join(SEALED_FILE).exists() {
+            self.codec.require_encrypted();
+        }
+        self.store = open_store(self.backend, &self.cache_dir, self.codec.clone());
+        Ok(self)
+    }
+
+    /// Also match similar requests by embedding, if enabled in `config`
+    pub fn with_semantic(mut self, config: &SemanticCacheConfig) -> Self {
+        self.semantic = SemanticCache::from_config(config);
+        self
+    }
+
+    /// Replay or record instead of the usual read-through caching
+    pub fn with_mode(mut self, mode: CacheMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Read and write entries in `namespace` only
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    /// Initialize cache directory, dropping entries from older key schemes,
+    /// moving per-file entries into an indexed store and encrypting plain
+    /// entries once encryption is on
+    pub fn init(&self) -> Result<()> {
+        if self.enabled {
+            fs::create_dir_all(&self.cache_dir)?;
+            self.migrate()?;
+            self.import_files()?;
+            self.seal_plain_entries()?;
+        }
+        Ok(())
+    }
+
+    /// Encrypt the entries stored in plain text, the first time the cache
+    /// opens with encryption on
+    ///
+    /// A marker file records that it ran; from then on, entries that aren't
+    /// encrypted are refused. Opening without encryption removes it, so
+    /// turning encryption back on seals what was written in between. Entries
+    /// that don't open are left for `verify` to report. Returns how many were
+    /// encrypted.
+    pub fn seal_plain_entries(&self) -> Result<usize> {
+        let marker = self.cache_dir.join(SEALED_FILE);
+        if !self.codec.is_encrypting() {
+            if marker.exists() {
+                fs::remove_file(&marker)?;
+            }
+            return Ok(0);
+        }
+        if marker.exists() {
+            return Ok(0);
+        }
+
+        let mut sealed = 0;
+        for (key, data) in self.store.raw_entries()? {
+            if Codec::is_encrypted(&data) {
+                continue;
+            }
+            if let Some(stored) = self.store.load(&key)? {
+                self.store.save(&key, &stored.entry)?;
+                sealed += 1;
+            }
+        }
+        fs::write(&marker, "")?;
+        self.codec.require_encrypted();
+        Ok(sealed)
+    }
+
+    /// Remove entries written under an older `CACHE_VERSION`
+    ///
+    /// Old keys didn't cover the rendered prompt or model settings, so they
+    /// can't be recomputed; the entries are invalidated rather than rekeyed.
+    /// Returns how many were removed.
+    pub fn migrate(&self) -> Result<usize> {
+        let version_path = self.cache_dir.join(VERSION_FILE);
+        let current = fs::read_to_string(&version_path)
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok());
+        if current == Some(CACHE_VERSION) {
+            return Ok(0);
+        }
+
+        let mut removed = FileStore::new(self.cache_dir.clone(), self.codec.clone()).clear()?;
+        if self.backend != CacheBackend::Files {
+            removed += self.store.clear()?;
+        }
+        fs::write(&version_path, CACHE_VERSION.to_string())?;
+        Ok(removed)
+    }
+
+    /// Move entries from the one-file-per-entry layout into this cache's store
+    ///
+    /// Returns how many were moved. Safe to run from several processes at once.
+    pub fn import_files(&self) -> Result<usize> {
+        if self.backend == CacheBackend::Files {
+            return Ok(0);
+        }
+
+        let files = FileStore::new(self.cache_dir.clone(), self.codec.clone());
+        let entries = files.entries()?;
+        for stored in &entries {
+            self.store.save(&stored.key, &stored.entry)?;
+            files.delete(&stored.key)?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Look up a cached response, recording the access for LRU eviction
+    ///
+    /// Offline, expired entries still answer: recorded fixtures don't age out.
+    pub fn get(&self, key: &CacheKey) -> Option<String> {
+        if !self.enabled || self.mode == CacheMode::Record {
+            return None;
+        }
+
+        let key = key.digest_in(&self.namespace);
+        let entry = self.store.load(&key).ok()??.entry;
+
+        if entry.version != CACHE_VERSION {
+            let _ = self.store.delete(&key);
+            return None;
+        }
+        if entry.is_expired() && self.mode != CacheMode::Offline {
+            // Clean up expired entry
+            let _ = self.store.delete(&key);
+            return None;
+        }
+        if !entry.files_current() {
+            if self.mode != CacheMode::Offline {
+                let _ = self.store.delete(&key);
+            }
+            return None;
+        }
+
+        let now = now_secs();
+        if entry.accessed_at < now {
+            let _ = self.store.touch(&key, now);
+        }
+
+        Some(entry.response)
+    }
+
+    /// Find a cached answer: the exact key, then a similar request, then
+    /// (offline) the same request recorded through another provider
+    ///
+    /// `request` is what the user asked, before templates and file
+    /// expansion; it is what the semantic layer compares.
+    pub async fn lookup(&self, key: &CacheKey, request: &str) -> Option<CacheHit> {
+        if let Some(response) = self.get(key) {
+            return Some(CacheHit {
+                response,
+                kind: HitKind::Exact,
+            });
+        }
+        if !self.enabled || self.mode == CacheMode::Record {
+            return None;
+        }
+
+        if let Some(hit) = self.lookup_similar(key, request).await {
+            return Some(hit);
+        }
+        if self.mode != CacheMode::Offline {
+            return None;
+        }
+
+        // Which providers are installed decides routing, and so the key; a
+        // machine replaying fixtures may not have the one that recorded them
+        let entries = self.candidates().ok()?;
+        let stored = same_request(key, request, &entries)?;
+        let _ = self.store.touch(&stored.key, now_secs());
+        Some(CacheHit {
+            response: stored.entry.response.clone(),
+            kind: HitKind::Request,
+        })
+    }
+
+    async fn lookup_similar(&self, key: &CacheKey, request: &str) -> Option<CacheHit> {
+        let semantic = self.semantic.as_ref()?;
+        let embedding = semantic.embed(request).await.ok()?;
+        let offline = self.mode == CacheMode::Offline;
+        // Only entries close enough to win are loaded in full
+        let setup = key.setup_digest_in(&self.namespace);
+        let entries: Vec<StoredEntry> = self
+            .store
+            .embeddings(&setup, offline)
+            .ok()?
+            .into_iter()
+            .filter(|(_, cached)| semantic.is_close(&embedding, cached))
+            .filter_map(|(stored_key, _)| self.store.load(&stored_key).ok().flatten())
+            .filter(|stored| stored.entry.files_current())
+            .collect();
+        let (stored, similarity) = semantic.best_match(&embedding, key, &entries, offline)?;
+        let _ = self.store.touch(&stored.key, now_secs());
+        Some(CacheHit {
+            response: stored.entry.response.clone(),
+            kind: HitKind::Semantic(similarity),
+        })
+    }
+
+    /// Store a response in cache, then evict down to the size limits
+    ///
+    /// With the semantic cache on, the request's embedding is kept with it;
+    /// if Ollama can't embed it, the entry still matches exactly.
+    pub async fn store(&self, key: &CacheKey, request: &str, response: &str) -> Result<()> {
+        if !self.enabled || self.mode == CacheMode::Offline {
+            return Ok(());
+        }
+
+        let embedding = match self.semantic {
+            Some(ref semantic) => semantic.embed(request).await.ok(),
+            None => None,
+        };
+        self.save(key, request, response, embedding)
+    }
+
+    fn save(
+        &self,
+        key: &CacheKey,
+        request: &str,
+        response: &str,
+        embedding: Option<Embedding>,
+    ) -> Result<()> {
+        let now = now_secs();
+        let entry = CacheEntry {
+            version: CACHE_VERSION,
+            namespace: self.namespace.clone(),
+            query: key.prompt.clone(),
+            request: request.to_string(),
+            provider: key.provider.clone(),
+            model: key.settings.model.clone(),
+            temperature: key.settings.temperature,
+            max_tokens: key.settings.max_tokens,
+            task_type: key.task_type.clone(),
+            template: key.template.clone(),
+            strategy: key.strategy.clone(),
+            response: response.to_string(),
+            created_at: now,
+            accessed_at: now,
+            ttl_secs: self.default_ttl.as_secs(),
+            embedding,
+            files: key.files.clone(),
+        };
+        self.store.save(&key.digest_in(&self.namespace), &entry)?;
+
+        self.evict()?;
+        Ok(())
+    }
+
+    /// Every stored entry
+    pub fn entries(&self) -> Result<Vec<StoredEntry>> {
+        self.store.entries()
+    }
+
+    /// Stored entries in this cache's namespace
+    fn namespace_entries(&self) -> Result<Vec<StoredEntry>> {
+        let mut entries = self.store.entries()?;
+        entries.retain(|stored| stored.entry.namespace == self.namespace);
+        Ok(entries)
+    }
+
+    /// Entries in this namespace that may answer a request that isn't an
+    /// exact match: none about files that have changed since
+    fn candidates(&self) -> Result<Vec<StoredEntry>> {
+        let mut entries = self.namespace_entries()?;
+        entries.retain(|stored| stored.entry.files_current());
+        Ok(entries)
+    }
+
+    /// Write this namespace's entries, or every entry with `all`, to a
+    /// bundle at `path`; returns how many were written
+    ///
+    /// Bundles aren't encrypted, so an encrypted cache only exports with
+    /// `plaintext`.
+    pub fn export(&self, path: &Path, all: bool, plaintext: bool) -> Result<usize> {
+        if self.codec.is_encrypting() && !plaintext {
+            anyhow::bail!(
+                "The cache is encrypted but bundles are not; \
+                 pass --plaintext to export its entries in the clear"
+            );
+        }
+        let entries = if all { self.store.entries()? } else { self.namespace_entries()? };
+        write_bundle(path, &entries)?;
+        Ok(entries.len())
+    }
+
+    /// Merge the entries of the bundle at `path` into the cache
+    ///
+    /// Entries keep their namespace, creation time and TTL. When both sides
+    /// have an entry, the newer one wins.
+    pub fn import(&self, path: &Path) -> Result<ImportSummary> {
+        let entries = read_bundle(path)?;
+        fs::create_dir_all(&self.cache_dir)?;
+        self.migrate()?;
+
+        let mut summary = ImportSummary::default();
+        for incoming in entries {
+            match self.store.load(&incoming.key)? {
+                Some(cached) if cached.entry.created_at >= incoming.entry.created_at => {
+                    summary.kept += 1;
+                }
+                cached => {
+                    self.store.save(&incoming.key, &incoming.entry)?;
+                    if cached.is_some() {
+                        summary.updated += 1;
+                    } else {
+                        summary.added += 1;
+                    }
+                }
+            }
+        }
+        self.evict()?;
+        Ok(summary)
+    }
+
+    /// The stored entry whose key starts with `prefix`
+    pub fn find(&self, prefix: &str) -> Result<StoredEntry> {
+        let keys = if prefix.is_empty() {
+            Vec::new()
+        } else {
+            self.store.keys_with_prefix(prefix)?
+        };
+        match keys.as_slice() {
+            [] => Err(anyhow::anyhow!("No cache entry matches '{}'", prefix)),
+            [key] => self
+                .store
+                .load(key)?
+                .ok_or_else(|| anyhow::anyhow!("No cache entry matches '{}'", prefix)),
+            _ => Err(anyhow::anyhow!(
+                "'{}' matches {} entries; use more of the key",
+                prefix,
+                keys.len()
+            )),
+        }
+    }
+
+    /// Remove the entry whose key starts with `prefix`, returning its full key
+    pub fn remove(&self, prefix: &str) -> Result<String> {
+        let stored = self.find(prefix)?;
+        self.store.delete(&stored.key)?;
+        Ok(stored.key)
+    }
+
+    /// Clear all cached entries
+    pub fn clear(&self) -> Result<usize> {
+        self.store.clear()
+    }
+
+    /// Remove expired entries, then evict down to the size limits
+    pub fn prune(&self) -> Result<usize> {
+        Ok(self.store.remove_expired()? + self.evict()?)
+    }
+
+    /// Drop least recently used entries until both size limits hold
+    fn evict(&self) -> Result<usize> {
+        if self.max_entries == 0 && self.max_bytes == 0 {
+            return Ok(0);
+        }
+        self.store.evict(self.max_entries, self.max_bytes)
+    }
+
+    /// Check every entry decodes, authenticates and is current, and that the
+    /// store itself is intact
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let mut report = VerifyReport {
+            store_problems: self.store.integrity_check()?,
+            ..VerifyReport::default()
+        };
+        for (key, data) in self.store.raw_entries()? {
+            report.checked += 1;
+            match self.codec.open(&key, &data) {
+                Ok(entry) if entry.version != CACHE_VERSION => report
+                    .failed
+                    .push((key, format!("written under cache version {}", entry.version))),
+                Ok(_) if Codec::is_encrypted(&data) => report.encrypted += 1,
+                Ok(_) => {}
+                Err(e) => report.failed.push((key, e.to_string())),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Seal every entry under a new key, then forget the old keys
+    ///
+    /// Plain entries are encrypted too. Entries that don't open are left as
+    /// they are; `verify` reports them. Returns the new key's id and how
+    /// many entries were resealed.
+    pub fn rotate_key(&self) -> Result<(String, usize)> {
+        if !self.codec.is_encrypting() {
+            anyhow::bail!("Cache encryption is off; enable [cache.encryption] first");
+        }
+        let key_id = self.codec.begin_rotation()?;
+        let entries = self.store.entries()?;
+        for stored in &entries {
+            self.store.save(&stored.key, &stored.entry)?;
+        }
+        self.codec.finish_rotation()?;
+        Ok((key_id, entries.len()))
+    }
+
+    /// Get cache statistics
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            max_entries: self.max_entries,
+            max_bytes: self.max_bytes,
+            ..self.store.stats().unwrap_or_default()
+        }
+    }
+
+    /// Turn caching on or off, opening the cache directory if needed
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<()> {
+        self.enabled = enabled;
+        self.init()
+    }
+
+    /// Whether entries are encrypted at rest
+    pub fn is_encrypted(&self) -> bool {
+        self.codec.is_encrypting()
+    }
+
+    /// Partition this cache reads and writes
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// How the cache is being used
+    pub fn mode(&self) -> CacheMode {
+        self.mode
+    }
+
+    /// Check if caching is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Where entries are stored
+    pub fn location(&self) -> &Path {
+        self.store.location()
+    }
+}
+
+/// The most recently written entry for the same request, task type and
+/// referenced files, preferring the key's provider
+fn same_request<'a>(
+    key: &CacheKey,
+    request: &str,
+    entries: &'a [StoredEntry],
+) -> Option<&'a StoredEntry> {
+    let request = normalize_request(request);
+    entries
+        .iter()
+        .filter(|stored| stored.entry.version == CACHE_VERSION)
+        .filter(|stored| stored.entry.task_type == key.task_type)
+        .filter(|stored| stored.entry.files == key.files)
+        .filter(|stored| normalize_request(&stored.entry.request) == request)
+        .max_by_key(|stored| (stored.entry.provider == key.provider, stored.entry.created_at))
+}
+
+/// Collapse whitespace, so reflowed requests still match
+fn normalize_request(request: &str) -> String {
+    request.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The store for a backend, kept in `cache_dir`
+fn open_store(backend: CacheBackend, cache_dir: &Path, codec: Arc<Codec>) -> Box<dyn CacheStore> {
+    match backend {
+        CacheBackend::Sqlite => Box::new(SqliteStore::new(cache_dir.join(DB_FILE), codec)),
+        CacheBackend::Files => Box::new(FileStore::new(cache_dir.to_path_buf(), codec)),
+    }
+}
+
+/// Cache statistics
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub total_entries: usize,
+    pub expired_entries: usize,
+    pub total_bytes: usize,
+    /// Configured limits (0 = no limit)
+    pub max_entries: usize,
+    pub max_bytes: u64,
+}
+
+impl CacheStats {
+    pub fn active_entries(&self) -> usize {
+        self.total_entries.saturating_sub(self.expired_entries)
+    }
+}
+
+/// Result of `ResponseCache::verify`
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    /// Entries that opened and were encrypted
+    pub encrypted: usize,
+    /// Entries that didn't decode, authenticate or match the cache version,
+    /// with the reason
+    pub failed: Vec<(String, String)>,
+    /// Problems the store found with itself
+    pub store_problems: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty() && self.store_problems.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use std::thread::sleep;
+
+    fn key(prompt: &str) -> CacheKey {
+        CacheKey {
+            prompt: prompt.to_string(),
+            provider: "ollama".to_string(),
+            settings: ModelSettings {
+                model: "llama3.2".to_string(),
+                temperature: Some(0.7),
+                max_tokens: Some(4096),
+            },
+            task_type: "general".to_string(),
+            template: "builtin@0123456789ab".to_string(),
+            strategy: "single".to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    /// A cache under `dir`, which removes it when dropped
+    fn temp_cache(dir: &TempDir, backend: CacheBackend) -> ResponseCache {
+        ResponseCache::with_dir(dir.path().join("cache"), true, 3600).with_backend(backend)
+    }
+
+    const BACKENDS: [CacheBackend; 2] = [CacheBackend::Sqlite, CacheBackend::Files];
+
+    #[tokio::test]
+    async fn test_cache_disabled() {
+        let cache = ResponseCache::new(false, 3600);
+        assert!(cache.get(&key("test")).is_none());
+        assert!(cache.store(&key("test"), "test", "response").await.is_ok());
+        assert!(cache.get(&key("test")).is_none()); // Still none, disabled
+    }
+
+    #[tokio::test]
+    async fn test_toggle_enabled() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("cache");
+        let mut cache = ResponseCache::with_dir(dir.clone(), false, 3600);
+        cache.init().unwrap();
+        assert!(!dir.exists());
+
+        // Turning the cache on mid-session opens it
+        cache.set_enabled(true).unwrap();
+        assert!(dir.join(VERSION_FILE).exists());
+        cache.store(&key("q"), "q", "a").await.unwrap();
+        assert!(cache.lookup(&key("q"), "q").await.is_some());
+
+        cache.set_enabled(false).unwrap();
+        assert!(cache.lookup(&key("q"), "q").await.is_none());
+        assert_eq!(cache.stats().total_entries, 1);
+    }
+
+    #[test]
+    fn test_cache_key_generation() {
+        let digest = key("hello").digest();
+        assert_eq!(digest, key("hello").digest());
+        assert_eq!(digest.len(), 64);
+        assert!(digest.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // Every field that shapes the answer changes the key
+        let variants = [
+            key("world"),
+            CacheKey { provider: "openai".to_string(), ..key("hello") },
+            CacheKey { task_type: "code".to_string(), ..key("hello") },
+            CacheKey { template: "code@ba9876543210".to_string(), ..key("hello") },
+            CacheKey { strategy: "vote-3".to_string(), ..key("hello") },
+        ];
+        for variant in &variants {
+            assert_ne!(variant.digest(), digest, "{:?}", variant);
+        }
+        let mut warmer = key("hello");
+        warmer.settings.temperature = Some(0.9);
+        assert_ne!(warmer.digest(), digest);
+        let mut shorter = key("hello");
+        shorter.settings.max_tokens = None;
+        assert_ne!(shorter.digest(), digest);
+
+        // Length prefixes keep field boundaries apart
+        let a = CacheKey { prompt: "ab".to_string(), provider: "c".to_string(), ..key("") };
+        let b = CacheKey { prompt: "a".to_string(), provider: "bc".to_string(), ..key("") };
+        assert_ne!(a.digest(), b.digest());
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_and_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = temp_cache(&dir, CacheBackend::Sqlite);
+        fs::create_dir_all(&cache.cache_dir).unwrap();
+        // An entry from the old DefaultHasher scheme, with no version file
+        let old = cache.cache_dir.join("00ff00ff00ff00ff.json");
+        let entry = concat!(
+            r#"{"query":"hi","model":"gemini","response":"old","#,
+            r#""created_at":0,"ttl_secs":1}"#
+        );
+        fs::write(&old, entry).unwrap();
+
+        cache.init().unwrap();
+        assert!(!old.exists());
+        assert_eq!(cache.migrate().unwrap(), 0);
+
+        cache.store(&key("hi"), "hi", "new").await.unwrap();
+        assert_eq!(cache.get(&key("hi")).as_deref(), Some("new"));
+        assert!(cache.get(&key("other")).is_none());
+
+        // Reopening under a newer scheme invalidates what's there
+        fs::write(cache.cache_dir.join(VERSION_FILE), "1").unwrap();
+        assert_eq!(cache.migrate().unwrap(), 1);
+        assert!(cache.get(&key("hi")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_import_from_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = temp_cache(&dir, CacheBackend::Files);
+        cache.init().unwrap();
+        cache.store(&key("kept"), "kept", "from a file").await.unwrap();
+
+        // Switching to SQLite moves the entry into the database
+        let cache = cache.with_backend(CacheBackend::Sqlite);
+        cache.init().unwrap();
+        assert_eq!(cache.get(&key("kept")).as_deref(), Some("from a file"));
+        let files = FileStore::new(cache.cache_dir.clone(), cache.codec.clone());
+        assert!(files.entries().unwrap().is_empty());
+        assert!(cache.location().ends_with(DB_FILE));
+        assert_eq!(cache.import_files().unwrap(), 0);
+    }
+
+    /// Backdate an entry's last use by `secs`
+    fn age(cache: &ResponseCache, key: &CacheKey, secs: u64) {
+        let digest = key.digest();
+        let mut entry = cache.store.load(&digest).unwrap().unwrap().entry;
+        entry.created_at -= secs;
+        entry.accessed_at -= secs;
+        cache.store.save(&digest, &entry).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction() {
+        for backend in BACKENDS {
+            lru_eviction(backend).await;
+        }
+    }
+
+    async fn lru_eviction(backend: CacheBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = temp_cache(&dir, backend).with_limits(2, 0);
+        cache.init().unwrap();
+        cache.store(&key("a"), "a", "A").await.unwrap();
+        age(&cache, &key("a"), 30);
+        cache.store(&key("b"), "b", "B").await.unwrap();
+        age(&cache, &key("b"), 20);
+
+        // Reading "a" makes "b" the least recently used
+        assert_eq!(cache.get(&key("a")).as_deref(), Some("A"));
+        cache.store(&key("c"), "c", "C").await.unwrap();
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("c")).is_some());
+
+        // A byte limit below two entries keeps only the newest
+        let bytes = cache.find(&key("c").digest()).unwrap().bytes;
+        let cache = cache.with_limits(0, bytes + 1);
+        age(&cache, &key("a"), 10);
+        assert_eq!(cache.prune().unwrap(), 1);
+        assert_eq!(cache.stats().total_entries, 1);
+        assert!(cache.get(&key("c")).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_find_and_remove_by_prefix() {
+        for backend in BACKENDS {
+            find_and_remove(backend).await;
+        }
+    }
+
+    async fn find_and_remove(backend: CacheBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = temp_cache(&dir, backend);
+        cache.init().unwrap();
+        cache.store(&key("one"), "one", "1").await.unwrap();
+        cache.store(&key("two"), "two", "2").await.unwrap();
+
+        let digest = key("one").digest();
+        let found = cache.find(&digest[..10]).unwrap();
+        assert_eq!(found.key, digest);
+        assert_eq!(found.entry.response, "1");
+        assert!(cache.find("").is_err());
+        assert!(cache.find("not-a-key").is_err());
+
+        assert_eq!(cache.remove(&digest[..10]).unwrap(), digest);
+        assert!(cache.get(&key("one")).is_none());
+        assert_eq!(cache.entries().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_offline() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("cache");
+        let recorder =
+            ResponseCache::with_dir(dir.clone(), true, 3600).with_mode(CacheMode::Record);
+        recorder.init().unwrap();
+
+        // Recording never reads, so a second answer replaces the first
+        recorder.store(&key("q"), "q", "first").await.unwrap();
+        assert!(recorder.lookup(&key("q"), "q").await.is_none());
+        recorder.store(&key("q"), "q", "second").await.unwrap();
+
+        let replay =
+            ResponseCache::with_dir(dir.clone(), true, 3600).with_mode(CacheMode::Offline);
+        let hit = replay.lookup(&key("q"), "q").await.unwrap();
+        assert_eq!((hit.response.as_str(), hit.kind), ("second", HitKind::Exact));
+        replay.store(&key("other"), "other", "x").await.unwrap();
+        assert_eq!(replay.entries().unwrap().len(), 1);
+
+        // Routed to another provider, the same request still replays offline
+        let elsewhere = CacheKey {
+            prompt: "rendered for gemini".to_string(),
+            provider: "gemini-cli".to_string(),
+            ..key("q")
+        };
+        let hit = replay.lookup(&elsewhere, " q\n").await.unwrap();
+        assert_eq!((hit.response.as_str(), hit.kind), ("second", HitKind::Request));
+        let normal = ResponseCache::with_dir(dir.clone(), true, 3600);
+        assert!(normal.lookup(&elsewhere, "q").await.is_none());
+
+        // Expired fixtures still answer offline; a normal lookup drops them
+        let mut stale = replay.store.load(&key("q").digest()).unwrap().unwrap().entry;
+        stale.created_at = 0;
+        replay.store.save(&key("q").digest(), &stale).unwrap();
+        assert!(replay.get(&key("q")).is_some());
+        assert!(normal.get(&key("q")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_and_bundles() {
+        let laptop_dir = tempfile::tempdir().unwrap();
+        let desktop_dir = tempfile::tempdir().unwrap();
+        let laptop = temp_cache(&laptop_dir, CacheBackend::Sqlite).with_namespace("research");
+        let desktop = temp_cache(&desktop_dir, CacheBackend::Files).with_namespace("research");
+        laptop.init().unwrap();
+
+        // Namespaces don't see each other's entries; the default keeps old keys
+        laptop.store(&key("q"), "q", "laptop").await.unwrap();
+        let default_ns = ResponseCache::with_dir(laptop.cache_dir.clone(), true, 3600);
+        assert!(default_ns.get(&key("q")).is_none());
+        assert_ne!(key("q").digest_in("research"), key("q").digest());
+        default_ns.store(&key("other"), "other", "elsewhere").await.unwrap();
+
+        let bundle = laptop.cache_dir.join("research.bundle");
+        assert_eq!(laptop.export(&bundle, false, false).unwrap(), 1);
+
+        // A fresh import adds; a second import keeps what's there
+        let summary = desktop.import(&bundle).unwrap();
+        assert_eq!(summary, ImportSummary { added: 1, ..ImportSummary::default() });
+        assert_eq!(desktop.get(&key("q")).as_deref(), Some("laptop"));
+        assert_eq!(desktop.import(&bundle).unwrap().kept, 1);
+
+        // The newer side wins, and the TTL travels with the entry
+        let digest = key("q").digest_in("research");
+        let mut newer = laptop.store.load(&digest).unwrap().unwrap().entry;
+        newer.response = "revised".to_string();
+        newer.created_at += 10;
+        newer.ttl_secs = 60;
+        laptop.store.save(&digest, &newer).unwrap();
+        laptop.export(&bundle, false, false).unwrap();
+        assert_eq!(desktop.import(&bundle).unwrap().updated, 1);
+        let merged = desktop.store.load(&digest).unwrap().unwrap().entry;
+        assert_eq!((merged.response.as_str(), merged.ttl_secs), ("revised", 60));
+
+        assert_eq!(laptop.export(&bundle, true, false).unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sealed_entries_verify_and_rotate() {
+        for backend in BACKENDS {
+            sealed_entries(backend).await;
+        }
+    }
+
+    async fn sealed_entries(backend: CacheBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = temp_cache(&dir, backend);
+        plain.init().unwrap();
+        plain.store(&key("old"), "old", "written in the clear").await.unwrap();
+
+        let encryption = EncryptionConfig {
+            enabled: true,
+            key_file: plain.cache_dir.join("cache.key").display().to_string(),
+            passphrase: String::new(),
+        };
+        let sealed = ResponseCache::with_dir(plain.cache_dir.clone(), true, 3600)
+            .with_backend(backend)
+            .with_sealing(true, &encryption)
+            .unwrap();
+        sealed.store(&key("new"), "new", "kept secret").await.unwrap();
+        assert_eq!(sealed.get(&key("old")).as_deref(), Some("written in the clear"));
+        assert_eq!(sealed.get(&key("new")).as_deref(), Some("kept secret"));
+        let raw = sealed.store.raw_entries().unwrap();
+        assert!(raw.iter().all(|(_, data)| !data.contains("kept secret")));
+
+        let report = sealed.verify().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!((report.checked, report.encrypted), (2, 1));
+
+        // The first init with encryption on seals what was written in the clear
+        sealed.init().unwrap();
+        let raw = sealed.store.raw_entries().unwrap();
+        assert!(raw.iter().all(|(_, data)| !data.contains("written in the clear")));
+        assert_eq!(sealed.verify().unwrap().encrypted, 2);
+        assert_eq!(sealed.get(&key("old")).as_deref(), Some("written in the clear"));
+        assert_eq!(sealed.seal_plain_entries().unwrap(), 0);
+
+        // Bundles are plain text, so exporting takes an explicit opt-in
+        let bundle = dir.path().join("sealed.bundle");
+        assert!(sealed.export(&bundle, true, false).is_err());
+        assert!(!bundle.exists());
+        assert_eq!(sealed.export(&bundle, true, true).unwrap(), 2);
+
+        // Rotation seals everything, plain entries included, under the new key
+        let (key_id, resealed) = sealed.rotate_key().unwrap();
+        assert_eq!(resealed, 2);
+        assert_eq!(sealed.codec.key_id(), Some(key_id));
+        assert_eq!(sealed.verify().unwrap().encrypted, 2);
+        assert_eq!(sealed.get(&key("new")).as_deref(), Some("kept secret"));
+
+        // Once sealed, a plain entry planted in the cache is refused, also
+        // after reopening, and verify flags it
+        plain.store(&key("planted"), "planted", "forged answer").await.unwrap();
+        assert!(sealed.get(&key("planted")).is_none());
+        let reopened = ResponseCache::with_dir(plain.cache_dir.clone(), true, 3600)
+            .with_backend(backend)
+            .with_sealing(true, &encryption)
+            .unwrap();
+        assert!(reopened.get(&key("planted")).is_none());
+        assert_eq!(reopened.get(&key("new")).as_deref(), Some("kept secret"));
+        let report = reopened.verify().unwrap();
+        assert_eq!((report.checked, report.encrypted, report.failed.len()), (3, 2, 1));
+        assert!(report.failed[0].1.contains("isn't encrypted"));
+        assert!(reopened.store.delete(&key("planted").digest()).unwrap());
+
+        // Without the key nothing opens, and verify says so
+        let report = plain.verify().unwrap();
+        assert_eq!(report.failed.len(), 2);
+        assert!(plain.get(&key("new")).is_none());
+        assert!(plain.rotate_key().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_referenced_file_changes_invalidate() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = temp_cache(&dir, CacheBackend::Sqlite).with_mode(CacheMode::Offline);
+        cache.init().unwrap();
+        let path = cache.cache_dir.join("main.rs");
+        fs::write(&path, "fn main() {}").unwrap();
+        let file = FileRef::new(&path.display().to_string(), b"fn main() {}");
+        let review = key("review @main.rs").with_files(vec![file.clone()]);
+
+        // The file's hash is part of the key
+        assert_ne!(review.digest(), key("review @main.rs").digest());
+        let writer = ResponseCache::with_dir(cache.cache_dir.clone(), true, 3600);
+        writer.store(&review, "review @main.rs", "looks good").await.unwrap();
+        assert_eq!(cache.get(&review).as_deref(), Some("looks good"));
+
+        // Once the file changes, neither the key nor the request text matches
+        fs::write(&path, "fn main() { panic!() }").unwrap();
+        assert!(!file.is_current());
+        assert!(cache.get(&review).is_none());
+        assert!(cache.lookup(&key("review @main.rs"), "review @main.rs").await.is_none());
+
+        // A normal lookup drops the stale entry
+        assert!(writer.get(&review).is_none());
+        assert_eq!(writer.stats().total_entries, 0);
+    }
+
+    /// A stand-in for Ollama's embeddings endpoint: requests mentioning
+    /// ownership embed close together, anything else far away
+    async fn fake_embeddings() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0; 8192];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let vector =
+                    if request.contains("ownership") { "[1.0, 0.1]" } else { "[0.0, 1.0]" };
+                let body = format!(r#"{{"embedding": {}}}"#, vector);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                     content-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        endpoint
+    }
+
+    #[tokio::test]
+    async fn test_semantic_lookup() {
+        let config = SemanticCacheConfig {
+            enabled: true,
+            endpoint: fake_embeddings().await,
+            threshold: 0.9,
+            ..SemanticCacheConfig::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let cache = temp_cache(&dir, CacheBackend::Sqlite).with_semantic(&config);
+        cache.init().unwrap();
+
+        let asked = "what is rust ownership?";
+        cache.store(&key(asked), asked, "Each value has one owner.").await.unwrap();
+
+        let exact = cache.lookup(&key(asked), asked).await.unwrap();
+        assert_eq!(exact.kind, HitKind::Exact);
+
+        let similar = "What's Rust's ownership model";
+        let hit = cache.lookup(&key(similar), similar).await.unwrap();
+        assert_eq!(hit.response, "Each value has one owner.");
+        assert!(matches!(hit.kind, HitKind::Semantic(s) if s > 0.9 && s < 1.0));
+
+        // Same words, different provider or an unrelated request: no reuse
+        let elsewhere = CacheKey { provider: "gemini-cli".to_string(), ..key(similar) };
+        assert!(cache.lookup(&elsewhere, similar).await.is_none());
+        assert!(cache.lookup(&key("tell me a joke"), "tell me a joke").await.is_none());
+    }
+
+    #[test]
+    fn test_cache_entry_expiry() {
+        let entry = CacheEntry {
+            version: CACHE_VERSION,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            query: "test".to_string(),
+            request: String::new(),
+            provider: "ollama".to_string(),
+            model: "model".to_string(),
+            temperature: None,
+            max_tokens: None,
+            task_type: "general".to_string(),
+            template: String::new(),
+            strategy: String::new(),
+            response: "response".to_string(),
+            created_at: 0, // Unix epoch - definitely expired
+            accessed_at: 0,
+            ttl_secs: 1,
+            embedding: None,
+            files: Vec::new(),
+        };
+        assert!(entry.is_expired());
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let fresh_entry = CacheEntry {
+            version: CACHE_VERSION,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            query: "test".to_string(),
+            request: String::new(),
+            provider: "ollama".to_string(),
+            model: "model".to_string(),
+            temperature: None,
+            max_tokens: None,
+            task_type: "general".to_string(),
+            template: String::new(),
+            strategy: String::new(),
+            response: "response".to_string(),
+            created_at: now,
+            accessed_at: now,
+            ttl_secs: 3600,
+            embedding: None,
+            files: Vec::new(),
+        };
+        assert!(!fresh_entry.is_expired());
+    }
+}
@@ -0,0 +1,592 @@
+//! Entries at rest - optional compression and encryption
+//!
+//! A sealed entry is a small JSON envelope around the encoded entry, so both
+//! stores keep holding text. Encryption is ChaCha20-Poly1305 with the entry's
+//! cache key as associated data: a ciphertext copied under another key fails
+//! to open instead of answering the wrong request. Plain JSON entries stay
+//! readable, and are sealed the first time the cache opens with encryption on.
+
+use anyhow::{Context, Result};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use super::{hex, now_secs, CacheEntry, Embedding};
+use crate::config::EncryptionConfig;
+
+/// Environment variable holding the new passphrase while rotating
+pub const NEW_PASSPHRASE_ENV: &str = "WORKYTERM_CACHE_NEW_PASSPHRASE";
+
+/// File in the cache directory holding the passphrase salt
+const SALT_FILE: &str = "KEY_SALT";
+
+/// Envelope format version
+const ENVELOPE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    /// Envelope format; its presence tells sealed entries from plain JSON
+    sealed: u32,
+    compressed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    data: String,
+}
+
+/// An encryption key and the id entries refer to it by
+#[derive(Clone)]
+struct SealKey {
+    id: String,
+    key: [u8; 32],
+    created_at: u64,
+}
+
+impl SealKey {
+    fn new(key: [u8; 32]) -> Self {
+        // The id names the key without revealing it
+        let id = hex(&Sha256::digest(key))[..16].to_string();
+        Self {
+            id,
+            key,
+            created_at: now_secs(),
+        }
+    }
+
+    fn random() -> Self {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Self::new(key)
+    }
+
+    fn derive(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        if passphrase.is_empty() {
+            anyhow::bail!("The cache passphrase is empty");
+        }
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("Failed to derive the cache key: {}", e))?;
+        Ok(Self::new(key))
+    }
+}
+
+/// The key file: the current key seals, every listed key opens
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    current: String,
+    keys: Vec<KeyFileEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyFileEntry {
+    id: String,
+    /// Hex-encoded key
+    key: String,
+    created_at: u64,
+}
+
+/// Where the keys come from
+enum KeySource {
+    File(PathBuf),
+    Passphrase { salt: Vec<u8> },
+}
+
+struct Keyring {
+    source: KeySource,
+    current: SealKey,
+    /// Keys still accepted for opening, during rotation
+    old: Vec<SealKey>,
+}
+
+impl Keyring {
+    /// Load the key file, creating it with a fresh key if it doesn't exist
+    fn from_file(path: PathBuf) -> Result<Self> {
+        if !path.exists() {
+            let keyring = Self {
+                source: KeySource::File(path),
+                current: SealKey::random(),
+                old: Vec::new(),
+            };
+            keyring.save()?;
+            return Ok(keyring);
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read cache key file {}", path.display()))?;
+        let file: KeyFile = serde_json::from_str(&content)
+            .with_context(|| format!("Bad cache key file {}", path.display()))?;
+        let mut keys = Vec::new();
+        for entry in &file.keys {
+            let bytes = unhex(&entry.key)
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or_else(|| anyhow::anyhow!("Bad key {} in {}", entry.id, path.display()))?;
+            keys.push(SealKey {
+                created_at: entry.created_at,
+                ..SealKey::new(bytes)
+            });
+        }
+        let position = keys
+            .iter()
+            .position(|key| key.id == file.current)
+            .ok_or_else(|| anyhow::anyhow!("Current key missing from {}", path.display()))?;
+        let current = keys.remove(position);
+        Ok(Self {
+            source: KeySource::File(path),
+            current,
+            old: keys,
+        })
+    }
+
+    /// Derive the key from a passphrase, salted per cache directory
+    fn from_passphrase(passphrase: &str, cache_dir: &Path) -> Result<Self> {
+        let salt_path = cache_dir.join(SALT_FILE);
+        let salt = match fs::read_to_string(&salt_path) {
+            Ok(content) => unhex(content.trim())
+                .ok_or_else(|| anyhow::anyhow!("Bad salt in {}", salt_path.display()))?,
+            Err(_) => {
+                let mut salt = vec![0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                fs::create_dir_all(cache_dir)?;
+                fs::write(&salt_path, hex(&salt))?;
+                salt
+            }
+        };
+        Ok(Self {
+            current: SealKey::derive(passphrase, &salt)?,
+            source: KeySource::Passphrase { salt },
+            old: Vec::new(),
+        })
+    }
+
+    fn find(&self, id: &str) -> Option<&SealKey> {
+        std::iter::once(&self.current)
+            .chain(&self.old)
+            .find(|key| key.id == id)
+    }
+
+    /// Write the key file; passphrase keys aren't stored
+    fn save(&self) -> Result<()> {
+        let KeySource::File(ref path) = self.source else {
+            return Ok(());
+        };
+        let file = KeyFile {
+            current: self.current.id.clone(),
+            keys: std::iter::once(&self.current)
+                .chain(&self.old)
+                .map(|key| KeyFileEntry {
+                    id: key.id.clone(),
+                    key: hex(&key.key),
+                    created_at: key.created_at,
+                })
+                .collect(),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+        write_private(&temp, serde_json::to_string_pretty(&file)?.as_bytes())?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+/// Write a file only the owner can read
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content)?;
+    Ok(())
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Associated data for an entry's embedding, so it can't pass for the entry
+fn embedding_aad(key: &str) -> String {
+    format!("{}#embedding", key)
+}
+
+/// Turns entries into stored text and back
+pub struct Codec {
+    compress: bool,
+    keys: RwLock<Option<Keyring>>,
+    /// Refuse entries that aren't encrypted, once every plain one is sealed
+    encrypted_only: AtomicBool,
+}
+
+impl Codec {
+    /// Entries stored as plain JSON
+    pub fn plain() -> Self {
+        Self {
+            compress: false,
+            keys: RwLock::new(None),
+            encrypted_only: AtomicBool::new(false),
+        }
+    }
+
+    /// The codec for the cache config; loads or creates the encryption key
+    pub fn from_config(
+        compress: bool,
+        encryption: &EncryptionConfig,
+        cache_dir: &Path,
+    ) -> Result<Self> {
+        let keys = if encryption.enabled {
+            Some(match encryption.passphrase()? {
+                Some(passphrase) => Keyring::from_passphrase(&passphrase, cache_dir)?,
+                None => Keyring::from_file(encryption.key_path())?,
+            })
+        } else {
+            None
+        };
+        Ok(Self {
+            compress,
+            keys: RwLock::new(keys),
+            encrypted_only: AtomicBool::new(false),
+        })
+    }
+
+    /// Stop opening plain and unencrypted entries, which only someone with
+    /// write access to the cache could have put there once it's sealed
+    ///
+    /// Has no effect unless encrypting.
+    pub fn require_encrypted(&self) {
+        self.encrypted_only.store(self.is_encrypting(), Ordering::Relaxed);
+    }
+
+    /// Whether entries are stored as plain JSON
+    pub fn is_plain(&self) -> bool {
+        !self.compress && !self.is_encrypting()
+    }
+
+    pub fn is_encrypting(&self) -> bool {
+        self.keys.read().map(|keys| keys.is_some()).unwrap_or(false)
+    }
+
+    /// Id of the key new entries are sealed with
+    #[cfg(test)]
+    pub fn key_id(&self) -> Option<String> {
+        let keys = self.keys.read().ok()?;
+        keys.as_ref().map(|keyring| keyring.current.id.clone())
+    }
+
+    /// Whether stored text is an encrypted entry
+    pub fn is_encrypted(data: &str) -> bool {
+        serde_json::from_str::<Envelope>(data).is_ok_and(|envelope| envelope.key_id.is_some())
+    }
+
+    /// Encode `entry`, stored under `key`
+    pub fn seal(&self, key: &str, entry: &CacheEntry) -> Result<String> {
+        self.seal_json(key.as_bytes(), serde_json::to_vec(entry)?)
+    }
+
+    /// Decode an entry stored under `key`, checking its authenticity
+    pub fn open(&self, key: &str, data: &str) -> Result<CacheEntry> {
+        Ok(serde_json::from_slice(&self.open_json(key.as_bytes(), data)?)?)
+    }
+
+    /// Encode the embedding of the entry under `key`, for stores that keep
+    /// it apart from the entry
+    pub fn seal_embedding(&self, key: &str, embedding: &Embedding) -> Result<String> {
+        self.seal_json(embedding_aad(key).as_bytes(), serde_json::to_vec(embedding)?)
+    }
+
+    /// Decode an embedding sealed with `seal_embedding`
+    pub fn open_embedding(&self, key: &str, data: &str) -> Result<Embedding> {
+        Ok(serde_json::from_slice(&self.open_json(embedding_aad(key).as_bytes(), data)?)?)
+    }
+
+    /// Compress and encrypt `json` as configured, bound to `aad`
+    fn seal_json(&self, aad: &[u8], json: Vec<u8>) -> Result<String> {
+        if self.is_plain() {
+            return Ok(String::from_utf8(json)?);
+        }
+
+        let payload = if self.compress {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&json)?;
+            encoder.finish()?
+        } else {
+            json
+        };
+
+        let keys = self
+            .keys
+            .read()
+            .map_err(|_| anyhow::anyhow!("Cache keyring lock poisoned"))?;
+        let envelope = match *keys {
+            Some(ref keyring) => {
+                let cipher = ChaCha20Poly1305::new(Key::from_slice(&keyring.current.key));
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let sealed = cipher
+                    .encrypt(&nonce, Payload { msg: &payload, aad })
+                    .map_err(|_| anyhow::anyhow!("Failed to encrypt cache entry"))?;
+                Envelope {
+                    sealed: ENVELOPE_VERSION,
+                    compressed: self.compress,
+                    key_id: Some(keyring.current.id.clone()),
+                    nonce: Some(BASE64.encode(nonce)),
+                    data: BASE64.encode(sealed),
+                }
+            }
+            None => Envelope {
+                sealed: ENVELOPE_VERSION,
+                compressed: true,
+                key_id: None,
+                nonce: None,
+                data: BASE64.encode(payload),
+            },
+        };
+        Ok(serde_json::to_string(&envelope)?)
+    }
+
+    /// The JSON sealed in `data`, checking it was sealed for `aad`
+    fn open_json(&self, aad: &[u8], data: &str) -> Result<Vec<u8>> {
+        let envelope = serde_json::from_str::<Envelope>(data).ok();
+        if self.encrypted_only.load(Ordering::Relaxed)
+            && envelope.as_ref().is_none_or(|envelope| envelope.key_id.is_none())
+        {
+            anyhow::bail!("Entry isn't encrypted, but the cache is");
+        }
+        let Some(envelope) = envelope else {
+            return Ok(data.as_bytes().to_vec());
+        };
+        if envelope.sealed != ENVELOPE_VERSION {
+            anyhow::bail!("Unknown entry envelope version {}", envelope.sealed);
+        }
+
+        let data = BASE64.decode(&envelope.data).context("Entry data isn't base64")?;
+        let payload = match envelope.key_id {
+            Some(ref id) => {
+                let keys = self
+                    .keys
+                    .read()
+                    .map_err(|_| anyhow::anyhow!("Cache keyring lock poisoned"))?;
+                let keyring = keys
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("Entry is encrypted but encryption is off"))?;
+                let seal_key = keyring
+                    .find(id)
+                    .ok_or_else(|| anyhow::anyhow!("Entry is encrypted with unknown key {}", id))?;
+                let nonce = envelope
+                    .nonce
+                    .as_deref()
+                    .and_then(|nonce| BASE64.decode(nonce).ok())
+                    .filter(|nonce| nonce.len() == 12)
+                    .ok_or_else(|| anyhow::anyhow!("Entry has a bad nonce"))?;
+                let payload = Payload { msg: &data, aad };
+                ChaCha20Poly1305::new(Key::from_slice(&seal_key.key))
+                    .decrypt(Nonce::from_slice(&nonce), payload)
+                    .map_err(|_| {
+                        anyhow::anyhow!("Entry failed authentication (corrupt or altered)")
+                    })?
+            }
+            None => data,
+        };
+
+        if envelope.compressed {
+            let mut json = Vec::new();
+            DeflateDecoder::new(payload.as_slice())
+                .read_to_end(&mut json)
+                .context("Entry doesn't decompress")?;
+            Ok(json)
+        } else {
+            Ok(payload)
+        }
+    }
+
+    /// Start sealing with a new key, still opening entries under the old ones
+    ///
+    /// With a key file, the new key is generated and saved beside the old
+    /// ones; with a passphrase, it is derived from `NEW_PASSPHRASE_ENV`.
+    /// Returns the new key's id.
+    pub fn begin_rotation(&self) -> Result<String> {
+        let mut keys = self
+            .keys
+            .write()
+            .map_err(|_| anyhow::anyhow!("Cache keyring lock poisoned"))?;
+        let keyring = keys.as_mut().ok_or_else(|| anyhow::anyhow!("Cache encryption is off"))?;
+        let next = match keyring.source {
+            KeySource::File(_) => SealKey::random(),
+            KeySource::Passphrase { ref salt } => {
+                let passphrase = std::env::var(NEW_PASSPHRASE_ENV).map_err(|_| {
+                    anyhow::anyhow!("Set {} to the new passphrase", NEW_PASSPHRASE_ENV)
+                })?;
+                SealKey::derive(&passphrase, salt)?
+            }
+        };
+        if next.id == keyring.current.id {
+            anyhow::bail!("The new key is the same as the current one");
+        }
+        let previous = std::mem::replace(&mut keyring.current, next);
+        keyring.old.push(previous);
+        keyring.save()?;
+        Ok(keyring.current.id.clone())
+    }
+
+    /// Forget every key but the current one, once entries are resealed
+    pub fn finish_rotation(&self) -> Result<()> {
+        let mut keys = self
+            .keys
+            .write()
+            .map_err(|_| anyhow::anyhow!("Cache keyring lock poisoned"))?;
+        if let Some(keyring) = keys.as_mut() {
+            keyring.old.clear();
+            keyring.save()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> CacheEntry {
+        CacheEntry {
+            version: super::super::CACHE_VERSION,
+            namespace: "default".to_string(),
+            query: "review @src/main.rs".to_string(),
+            request: String::new(),
+            provider: "ollama".to_string(),
+            model: String::new(),
+            temperature: None,
+            max_tokens: None,
+            task_type: "review".to_string(),
+            template: String::new(),
+            strategy: String::new(),
+            response: "Looks fine. ".repeat(50),
+            created_at: 1_700_000_000,
+            accessed_at: 1_700_000_000,
+            ttl_secs: 3600,
+            embedding: None,
+            files: Vec::new(),
+        }
+    }
+
+    fn encrypting(dir: &Path, compress: bool) -> Codec {
+        let config = EncryptionConfig {
+            enabled: true,
+            key_file: dir.join("cache.key").display().to_string(),
+            passphrase: String::new(),
+        };
+        Codec::from_config(compress, &config, dir).unwrap()
+    }
+
+    #[test]
+    fn test_compress_and_encrypt() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let plain = serde_json::to_string(&entry()).unwrap();
+
+        let compressing = Codec::from_config(true, &EncryptionConfig::default(), dir).unwrap();
+        let packed = compressing.seal("k1", &entry()).unwrap();
+        assert!(packed.len() < plain.len());
+        assert!(!Codec::is_encrypted(&packed));
+        assert_eq!(compressing.open("k1", &packed).unwrap().response, entry().response);
+
+        let codec = encrypting(dir, true);
+        let sealed = codec.seal("k1", &entry()).unwrap();
+        assert!(Codec::is_encrypted(&sealed));
+        assert!(!sealed.contains("Looks fine") && !sealed.contains("main.rs"));
+        assert_eq!(codec.open("k1", &sealed).unwrap().query, entry().query);
+
+        // Plain entries from before sealing still open
+        assert_eq!(codec.open("k1", &plain).unwrap().response, entry().response);
+
+        // Moved to another key or altered, the entry is refused
+        assert!(codec.open("k2", &sealed).is_err());
+        let mut envelope: Envelope = serde_json::from_str(&sealed).unwrap();
+        let mut data = BASE64.decode(&envelope.data).unwrap();
+        data[0] ^= 1;
+        envelope.data = BASE64.encode(data);
+        assert!(codec.open("k1", &serde_json::to_string(&envelope).unwrap()).is_err());
+
+        // Without the key, encrypted entries don't open
+        assert!(Codec::plain().open("k1", &sealed).is_err());
+
+        // Embeddings kept beside the entry are sealed too, and can't pass for it
+        let embedding = Embedding { model: "nomic-embed-text".to_string(), vector: vec![0.5] };
+        let sealed_embedding = codec.seal_embedding("k1", &embedding).unwrap();
+        assert!(Codec::is_encrypted(&sealed_embedding));
+        assert_eq!(codec.open_embedding("k1", &sealed_embedding).unwrap(), embedding);
+        assert!(codec.open_embedding("k2", &sealed_embedding).is_err());
+        assert!(codec.open("k1", &sealed_embedding).is_err());
+
+        // Once the cache is sealed, neither plain nor merely compressed entries open
+        codec.require_encrypted();
+        assert!(codec.open("k1", &plain).is_err());
+        assert!(codec.open("k1", &packed).is_err());
+        assert_eq!(codec.open("k1", &sealed).unwrap().query, entry().query);
+        compressing.require_encrypted();
+        assert!(compressing.open("k1", &packed).is_ok());
+    }
+
+    #[test]
+    fn test_key_file_rotation() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let codec = encrypting(dir, false);
+        let old_id = codec.key_id().unwrap();
+        let sealed = codec.seal("k", &entry()).unwrap();
+
+        // The key file is reused across runs
+        assert_eq!(encrypting(dir, false).key_id().unwrap(), old_id);
+
+        let new_id = codec.begin_rotation().unwrap();
+        assert_ne!(new_id, old_id);
+        assert!(codec.open("k", &sealed).is_ok());
+        let resealed = codec.seal("k", &entry()).unwrap();
+        codec.finish_rotation().unwrap();
+
+        assert!(codec.open("k", &sealed).is_err());
+        let reloaded = encrypting(dir, false);
+        assert_eq!(reloaded.key_id().unwrap(), new_id);
+        assert!(reloaded.open("k", &resealed).is_ok());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("cache.key")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_passphrase_key() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let one = Keyring::from_passphrase("correct horse", dir).unwrap();
+        let again = Keyring::from_passphrase("correct horse", dir).unwrap();
+        let other = Keyring::from_passphrase("battery staple", dir).unwrap();
+        assert_eq!(one.current.id, again.current.id);
+        assert_ne!(one.current.id, other.current.id);
+        assert!(Keyring::from_passphrase("", dir).is_err());
+    }
+}
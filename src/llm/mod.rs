@@ -2,6 +2,8 @@
 
 mod provider;
 mod council;
+mod context;
 
 pub use provider::*;
 pub use council::*;
+pub use context::*;
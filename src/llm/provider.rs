@@ -1,9 +1,11 @@
 //! LLM Provider implementations - CLI-first, API optional
 
 use anyhow::Result;
+use futures::future::join_all;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::process::Command;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncBufReadExt, BufReader};
 
 use crate::config::ProviderConfig;
@@ -33,8 +35,32 @@ pub trait LlmProvider: Send + Sync {
     fn supports_streaming(&self) -> bool {
         false
     }
+
+    /// Context window in tokens, used to budget what goes into a prompt
+    fn context_window(&self) -> usize {
+        DEFAULT_CONTEXT_WINDOW
+    }
+
+    /// Model and sampling settings sent with each request
+    ///
+    /// CLI providers leave these to the CLI, so they report the defaults.
+    fn settings(&self) -> ModelSettings {
+        ModelSettings::default()
+    }
+}
+
+/// The settings a provider generates with, as far as WorkyTerm controls them
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ModelSettings {
+    /// Model name; empty when the provider picks its own
+    pub model: String,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
 }
 
+/// Context window assumed when a provider doesn't report one
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8_192;
+
 // ============================================================================
 // CLI-BASED PROVIDERS (No API Key Required)
 // ============================================================================
@@ -126,6 +152,10 @@ impl LlmProvider for ClaudeCliProvider {
     fn supports_streaming(&self) -> bool {
         true
     }
+
+    fn context_window(&self) -> usize {
+        200_000
+    }
 }
 
 /// OpenAI Codex CLI provider
@@ -215,6 +245,10 @@ impl LlmProvider for CodexCliProvider {
     fn supports_streaming(&self) -> bool {
         true
     }
+
+    fn context_window(&self) -> usize {
+        128_000
+    }
 }
 
 /// Gemini CLI provider
@@ -304,6 +338,10 @@ impl LlmProvider for GeminiCliProvider {
     fn supports_streaming(&self) -> bool {
         true
     }
+
+    fn context_window(&self) -> usize {
+        1_000_000
+    }
 }
 
 // ============================================================================
@@ -370,6 +408,18 @@ impl LlmProvider for OllamaProvider {
     fn is_available(&self) -> bool {
         Self::is_running()
     }
+
+    fn context_window(&self) -> usize {
+        // Ollama's default num_ctx unless the model is configured larger
+        self.config.context_window.unwrap_or(4_096)
+    }
+
+    fn settings(&self) -> ModelSettings {
+        ModelSettings {
+            model: self.config.model.clone(),
+            ..ModelSettings::default()
+        }
+    }
 }
 
 /// OpenAI API provider
@@ -457,6 +507,18 @@ impl LlmProvider for OpenAiProvider {
     fn is_available(&self) -> bool {
         !self.api_key.is_empty()
     }
+
+    fn context_window(&self) -> usize {
+        self.config.context_window.unwrap_or(128_000)
+    }
+
+    fn settings(&self) -> ModelSettings {
+        ModelSettings {
+            model: self.config.model.clone(),
+            temperature: self.config.temperature,
+            max_tokens: self.config.max_tokens,
+        }
+    }
 }
 
 /// Anthropic API provider
@@ -538,6 +600,18 @@ impl LlmProvider for AnthropicProvider {
     fn is_available(&self) -> bool {
         !self.api_key.is_empty()
     }
+
+    fn context_window(&self) -> usize {
+        self.config.context_window.unwrap_or(200_000)
+    }
+
+    fn settings(&self) -> ModelSettings {
+        ModelSettings {
+            model: self.config.model.clone(),
+            temperature: None,
+            max_tokens: Some(self.config.max_tokens.unwrap_or(4096)),
+        }
+    }
 }
 
 // ============================================================================
@@ -676,6 +750,36 @@ pub fn create_provider(
     }
 }
 
+// ============================================================================
+// CONCURRENT GENERATION
+// ============================================================================
+
+/// Outcome of a single timed provider call
+pub struct TimedResponse {
+    pub result: Result<String>,
+    pub latency: Duration,
+}
+
+/// Call a provider and measure how long it took
+pub async fn generate_timed(provider: &dyn LlmProvider, prompt: &str) -> TimedResponse {
+    let start = Instant::now();
+    let result = provider.generate(prompt).await;
+    TimedResponse {
+        result,
+        latency: start.elapsed(),
+    }
+}
+
+/// Run several provider calls at once, returning results in call order
+pub async fn generate_concurrently(calls: &[(&dyn LlmProvider, &str)]) -> Vec<TimedResponse> {
+    join_all(
+        calls
+            .iter()
+            .map(|(provider, prompt)| generate_timed(*provider, prompt)),
+    )
+    .await
+}
+
 /// Auto-select best available provider
 pub fn auto_select_provider(
     config: &crate::config::Config,
@@ -751,6 +855,7 @@ mod tests {
             enabled: true,
             max_tokens: None,
             temperature: None,
+            context_window: None,
         };
         let provider = create_provider("claude-cli", config, None);
         assert!(provider.is_ok());
@@ -766,6 +871,7 @@ mod tests {
             enabled: true,
             max_tokens: None,
             temperature: None,
+            context_window: None,
         };
         let provider = create_provider("codex-cli", config, None);
         assert!(provider.is_ok());
@@ -9,18 +9,20 @@ mod llm;
 mod team;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{ArgGroup, Parser, Subcommand};
 use colored::Colorize;
 use std::io::{self, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 
-use cache::ResponseCache;
-use config::Config;
+use cache::{CacheKey, CacheMiss, CacheMode, FileRef, HitKind, ResponseCache};
+use config::{Config, CouncilMode, Layers, Origin};
+use llm::Council;
 use team::SupportTeam;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "WorkyTerm - AI coding assistant", long_about = None)]
+#[command(group(ArgGroup::new("print_mode").args(["print", "json", "quiet"]).multiple(true)))]
 struct Args {
     /// Initial prompt to process (or use positional)
     #[arg(long)]
@@ -38,14 +40,44 @@ struct Args {
     #[arg(short = 'm', long)]
     model: Option<String>,
 
-    /// Hint task type: research, code, write, analyze, explain
+    /// Hint task type: research, write, analyze, code, review, summarize, translate, explain
     #[arg(short = 't', long)]
     task: Option<String>,
 
+    /// Translate the prompt into LANG (implies -t translate)
+    #[arg(long, value_name = "LANG")]
+    to: Option<String>,
+
     /// Quiet mode - minimal output, just the response
     #[arg(short = 'q', long)]
     quiet: bool,
 
+    /// Generate N candidate answers and let a judge pick or merge the best
+    #[arg(long, value_name = "N")]
+    best_of: Option<u32>,
+
+    /// Sample N independent answers and return the majority final answer
+    #[arg(long, value_name = "N", conflicts_with = "best_of")]
+    vote: Option<u32>,
+
+    /// Send the request to the multi-model council instead of a single member
+    /// (print mode only)
+    #[arg(long, requires = "print_mode")]
+    council: bool,
+
+    /// Run the council as a structured debate (implies --council; print mode only)
+    #[arg(long, requires = "print_mode")]
+    debate: bool,
+
+    /// Write the council transcript to FILE (.json or Markdown; implies --council;
+    /// print mode only)
+    #[arg(long, value_name = "FILE", requires = "print_mode")]
+    council_transcript: Option<String>,
+
+    /// Explain how the request was routed (scores, rule, member selection)
+    #[arg(long)]
+    explain_routing: bool,
+
     /// Enable response caching (default: enabled)
     #[arg(long, default_value = "true")]
     cache: bool,
@@ -62,21 +94,181 @@ struct Args {
     #[arg(long)]
     clear_cache: bool,
 
+    /// Answer only from the cache, never calling a provider; a miss exits with code 3
+    #[arg(
+        long,
+        conflicts_with_all = ["no_cache", "record", "council", "debate", "council_transcript"]
+    )]
+    offline: bool,
+
+    /// Always call the provider and store the answer, replacing any cached one
+    #[arg(long, conflicts_with = "no_cache")]
+    record: bool,
+
+    /// Cache namespace to read and write (default: `[cache] namespace`)
+    #[arg(long, value_name = "NAME", global = true)]
+    cache_ns: Option<String>,
+
+    /// Forget recorded routing outcomes (adaptive routing) and exit
+    #[arg(long)]
+    reset_routing: bool,
+
     /// Resume a previous session
     #[arg(short, long)]
     resume: Option<String>,
 
-    /// Config file path
-    #[arg(short, long)]
+    /// Global config file path (default: ~/.config/workyterm/config.toml)
+    #[arg(short, long, global = true)]
     config: Option<String>,
 
+    /// Override a config setting, e.g. `cache.max_entries=500` (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_var, global = true)]
+    settings: Vec<(String, String)>,
+
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
 
+    /// Set a prompt template variable, used as {{KEY}} (repeatable)
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_var, global = true)]
+    vars: Vec<(String, String)>,
+
     /// Positional prompt
     #[arg(trailing_var_arg = true)]
     query: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Train or evaluate the local task classifier
+    Router {
+        #[command(subcommand)]
+        action: RouterAction,
+    },
+    /// List, show and preview prompt templates
+    Prompts {
+        #[command(subcommand)]
+        action: PromptsAction,
+    },
+    /// Inspect and trim the response cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Inspect the effective configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print every effective setting
+    Show {
+        /// Also print the layer each value came from
+        #[arg(long)]
+        origin: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Entry count, size and limits
+    Stats,
+    /// Remove expired entries and evict down to the size limits
+    Prune,
+    /// List entries, most recently used first
+    List,
+    /// Print an entry's prompt and response (a unique key prefix is enough)
+    Show {
+        key: String,
+    },
+    /// Remove an entry (a unique key prefix is enough)
+    Rm {
+        key: String,
+    },
+    /// Write the namespace's entries to a compressed bundle
+    Export {
+        bundle: std::path::PathBuf,
+
+        /// Export every namespace
+        #[arg(long)]
+        all: bool,
+
+        /// Export from an encrypted cache; the bundle itself is not encrypted
+        #[arg(long)]
+        plaintext: bool,
+    },
+    /// Merge a bundle's entries into the cache, keeping the newer of each
+    Import {
+        bundle: std::path::PathBuf,
+    },
+    /// Check every entry decodes and authenticates, and the store is intact
+    Verify,
+    /// Encrypt every entry under a new key and retire the old one
+    RotateKey,
+}
+
+#[derive(Subcommand, Debug)]
+enum PromptsAction {
+    /// List template files, highest priority first
+    List,
+    /// Print a template (e.g. `code`, `roles/coder`, `builtin`)
+    Show {
+        name: String,
+    },
+    /// Print the final prompt for a request without calling a provider
+    Render {
+        /// Task type to render for (default: route the request)
+        #[arg(short = 't', long)]
+        task: Option<String>,
+
+        /// Team member to render for, by name (default: route the request)
+        #[arg(long)]
+        member: Option<String>,
+
+        /// The request; @file references are expanded
+        #[arg(required = true, trailing_var_arg = true)]
+        request: Vec<String>,
+    },
+}
+
+/// Parse `--var KEY=VALUE`
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum RouterAction {
+    /// Train the classifier from labelled JSONL files and past sessions
+    Train {
+        /// Labelled examples, one {"text": ..., "task": ...} per line (repeatable)
+        #[arg(long, value_name = "FILE")]
+        data: Vec<String>,
+
+        /// Don't learn from requests recorded in past sessions
+        #[arg(long)]
+        no_history: bool,
+
+        /// Fraction of examples held out to evaluate the new model (0 to skip)
+        #[arg(long, default_value = "0.2")]
+        holdout: f32,
+    },
+    /// Evaluate the trained classifier against a labelled held-out set
+    Eval {
+        /// Labelled examples, one {"text": ..., "task": ...} per line
+        #[arg(long, value_name = "FILE")]
+        data: String,
+    },
 }
 
 /// Session state tracking
@@ -87,6 +279,10 @@ struct Session {
     tokens_out: usize,
     start_time: Instant,
     model: String,
+    /// Last request and how it was routed, for /why
+    last_routing: Option<(String, team::RoutingExplanation)>,
+    /// Member the conversation is with, for routing follow-ups
+    thread: Option<team::Thread>,
 }
 
 impl Session {
@@ -98,6 +294,8 @@ impl Session {
             tokens_out: 0,
             start_time: Instant::now(),
             model: String::new(),
+            last_routing: None,
+            thread: None,
         }
     }
 
@@ -107,6 +305,9 @@ impl Session {
     }
 }
 
+/// Exit code when `--offline` finds no cached answer
+const EXIT_CACHE_MISS: i32 = 3;
+
 /// Global verbose flag (thread-safe)
 static VERBOSE: AtomicBool = AtomicBool::new(false);
 
@@ -150,7 +351,11 @@ fn normalize_model_name(model: &str) -> String {
 fn hint_to_task_type(hint: &str) -> team::TaskType {
     match hint.to_lowercase().as_str() {
         "research" | "search" | "find" | "r" => team::TaskType::Research,
-        "code" | "analyze" | "debug" | "a" => team::TaskType::Analyze,
+        "analyze" | "debug" | "a" => team::TaskType::Analyze,
+        "code" | "coding" | "implement" | "c" => team::TaskType::Code,
+        "review" | "diff" | "pr" | "v" => team::TaskType::Review,
+        "summarize" | "summary" | "tldr" | "u" => team::TaskType::Summarize,
+        "translate" | "translation" | "t" => team::TaskType::Translate,
         "write" | "draft" | "compose" | "w" => team::TaskType::Write,
         "explain" | "teach" | "e" => team::TaskType::Explain,
         "create" | "creative" | "brainstorm" => team::TaskType::Create,
@@ -169,11 +374,47 @@ async fn main() -> Result<()> {
 
     debug_log!("WorkyTerm starting...");
 
-    // Initialize cache
-    let cache_enabled = args.cache && !args.no_cache;
-    let cache = ResponseCache::new(cache_enabled, args.cache_ttl);
+    // Load config: defaults, global file, project file, environment, then flags
+    let cwd = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+    let mut layers = Layers::load(args.config.as_deref(), &cwd)?;
+    if let Some(ns) = &args.cache_ns {
+        layers.set("cache.namespace", ns, Origin::Cli("--cache-ns".to_string()))?;
+    }
+    for (key, value) in &args.settings {
+        layers.set(key, value, Origin::Cli("--set".to_string()))?;
+    }
+    let config = layers.config()?;
+    debug_log!("Config loaded");
+
+    // Commands that don't touch the cache run before it is opened, so a bad
+    // `[cache.encryption]` setting can't stop them
+    match args.command {
+        Some(Command::Config { action }) => return run_config_command(action, &layers),
+        Some(Command::Router { action }) => return run_router_command(action, &config),
+        Some(Command::Prompts { action }) => {
+            return run_prompts_command(action, &config, args.vars).await;
+        }
+        _ => {}
+    }
+
+    // Initialize cache; replaying and recording need it whatever --cache says
+    let cache_mode = if args.offline {
+        CacheMode::Offline
+    } else if args.record {
+        CacheMode::Record
+    } else {
+        CacheMode::Normal
+    };
+    let cache_enabled = cache_mode != CacheMode::Normal || (args.cache && !args.no_cache);
+    let mut cache = ResponseCache::new(cache_enabled, args.cache_ttl)
+        .with_limits(config.cache.max_entries, config.cache.max_bytes)
+        .with_backend(config.cache.backend)
+        .with_semantic(&config.cache.semantic)
+        .with_mode(cache_mode)
+        .with_namespace(&config.cache.namespace)
+        .with_sealing(config.cache.compress, &config.cache.encryption)?;
     cache.init()?;
-    debug_log!("Cache initialized (enabled: {})", cache_enabled);
+    debug_log!("Cache initialized (enabled: {}, mode: {:?})", cache_enabled, cache_mode);
 
     // Handle cache clear command
     if args.clear_cache {
@@ -188,12 +429,26 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
-    // Load config
-    let config = Config::load(args.config.as_deref())?;
-    debug_log!("Config loaded");
+    if let Some(Command::Cache { action }) = args.command {
+        return run_cache_command(action, &cache, &config);
+    }
+
+    // Handle routing reset command
+    if args.reset_routing {
+        match team::AdaptiveRouter::load(&config.routing.adaptive).reset(None) {
+            Ok(count) => {
+                println!("{} Forgot {} routing outcomes", "✓".green(), count);
+            }
+            Err(e) => {
+                println!("{} Failed to reset routing: {}", "✗".red(), e);
+            }
+        }
+        return Ok(());
+    }
 
     // Use async team initialization for parallel provider detection (faster startup)
     let mut team = SupportTeam::new_async(&config).await;
+    team.set_prompt_vars(args.vars.clone());
     let mut session = Session::new();
 
     // Override model if specified
@@ -214,39 +469,138 @@ async fn main() -> Result<()> {
             } else {
                 None
             }
+        })
+        .map(|prompt| match args.to {
+            Some(ref language) => team::translation_request(&prompt, language),
+            None => prompt,
         });
+    let task_hint = args.task.as_deref().or(args.to.as_ref().map(|_| "translate"));
 
     // Print/JSON/Quiet mode: single query and exit
     if args.print || args.json || args.quiet {
         if let Some(prompt) = initial_prompt {
             let start = Instant::now();
+            let request = Request::new(&prompt);
+            let prompt = request.expanded.clone();
+
+            if args.council || args.debate || args.council_transcript.is_some() {
+                let mut council_config = config.clone();
+                council_config.council.enabled = true;
+                if args.debate {
+                    council_config.council.mode = CouncilMode::Debate;
+                }
+
+                let council = Council::new(&council_config);
+                let deliberation = council.process(&prompt).await?;
+                let elapsed = start.elapsed();
+
+                if let Some(ref path) = args.council_transcript {
+                    deliberation.write_transcript(std::path::Path::new(path))?;
+                    debug_log!("Council transcript written to {}", path);
+                }
+
+                if args.json {
+                    let json = serde_json::json!({
+                        "success": true,
+                        "response": deliberation.answer,
+                        "mode": deliberation.mode,
+                        "transcript": deliberation.transcript,
+                        "elapsed_ms": elapsed.as_millis(),
+                        "tokens_out": Session::estimate_tokens(&deliberation.answer),
+                    });
+                    println!("{}", serde_json::to_string(&json)?);
+                } else if args.quiet {
+                    println!("{}", deliberation.answer);
+                } else {
+                    println!("{}", deliberation.render_transcript().dimmed());
+                    println!("{}", deliberation.answer);
+                }
+                return Ok(());
+            }
 
             // Process with optional model override, task hint, and caching
-            let (response, from_cache) = process_request_direct(
+            let direct = match process_request_direct(
                 &mut team,
                 &mut session,
-                &prompt,
+                &request,
                 args.model.as_deref(),
-                args.task.as_deref(),
+                task_hint,
+                SamplingFlags { best_of: args.best_of, vote: args.vote },
                 &cache,
-            ).await?;
+            ).await {
+                Err(e) if e.is::<CacheMiss>() => {
+                    if args.json {
+                        let json = serde_json::json!({
+                            "success": false,
+                            "error": e.to_string(),
+                            "cached": false,
+                        });
+                        println!("{}", serde_json::to_string(&json)?);
+                    } else {
+                        eprintln!("{} {}", "✗".red(), e);
+                    }
+                    std::process::exit(EXIT_CACHE_MISS);
+                }
+                result => result?,
+            };
 
             let elapsed = start.elapsed();
 
             if args.json {
                 // JSON output for programmatic consumption
-                let json = serde_json::json!({
+                let mut json = serde_json::json!({
                     "success": true,
-                    "response": response,
+                    "response": direct.response,
                     "model": session.model,
                     "elapsed_ms": elapsed.as_millis(),
-                    "tokens_out": Session::estimate_tokens(&response),
-                    "cached": from_cache,
+                    "tokens_out": Session::estimate_tokens(&direct.response),
+                    "cached": match direct.cached {
+                        None => serde_json::json!(false),
+                        Some(HitKind::Exact) => serde_json::json!(true),
+                        Some(HitKind::Semantic(_)) => serde_json::json!("semantic"),
+                        Some(HitKind::Request) => serde_json::json!("request"),
+                    },
                 });
+                if let Some(HitKind::Semantic(similarity)) = direct.cached {
+                    json["similarity"] = serde_json::json!(round_similarity(similarity));
+                }
+                if let Some(ref best_of) = direct.best_of {
+                    json["best_of"] = serde_json::to_value(best_of)?;
+                }
+                if let Some(ref vote) = direct.vote {
+                    json["vote"] = serde_json::to_value(vote)?;
+                }
+                if let Some(ref rule) = direct.rule {
+                    json["rule"] = serde_json::to_value(rule)?;
+                }
+                if args.explain_routing {
+                    if let Some((_, ref explanation)) = session.last_routing {
+                        json["routing"] = serde_json::to_value(explanation)?;
+                    }
+                }
                 println!("{}", serde_json::to_string(&json)?);
             } else {
-                // Plain text output
-                println!("{}", response);
+                // Plain text output; the routing report goes to stderr so
+                // stdout stays just the response
+                if args.explain_routing {
+                    if let Some((_, ref explanation)) = session.last_routing {
+                        eprintln!("{}\n", explanation.render().dimmed());
+                    }
+                }
+                let note = match direct.cached {
+                    Some(HitKind::Semantic(similarity)) => Some(format!(
+                        "(cached answer to a similar request, similarity {:.2})",
+                        similarity
+                    )),
+                    Some(HitKind::Request) => {
+                        Some("(cached answer recorded through another provider)".to_string())
+                    }
+                    _ => None,
+                };
+                if let (Some(note), false) = (note, args.quiet) {
+                    eprintln!("{}", note.dimmed());
+                }
+                println!("{}", direct.response);
             }
         }
         return Ok(());
@@ -256,7 +610,8 @@ async fn main() -> Result<()> {
     print_welcome(&team);
 
     if let Some(prompt) = initial_prompt {
-        process_request(&mut team, &mut session, &prompt, false).await?;
+        let request = Request::new(&prompt);
+        process_request(&mut team, &mut session, &cache, &request, false).await?;
     }
 
     // Main REPL loop
@@ -274,7 +629,7 @@ async fn main() -> Result<()> {
 
         // Handle slash commands
         if input.starts_with('/') {
-            if handle_slash_command(input, &team, &mut session).await {
+            if handle_slash_command(input, &mut team, &mut session, &mut cache).await {
                 continue;
             }
             // If command returned false, it means /exit
@@ -288,26 +643,33 @@ async fn main() -> Result<()> {
         }
 
         // Handle file references with @
-        let processed_input = process_file_refs(input);
+        let request = Request::new(input);
 
         // Process the request
-        process_request(&mut team, &mut session, &processed_input, false).await?;
+        process_request(&mut team, &mut session, &cache, &request, false).await?;
     }
 
     Ok(())
 }
 
 /// Handle slash commands. Returns true to continue, false to exit.
-async fn handle_slash_command(cmd: &str, team: &SupportTeam, session: &mut Session) -> bool {
+async fn handle_slash_command(
+    cmd: &str,
+    team: &mut SupportTeam,
+    session: &mut Session,
+    cache: &mut ResponseCache,
+) -> bool {
     let parts: Vec<&str> = cmd.split_whitespace().collect();
     let command = parts[0];
-    let _args: Vec<&str> = parts.iter().skip(1).copied().collect();
+    let args: Vec<&str> = parts.iter().skip(1).copied().collect();
 
     match command {
         "/help" | "/h" | "/?" => {
             print_help();
         }
         "/clear" => {
+            session.thread = None;
+            team.clear_history();
             print!("\x1B[2J\x1B[1;1H");
             print_welcome(team);
         }
@@ -321,11 +683,102 @@ async fn handle_slash_command(cmd: &str, team: &SupportTeam, session: &mut Sessi
             print_models(team);
         }
         "/cost" => {
-            print_cost(session);
+            print_cost(team, session);
         }
         "/context" => {
             print_context(session);
         }
+        "/why" => {
+            print_why(session);
+        }
+        "/cache" => match args.first().copied() {
+            Some(toggle @ ("on" | "off")) => match cache.set_enabled(toggle == "on") {
+                Ok(()) => println!("{} Response cache {}", "✓".green(), toggle),
+                Err(e) => println!("{} Failed to open the cache: {}", "✗".red(), e),
+            },
+            Some("clear") => match cache.clear() {
+                Ok(count) => println!("{} Cleared {} cached entries", "✓".green(), count),
+                Err(e) => println!("{} Failed to clear cache: {}", "✗".red(), e),
+            },
+            Some(other) => {
+                println!("{} Unknown option '{}'; use /cache on|off|clear", "✗".red(), other);
+            }
+            None => {
+                let stats = cache.stats();
+                let state = if cache.is_enabled() { "on".green() } else { "off".yellow() };
+                println!(
+                    "Response cache is {} ({} entries, {})",
+                    state,
+                    stats.active_entries(),
+                    format_bytes(stats.total_bytes as u64)
+                );
+            }
+        },
+        "/good" | "/bad" => {
+            let rating = if command == "/good" { team::Rating::Good } else { team::Rating::Bad };
+            match team.rate_last(rating) {
+                Ok(Some(outcome)) => println!(
+                    "{} Rated {}'s last answer ({}) as {}",
+                    "✓".green(),
+                    outcome.member,
+                    outcome.provider,
+                    &command[1..]
+                ),
+                Ok(None) => println!("{}", "Nothing to rate yet.".dimmed()),
+                Err(e) => println!("{} Failed to save rating: {}", "✗".red(), e),
+            }
+        }
+        "/routing" => match args.as_slice() {
+            ["reset"] | ["reset", _] => {
+                let task_type = args.get(1).map(|hint| hint_to_task_type(hint));
+                match team.reset_outcomes(task_type) {
+                    Ok(count) => println!(
+                        "{} Forgot {} routing outcomes",
+                        "✓".green(),
+                        count
+                    ),
+                    Err(e) => println!("{} Failed to reset routing: {}", "✗".red(), e),
+                }
+            }
+            _ => println!("{}", "Usage: /routing reset [task type]".dimmed()),
+        },
+        "/switch" => match args.first().copied() {
+            None | Some("auto") => {
+                if let Some(ref mut thread) = session.thread {
+                    thread.pinned = false;
+                }
+                println!("{}", "Routing each turn automatically again.".dimmed());
+            }
+            Some(name) => {
+                let member = team
+                    .get_members()
+                    .iter()
+                    .find(|m| m.available && m.name.eq_ignore_ascii_case(name));
+                match member {
+                    Some(member) => {
+                        session.thread = Some(team::Thread {
+                            member: member.name.clone(),
+                            task_type: member.specialty,
+                            pinned: true,
+                        });
+                        println!(
+                            "{} Switched to {} ({}, {}); {} to route automatically",
+                            "✓".green(),
+                            member.name.cyan(),
+                            member.role,
+                            member.provider_type,
+                            "/switch auto".cyan()
+                        );
+                    }
+                    None => println!(
+                        "{} No available member named {}; see {}",
+                        "✗".red(),
+                        name,
+                        "/team".cyan()
+                    ),
+                }
+            }
+        },
         "/compact" => {
             println!("{}", "Context compacted.".dimmed());
             session.tokens_in = session.tokens_in / 2;
@@ -401,9 +854,32 @@ const MAX_FILE_REF_SIZE: u64 = 1_048_576;
 static FILE_REF_REGEX: std::sync::LazyLock<regex::Regex> =
     std::sync::LazyLock::new(|| regex::Regex::new(r"@([\w./\-]+)").unwrap());
 
-/// Process @file references in input with security validation
-fn process_file_refs(input: &str) -> String {
+/// A request as typed, and with its @file references expanded
+struct Request {
+    /// What the user typed; the cache matches similar requests on this
+    typed: String,
+    /// With referenced files inlined; this is what gets routed and sent
+    expanded: String,
+    /// The inlined files, hashed so cached answers about them go stale
+    files: Vec<FileRef>,
+}
+
+impl Request {
+    fn new(input: &str) -> Self {
+        let (expanded, files) = expand_file_refs(input);
+        Self {
+            typed: input.to_string(),
+            expanded,
+            files,
+        }
+    }
+}
+
+/// Process @file references in input with security validation, returning
+/// the expanded input and the files read
+fn expand_file_refs(input: &str) -> (String, Vec<FileRef>) {
     let mut result = input.to_string();
+    let mut files: Vec<FileRef> = Vec::new();
 
     for cap in FILE_REF_REGEX.captures_iter(input) {
         let path_str = &cap[1];
@@ -438,13 +914,17 @@ fn process_file_refs(input: &str) -> String {
         }
 
         // Safe to read
+        if files.iter().any(|file| file.path == path_str) {
+            continue;
+        }
         if let Ok(content) = std::fs::read_to_string(path) {
             let replacement = format!("\n--- {} ---\n{}\n---", path_str, content);
             result = result.replace(&format!("@{}", path_str), &replacement);
+            files.push(FileRef::new(path_str, content.as_bytes()));
         }
     }
 
-    result
+    (result, files)
 }
 
 /// Find custom command in ~/.workyterm/commands/
@@ -576,6 +1056,12 @@ fn print_help() {
         ("/model", "Show available models"),
         ("/cost", "Show token usage and estimated cost"),
         ("/context", "Show context usage"),
+        ("/why", "Explain how the last request was routed"),
+        ("/good", "Rate the last answer as good (adaptive routing)"),
+        ("/bad", "Rate the last answer as bad (adaptive routing)"),
+        ("/routing", "reset [task] - forget recorded routing outcomes"),
+        ("/switch", "<member> | auto - pin the conversation to a member"),
+        ("/cache", "on | off | clear - toggle or empty the response cache"),
         ("/compact", "Compress conversation context"),
         ("/config", "Show configuration path"),
         ("/init", "Create CLAUDE.md in current directory"),
@@ -630,8 +1116,9 @@ fn print_team(team: &SupportTeam) {
             "○".bright_black()
         };
 
-        println!("  {} {:8} {:15} {}",
+        println!("  {} {} {:8} {:15} {}",
             status,
+            team::get_member_icon(&member.role).bright_black(),
             member.name.bold(),
             member.role,
             member.provider_type.dimmed()
@@ -652,7 +1139,7 @@ fn print_models(team: &SupportTeam) {
     }
 }
 
-fn print_cost(session: &Session) {
+fn print_cost(team: &SupportTeam, session: &Session) {
     println!();
     println!("{}", "Token Usage".bold());
     println!("{}", "───────────".dimmed());
@@ -665,6 +1152,14 @@ fn print_cost(session: &Session) {
     let cost = (session.tokens_in as f64 * 0.000003) + (session.tokens_out as f64 * 0.000015);
     println!("  Est Cost: ${:.4}", cost);
 
+    if let Some(budget) = team.budget() {
+        println!(
+            "  Budget:   {:>8.1} of {} units left",
+            budget.remaining(),
+            budget.limit
+        );
+    }
+
     println!();
     println!("{}", "(Note: Using CLI tools - actual cost via provider accounts)".dimmed());
 }
@@ -691,65 +1186,595 @@ fn print_context(session: &Session) {
     println!("  {} / {} tokens", total, max_context);
 }
 
+fn print_why(session: &Session) {
+    println!();
+    match session.last_routing {
+        Some((ref request, ref explanation)) => {
+            let preview: String = request.chars().take(60).collect();
+            let ellipsis = if request.chars().count() > 60 { "..." } else { "" };
+            println!("{} \"{}{}\"", "Routing for".bold(), preview, ellipsis);
+            println!("{}", explanation.render());
+        }
+        None => println!("{}", "No request routed yet.".dimmed()),
+    }
+}
+
+/// `workyterm prompts list|show|render`
+async fn run_prompts_command(
+    action: PromptsAction,
+    config: &Config,
+    vars: Vec<(String, String)>,
+) -> Result<()> {
+    match action {
+        PromptsAction::List => {
+            let library = team::PromptLibrary::load();
+            for template in library.list() {
+                let note = if template.shadowed { "  (shadowed)" } else { "" };
+                println!(
+                    "  {:24} {}{}",
+                    template.name.cyan(),
+                    template.path.display().to_string().dimmed(),
+                    note.yellow()
+                );
+            }
+            println!("  {:24} {}", "builtin".cyan(), "(used when no file applies)".dimmed());
+        }
+        PromptsAction::Show { name } => {
+            let library = team::PromptLibrary::load();
+            let template = match name.as_str() {
+                "builtin" => team::Template::builtin(),
+                _ => library.find(&name).ok_or_else(|| {
+                    anyhow::anyhow!("No template '{}'; see `workyterm prompts list`", name)
+                })?,
+            };
+            if let Some(ref path) = template.path {
+                eprintln!("{}", path.display().to_string().dimmed());
+            }
+            println!("{}", template.text);
+        }
+        PromptsAction::Render { task, member, request } => {
+            let mut team = SupportTeam::new_async(config).await;
+            team.set_prompt_vars(vars);
+            let (request, _) = expand_file_refs(&request.join(" "));
+
+            let hint = task.as_deref().map(hint_to_task_type);
+            let explanation = team.explain_route(&request, hint, None);
+            let name = member.or(explanation.member.clone()).ok_or_else(|| {
+                anyhow::anyhow!("No team member available to render for")
+            })?;
+            let member = team
+                .get_members()
+                .iter()
+                .find(|m| m.name.eq_ignore_ascii_case(&name))
+                .ok_or_else(|| anyhow::anyhow!("No team member named {}", name))?;
+
+            let template = team
+                .prompt_library()
+                .template_for(explanation.task_type, &member.role);
+            eprintln!(
+                "{}",
+                format!(
+                    "{} → {} ({}) | template: {}",
+                    explanation.task_type.display_name(),
+                    member.name,
+                    member.role,
+                    template.name
+                )
+                .dimmed()
+            );
+            let (prompt, missing) = team.render_prompt(&request, explanation.task_type, member);
+            for name in missing {
+                eprintln!(
+                    "{} {{{{{}}}}} is not set; pass --var {}=...",
+                    "Warning:".yellow(),
+                    name,
+                    name
+                );
+            }
+            println!("{}", prompt);
+        }
+    }
+    Ok(())
+}
+
+/// `workyterm config show [--origin]`
+fn run_config_command(action: ConfigAction, layers: &Layers) -> Result<()> {
+    match action {
+        ConfigAction::Show { origin } => {
+            let settings = layers.settings();
+            let lines: Vec<String> = settings
+                .iter()
+                .map(|(key, value, _)| {
+                    let value = if config::is_secret(key, value) {
+                        "\"********\"".to_string()
+                    } else {
+                        value.to_string()
+                    };
+                    format!("{} = {}", key, value)
+                })
+                .collect();
+            let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+            for (line, (_, _, layer)) in lines.iter().zip(&settings) {
+                if origin {
+                    let pad = width - line.chars().count();
+                    println!("{}{}  {}", line, " ".repeat(pad), format!("# {}", layer).dimmed());
+                } else {
+                    println!("{}", line);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `workyterm cache stats|prune|list|show|rm|export|import|verify|rotate-key`
+fn run_cache_command(action: CacheAction, cache: &ResponseCache, config: &Config) -> Result<()> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let limit = |value: u64, text: String| {
+        if value == 0 { "no limit".to_string() } else { text }
+    };
+
+    match action {
+        CacheAction::Stats => {
+            let stats = cache.stats();
+            println!();
+            println!("{}", "Response Cache".bold());
+            println!("{}", "──────────────".dimmed());
+            println!(
+                "  Entries:  {:>8} ({} active, {} expired)",
+                stats.total_entries,
+                stats.active_entries(),
+                stats.expired_entries
+            );
+            println!(
+                "  Limit:    {:>8}",
+                limit(stats.max_entries as u64, stats.max_entries.to_string())
+            );
+            println!("  Size:     {:>8}", format_bytes(stats.total_bytes as u64));
+            println!("  Limit:    {:>8}", limit(stats.max_bytes, format_bytes(stats.max_bytes)));
+            println!("  Namespace: {}", cache.namespace());
+            println!("  {}", cache.location().display().to_string().dimmed());
+            if !cache.is_enabled() {
+                println!("  {}", "(caching is disabled for this run)".dimmed());
+            }
+        }
+        CacheAction::Prune => {
+            let count = cache.prune()?;
+            println!("{} Removed {} entries (expired or over the limits)", "✓".green(), count);
+        }
+        CacheAction::List => {
+            let mut entries = cache.entries()?;
+            entries.sort_by_key(|stored| std::cmp::Reverse(stored.last_used()));
+            for stored in &entries {
+                let entry = &stored.entry;
+                let preview: String = entry
+                    .query
+                    .lines()
+                    .rev()
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or("")
+                    .chars()
+                    .take(48)
+                    .collect();
+                let note = if entry.is_expired() { " expired" } else { "" };
+                println!(
+                    "  {}  {:10} {:12} {:10} {:>9} {:>8}{}  {}",
+                    short_key(&stored.key).cyan(),
+                    entry.namespace,
+                    entry.provider,
+                    entry.task_type,
+                    format_age(now.saturating_sub(stored.last_used())),
+                    format_bytes(stored.bytes),
+                    note.yellow(),
+                    preview.dimmed()
+                );
+            }
+            if entries.is_empty() {
+                println!("{}", "The cache is empty".dimmed());
+            }
+        }
+        CacheAction::Show { key } => {
+            let stored = cache.find(&key)?;
+            let entry = &stored.entry;
+            eprintln!("{}", format!("Key:       {}", stored.key).dimmed());
+            let model = if entry.model.is_empty() { "default" } else { &entry.model };
+            eprintln!(
+                "{}",
+                format!("Provider:  {} (model: {})", entry.provider, model).dimmed()
+            );
+            eprintln!("{}", format!("Task:      {}", entry.task_type).dimmed());
+            eprintln!("{}", format!("Namespace: {}", entry.namespace).dimmed());
+            eprintln!(
+                "{}",
+                format!(
+                    "Created:   {} ago, last used {} ago{}",
+                    format_age(now.saturating_sub(entry.created_at)),
+                    format_age(now.saturating_sub(stored.last_used())),
+                    if entry.is_expired() { ", expired" } else { "" }
+                )
+                .dimmed()
+            );
+            eprintln!();
+            eprintln!("{}", "Prompt".bold());
+            eprintln!("{}", entry.query);
+            eprintln!();
+            eprintln!("{}", "Response".bold());
+            println!("{}", entry.response);
+        }
+        CacheAction::Rm { key } => {
+            let key = cache.remove(&key)?;
+            println!("{} Removed {}", "✓".green(), key);
+        }
+        CacheAction::Export { bundle, all, plaintext } => {
+            let count = cache.export(&bundle, all, plaintext)?;
+            if cache.is_encrypted() {
+                eprintln!(
+                    "{} {} is not encrypted; the entries in it can be read by anyone with the file",
+                    "⚠".yellow(),
+                    bundle.display()
+                );
+            }
+            let scope = if all {
+                "all namespaces".to_string()
+            } else {
+                format!("namespace '{}'", cache.namespace())
+            };
+            println!(
+                "{} Exported {} entries from {} to {}",
+                "✓".green(),
+                count,
+                scope,
+                bundle.display()
+            );
+        }
+        CacheAction::Import { bundle } => {
+            let summary = cache.import(&bundle)?;
+            println!(
+                "{} Imported {}: {} added, {} updated, {} kept (cached copy as new or newer)",
+                "✓".green(),
+                bundle.display(),
+                summary.added,
+                summary.updated,
+                summary.kept
+            );
+        }
+        CacheAction::Verify => {
+            let report = cache.verify()?;
+            for problem in &report.store_problems {
+                println!("{} {}", "✗".red(), problem);
+            }
+            for (key, reason) in &report.failed {
+                println!("{} {}  {}", "✗".red(), short_key(key).cyan(), reason);
+            }
+            println!(
+                "{} Checked {} entries: {} encrypted, {} plain, {} failed",
+                if report.is_ok() { "✓".green() } else { "✗".red() },
+                report.checked,
+                report.encrypted,
+                report.checked - report.encrypted - report.failed.len(),
+                report.failed.len()
+            );
+            if !report.is_ok() {
+                anyhow::bail!("The cache failed verification; remove bad entries with `cache rm`");
+            }
+        }
+        CacheAction::RotateKey => {
+            let (key_id, count) = cache.rotate_key()?;
+            println!("{} Resealed {} entries under key {}", "✓".green(), count, key_id);
+            if config.cache.encryption.passphrase()?.is_some() {
+                println!(
+                    "  {}",
+                    format!(
+                        "The key now derives from ${}; update the configured passphrase to match",
+                        cache::NEW_PASSPHRASE_ENV
+                    )
+                    .dimmed()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Similarity for JSON output, to four decimal places
+fn round_similarity(similarity: f32) -> f64 {
+    (similarity as f64 * 10_000.0).round() / 10_000.0
+}
+
+/// `512 B`, `3.2 KB`, `48.0 MB`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// The first 12 characters of a cache key, or all of a shorter or odd one
+/// (file stems with the files backend)
+fn short_key(key: &str) -> &str {
+    key.get(..12).unwrap_or(key)
+}
+
+/// `45s`, `12m`, `3h`, `2d`
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3_599 => format!("{}m", secs / 60),
+        3_600..=86_399 => format!("{}h", secs / 3_600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// `workyterm router train|eval`
+fn run_router_command(action: RouterAction, config: &Config) -> Result<()> {
+    let model_path = team::TaskClassifier::default_path();
+
+    match action {
+        RouterAction::Train { data, no_history, holdout } => {
+            let mut examples = Vec::new();
+            for path in &data {
+                examples.extend(team::read_examples(std::path::Path::new(path))?);
+            }
+            let from_files = examples.len();
+            if !no_history {
+                let history = team::AdaptiveRouter::load(&config.routing.adaptive);
+                examples.extend(team::history_examples(history.outcomes()));
+            }
+            println!(
+                "Training on {} examples ({} from files, {} from past sessions)",
+                examples.len(),
+                from_files,
+                examples.len() - from_files
+            );
+
+            let (train, held) = team::split_holdout(&examples, holdout);
+            if !held.is_empty() {
+                let model = team::TaskClassifier::train(&train)?;
+                let matrix = team::ConfusionMatrix::evaluate(&held, |text| model.predict(text).0);
+                let baseline = team::ConfusionMatrix::evaluate(&held, team::analyze_request);
+
+                println!();
+                println!("{} ({} held-out examples)", "Evaluation".bold(), held.len());
+                println!("{}", matrix.render());
+                println!(
+                    "{}",
+                    format!("keyword classifier: {:.1}%", baseline.accuracy() * 100.0).dimmed()
+                );
+                println!();
+            }
+
+            // The saved model learns from every example, held-out ones included
+            let model = team::TaskClassifier::train(&examples)?;
+            model.save(&model_path)?;
+            let classes: Vec<String> = model
+                .classes()
+                .iter()
+                .map(|c| format!("{:?}", c).to_lowercase())
+                .collect();
+            println!(
+                "{} Saved classifier ({}) to {}",
+                "✓".green(),
+                classes.join(", "),
+                model_path.display()
+            );
+        }
+        RouterAction::Eval { data } => {
+            let model = team::TaskClassifier::load(&model_path).ok_or_else(|| {
+                anyhow::anyhow!("No trained classifier; run `workyterm router train` first")
+            })?;
+            let examples = team::read_examples(std::path::Path::new(&data))?;
+
+            let matrix = team::ConfusionMatrix::evaluate(&examples, |text| model.predict(text).0);
+            let baseline = team::ConfusionMatrix::evaluate(&examples, team::analyze_request);
+
+            println!("{} ({} examples)", "Evaluation".bold(), examples.len());
+            println!("{}", matrix.render());
+            println!(
+                "{}",
+                format!("keyword classifier: {:.1}%", baseline.accuracy() * 100.0).dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Result of a direct (print/JSON/quiet) request
+struct DirectResponse {
+    response: String,
+    /// How the answer was found in the cache, if it was
+    cached: Option<HitKind>,
+    /// Candidates and scores, when the answer came from best-of-N sampling
+    best_of: Option<team::BestOf>,
+    /// Majority answer, vote share and samples, when the answer came from voting
+    vote: Option<team::Vote>,
+    /// Config routing rule that decided the route, if one matched
+    rule: Option<team::RuleMatch>,
+}
+
+/// Per-request sampling overrides from the command line
+#[derive(Debug, Clone, Copy, Default)]
+struct SamplingFlags {
+    best_of: Option<u32>,
+    vote: Option<u32>,
+}
+
+/// How an answer is sampled, as part of its cache key
+fn cache_strategy(votes: u32, samples: u32) -> String {
+    if votes > 1 {
+        format!("vote-{}", votes)
+    } else if samples > 1 {
+        format!("best-of-{}", samples)
+    } else {
+        "single".to_string()
+    }
+}
+
 /// Process a request directly for programmatic use (JSON/quiet modes)
-/// Supports model override, task type hints, and response caching
+/// Supports model override, task type hints, best-of-N sampling, self-consistency
+/// voting, and response caching
 async fn process_request_direct(
     team: &mut SupportTeam,
     session: &mut Session,
-    request: &str,
+    request: &Request,
     model_override: Option<&str>,
     task_hint: Option<&str>,
+    flags: SamplingFlags,
     cache: &ResponseCache,
-) -> Result<(String, bool)> {
+) -> Result<DirectResponse> {
+    let typed = request.typed.as_str();
+    let files = request.files.clone();
+    let request = request.expanded.as_str();
     debug_log!("Direct processing: \"{}\"", request);
 
     session.messages += 1;
     session.tokens_in += Session::estimate_tokens(request);
 
-    // Get the task type (from hint or auto-detect)
-    let task_type = task_hint
-        .map(hint_to_task_type)
-        .unwrap_or_else(|| team::analyze_request_detailed(request).primary_type);
+    // Route by -t/-m overrides, then config rules, then the classifier
+    let model_override = model_override.map(normalize_model_name);
+    let explanation = team.explain_route(
+        request,
+        task_hint.map(hint_to_task_type),
+        model_override.as_deref(),
+    );
+    let route = explanation.route();
+    session.last_routing = Some((request.to_string(), explanation));
+    let task_type = route.task_type;
+    let provider_type = route.provider.clone().unwrap_or_else(|| session.model.clone());
+
+    if let Some(ref rule) = route.rule {
+        debug_log!(
+            "Routing rule '{}' matched: task {:?}, member {:?}, provider {:?}",
+            rule.name,
+            rule.task,
+            rule.member,
+            rule.provider
+        );
+    }
+    debug_log!("Using provider: {} for task: {:?}", provider_type, task_type);
 
-    // Find the appropriate member/provider
-    let provider_type = if let Some(model) = model_override {
-        normalize_model_name(model)
+    // An explicit --best-of overrides configured voting, and voting wins over
+    // configured best-of for the task types that vote
+    let votes = match (flags.vote, flags.best_of) {
+        (Some(n), _) => n,
+        (None, Some(_)) => 1,
+        (None, None) => team.vote_samples_for(task_type),
+    };
+    let samples = if votes > 1 {
+        1
     } else {
-        // Use task-based routing
-        team.find_member_for_task(task_type)
-            .map(|m| m.provider_type.clone())
-            .unwrap_or_else(|| session.model.clone())
+        flags.best_of.unwrap_or_else(|| team.best_of_for(task_type))
     };
-
-    debug_log!("Using provider: {} for task: {:?}", provider_type, task_type);
+    let strategy = cache_strategy(votes, samples);
+    let cache_key = team.cache_key(request, &route, &provider_type, &strategy).with_files(files);
 
     // Check cache first
-    if let Some(cached) = cache.get(request, &provider_type) {
-        debug_log!("Cache hit!");
-        session.tokens_out += Session::estimate_tokens(&cached);
+    if let Some(hit) = cache.lookup(&cache_key, typed).await {
+        debug_log!("Cache hit ({:?})", hit.kind);
+        session.tokens_out += Session::estimate_tokens(&hit.response);
         session.model = provider_type;
-        return Ok((cached, true)); // true = from cache
+        return Ok(DirectResponse {
+            response: hit.response,
+            cached: Some(hit.kind),
+            best_of: None,
+            vote: None,
+            rule: route.rule.clone(),
+        });
+    }
+    if cache.mode() == CacheMode::Offline {
+        return Err(CacheMiss.into());
     }
 
-    // Plan and process the request
-    let tasks = team.plan_request(request);
+    if votes > 1 {
+        debug_log!("Voting with {} samples", votes);
+        let result = team.vote(request, task_type, &provider_type, votes).await?;
+        for sample in &result.samples {
+            debug_log!(
+                "Sample {} ({}): answer {:?}, {}ms",
+                sample.member,
+                sample.provider,
+                sample.answer,
+                sample.latency_ms
+            );
+        }
+
+        session.tokens_out += Session::estimate_tokens(&result.response);
+        session.model = provider_type;
+
+        if let Err(e) = cache.store(&cache_key, typed, &result.response).await {
+            debug_log!("Failed to cache response: {}", e);
+        }
+
+        return Ok(DirectResponse {
+            response: result.response.clone(),
+            cached: None,
+            best_of: None,
+            vote: Some(result),
+            rule: route.rule.clone(),
+        });
+    }
 
-    if tasks.is_empty() {
+    if samples > 1 {
+        debug_log!("Best of {}: sampling candidates", samples);
+        let result = team.best_of(request, task_type, &provider_type, samples).await?;
+        for candidate in &result.candidates {
+            debug_log!(
+                "Candidate {} ({}): score {:?}, {}ms",
+                candidate.member,
+                candidate.provider,
+                candidate.score,
+                candidate.latency_ms
+            );
+        }
+
+        session.tokens_out += Session::estimate_tokens(&result.answer);
+        session.model = provider_type;
+
+        if let Err(e) = cache.store(&cache_key, typed, &result.answer).await {
+            debug_log!("Failed to cache response: {}", e);
+        }
+
+        return Ok(DirectResponse {
+            response: result.answer.clone(),
+            cached: None,
+            best_of: Some(result),
+            vote: None,
+            rule: route.rule.clone(),
+        });
+    }
+
+    if route.member.is_none() {
         return Err(anyhow::anyhow!("No provider available"));
     }
 
     // Process without streaming for direct mode
-    match team.handle_request(request).await {
+    match team.handle_route(request, &route).await {
         Ok((response, _completed_tasks)) => {
             session.tokens_out += Session::estimate_tokens(&response);
             session.model = provider_type.clone();
 
             // Store in cache
-            if let Err(e) = cache.set(request, &provider_type, &response) {
+            if let Err(e) = cache.store(&cache_key, typed, &response).await {
                 debug_log!("Failed to cache response: {}", e);
             }
 
-            Ok((response, false)) // false = not from cache
+            Ok(DirectResponse {
+                response,
+                cached: None,
+                best_of: None,
+                vote: None,
+                rule: route.rule.clone(),
+            })
         }
         Err(e) => {
             debug_log!("Error: {}", e);
@@ -761,9 +1786,13 @@ async fn process_request_direct(
 async fn process_request(
     team: &mut SupportTeam,
     session: &mut Session,
-    request: &str,
+    cache: &ResponseCache,
+    request: &Request,
     quiet: bool
 ) -> Result<String> {
+    let typed = request.typed.as_str();
+    let files = &request.files;
+    let request = request.expanded.as_str();
     debug_log!("Processing: \"{}\"", request);
     let start = Instant::now();
 
@@ -777,24 +1806,195 @@ async fn process_request(
         io::stdout().flush()?;
     }
 
-    // Plan the request
-    let tasks = team.plan_request(request);
+    // Plan the request, keeping follow-ups in the current thread
+    let explanation = team.explain_turn(request, session.thread.as_ref());
+    let route = explanation.route();
+    let branch = explanation.branch;
+    session.last_routing = Some((request.to_string(), explanation));
+    let previous = session.thread.as_ref().map(|t| t.member.clone());
+    session.thread = team::Thread::after(&route, session.thread.as_ref());
+    let tasks = team.plan_route(request, &route);
     debug_log!("Tasks: {}", tasks.len());
+    if let Some(ref rule) = route.rule {
+        debug_log!("Routing rule '{}' matched", rule.name);
+    }
 
     if !quiet {
-        // Show what's happening
+        // Show what's happening, and when the conversation changes hands
+        let note = match (branch, &route.rule) {
+            (team::SelectionBranch::Pinned, _) => " (pinned)".to_string(),
+            (team::SelectionBranch::FollowUp, _) => " (follow-up)".to_string(),
+            (_, Some(rule)) => format!(" (rule: {})", rule.name),
+            _ => String::new(),
+        };
         for task in &tasks {
             if let Some(ref assignee) = task.assigned_to {
-                println!("{} {} → {}",
+                let handoff = match previous {
+                    Some(ref from) if from != assignee => format!(" (from {})", from),
+                    _ => String::new(),
+                };
+                let member = team.get_members().iter().find(|m| &m.name == assignee);
+                let (icon, personality) = match member {
+                    Some(member) => (
+                        team::get_member_icon(&member.role),
+                        Some(team.personality(member)),
+                    ),
+                    None => ("", None),
+                };
+                let greeting = personality
+                    .as_ref()
+                    .map(|p| format!(" {}", p.greeting))
+                    .unwrap_or_default();
+                println!("{} {} → {} {}{}{}{}",
                     "".bright_black(),
                     task.task_type.display_name().dimmed(),
-                    assignee.cyan()
+                    icon.bright_black(),
+                    assignee.cyan(),
+                    handoff.yellow(),
+                    note.bright_black(),
+                    greeting.italic()
                 );
+                if let Some(personality) = personality {
+                    println!("  {}", personality.working_message.dimmed());
+                }
             }
         }
         println!();
     }
 
+    // Task types configured for voting or best-of-N sample instead of streaming
+    let routed = tasks.first().and_then(|task| {
+        let assignee = task.assigned_to.as_ref()?;
+        let member = team.get_members().iter().find(|m| &m.name == assignee)?;
+        Some((task.task_type, member.provider_type.clone()))
+    });
+
+    // Answer from the cache when this request was seen before
+    let cache_key = routed.as_ref().map(|(task_type, provider)| {
+        let votes = team.vote_samples_for(*task_type);
+        let samples = if votes > 1 { 1 } else { team.best_of_for(*task_type) };
+        team.cache_key(request, &route, provider, &cache_strategy(votes, samples))
+            .with_files(files.clone())
+    });
+    if let Some(ref key) = cache_key {
+        if let Some(hit) = cache.lookup(key, typed).await {
+            debug_log!("Cache hit ({:?})", hit.kind);
+            session.tokens_out += Session::estimate_tokens(&hit.response);
+            team.remember_turn(request, &hit.response);
+            println!("{}", hit.response);
+
+            if !quiet {
+                let marker = match hit.kind {
+                    HitKind::Exact => "(cached)".to_string(),
+                    HitKind::Semantic(similarity) => format!(
+                        "(cached: similar request, similarity {:.2})",
+                        similarity
+                    ),
+                    HitKind::Request => "(cached: recorded through another provider)".to_string(),
+                };
+                println!();
+                println!("{}", marker.bright_black());
+            }
+            return Ok(hit.response);
+        }
+    }
+    if cache.mode() == CacheMode::Offline {
+        println!("{} {}", "✗".red(), CacheMiss);
+        return Ok(String::new());
+    }
+    if let Some((task_type, provider)) = routed.clone() {
+        let votes = team.vote_samples_for(task_type);
+        if votes > 1 {
+            debug_log!("Voting with {} samples via {}", votes, provider);
+            return match team.vote(request, task_type, &provider, votes).await {
+                Ok(vote) => {
+                    let elapsed = start.elapsed();
+                    for sample in &vote.samples {
+                        team.charge(&sample.provider);
+                    }
+                    session.tokens_out += Session::estimate_tokens(&vote.response);
+                    team.remember_turn(request, &vote.response);
+                    store_answer(cache, cache_key.as_ref(), typed, &vote.response).await;
+                    println!("{}", vote.response);
+
+                    if !quiet {
+                        let responded = vote.samples.iter().filter(|s| s.response.is_some());
+                        let answer = if vote.answer.is_empty() {
+                            "no final answer found"
+                        } else {
+                            vote.answer.as_str()
+                        };
+                        println!();
+                        println!("{}", format!(
+                            "(vote: {} | {:.0}% of {} samples agree | {:.1}s)",
+                            answer,
+                            vote.vote_share * 100.0,
+                            responded.count(),
+                            elapsed.as_secs_f64()
+                        ).bright_black());
+                    }
+                    Ok(vote.response)
+                }
+                Err(e) => {
+                    if !quiet {
+                        println!("{} {}", "Error:".red().bold(), e);
+                    }
+                    Err(e)
+                }
+            };
+        }
+    }
+
+    let sampled = routed.and_then(|(task_type, provider)| {
+        let samples = team.best_of_for(task_type);
+        (samples > 1).then_some((task_type, provider, samples))
+    });
+
+    if let Some((task_type, provider, samples)) = sampled {
+        debug_log!("Best of {} via {}", samples, provider);
+        let result = team.best_of(request, task_type, &provider, samples).await;
+        return match result {
+            Ok(best) => {
+                let elapsed = start.elapsed();
+                for candidate in &best.candidates {
+                    team.charge(&candidate.provider);
+                }
+                if let Some(ref judge) = best.judge {
+                    team.charge(judge);
+                }
+                session.tokens_out += Session::estimate_tokens(&best.answer);
+                team.remember_turn(request, &best.answer);
+                store_answer(cache, cache_key.as_ref(), typed, &best.answer).await;
+                println!("{}", best.answer);
+
+                if !quiet {
+                    let scores: Vec<String> = best
+                        .candidates
+                        .iter()
+                        .map(|c| match c.score {
+                            Some(score) => format!("{} {:.0}", c.member, score),
+                            None => c.member.clone(),
+                        })
+                        .collect();
+                    println!();
+                    println!("{}", format!(
+                        "(best of {}: {} | {:.1}s)",
+                        samples,
+                        scores.join(", "),
+                        elapsed.as_secs_f64()
+                    ).bright_black());
+                }
+                Ok(best.answer)
+            }
+            Err(e) => {
+                if !quiet {
+                    println!("{} {}", "Error:".red().bold(), e);
+                }
+                Err(e)
+            }
+        };
+    }
+
     // Process with streaming output
     debug_log!("Calling provider (streaming)...");
 
@@ -804,12 +2004,14 @@ async fn process_request(
         let _ = io::stdout().flush();
     });
 
-    match team.handle_request_streaming(request, callback).await {
+    match team.handle_route_streaming(request, &route, callback).await {
         Ok((response, _completed_tasks)) => {
             let elapsed = start.elapsed();
             debug_log!("Response in {:.2}s", elapsed.as_secs_f64());
 
             session.tokens_out += Session::estimate_tokens(&response);
+            team.remember_turn(request, &response);
+            store_answer(cache, cache_key.as_ref(), typed, &response).await;
 
             if !quiet {
                 // Show timing
@@ -830,6 +2032,20 @@ async fn process_request(
     }
 }
 
+/// Keep a REPL answer in the cache, if it has a key
+async fn store_answer(
+    cache: &ResponseCache,
+    key: Option<&CacheKey>,
+    request: &str,
+    response: &str,
+) {
+    if let Some(key) = key {
+        if let Err(e) = cache.store(key, request, response).await {
+            debug_log!("Failed to cache response: {}", e);
+        }
+    }
+}
+
 /// Render response with basic markdown formatting
 fn render_response(text: &str) {
     let mut in_code_block = false;
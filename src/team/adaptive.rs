@@ -0,0 +1,466 @@
+//! Adaptive routing - learn which providers actually perform per task type
+//!
+//! Every processed task records an outcome (provider, task type, latency,
+//! failure, and an optional `/good` or `/bad` rating). An epsilon-greedy
+//! bandit then prefers the provider with the best recent reward for each
+//! task type, trying the least-sampled one now and then so it keeps learning.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{SupportTeam, TaskType, TeamMember};
+use crate::config::AdaptiveRoutingConfig;
+
+/// Outcomes kept on disk; older ones are dropped when the log is rewritten
+const MAX_OUTCOMES: usize = 5_000;
+
+/// Characters of the request kept with an outcome, for classifier training
+const MAX_RECORDED_REQUEST: usize = 500;
+
+/// A user's verdict on an answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Good,
+    Bad,
+}
+
+/// What happened when a provider handled a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Outcome {
+    pub timestamp: u64,
+    pub provider: String,
+    pub member: String,
+    pub task_type: TaskType,
+    pub latency_ms: u64,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<Rating>,
+    /// Start of the request, used to train the task classifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+}
+
+impl Outcome {
+    pub fn new(
+        provider: &str,
+        member: &str,
+        task_type: TaskType,
+        latency_ms: u64,
+        success: bool,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            provider: provider.to_string(),
+            member: member.to_string(),
+            task_type,
+            latency_ms,
+            success,
+            rating: None,
+            request: None,
+        }
+    }
+
+    /// Keep the start of the request with the outcome
+    pub fn with_request(mut self, request: &str) -> Self {
+        self.request = Some(request.chars().take(MAX_RECORDED_REQUEST).collect());
+        self
+    }
+}
+
+/// Recent performance of one provider on one task type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArmStats {
+    pub provider: String,
+    pub samples: usize,
+    pub failures: usize,
+    /// Mean reward from 0 to 1, if there are any samples
+    pub reward: Option<f32>,
+}
+
+/// What the bandit decided for one request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AdaptiveDecision {
+    pub provider: String,
+    /// Whether this was an exploration pick rather than the best arm
+    pub explored: bool,
+    pub arms: Vec<ArmStats>,
+}
+
+/// Outcome log plus the bandit that reads it
+#[derive(Default)]
+pub struct AdaptiveRouter {
+    config: AdaptiveRoutingConfig,
+    /// Where outcomes are persisted; `None` keeps them in memory only
+    path: Option<PathBuf>,
+    outcomes: Vec<Outcome>,
+}
+
+impl AdaptiveRouter {
+    /// Load the outcome log from the data directory
+    pub fn load(config: &AdaptiveRoutingConfig) -> Self {
+        let path = Self::default_path();
+        let outcomes = fs::read_to_string(&path)
+            .map(|content| {
+                content
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            config: config.clone(),
+            path: Some(path),
+            outcomes,
+        }
+    }
+
+    /// A router that keeps its outcomes in memory only
+    #[cfg(test)]
+    pub fn in_memory(config: &AdaptiveRoutingConfig) -> Self {
+        Self {
+            config: config.clone(),
+            path: None,
+            outcomes: Vec::new(),
+        }
+    }
+
+    /// Outcome log location
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("workyterm")
+            .join("outcomes.jsonl")
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Whether outcomes are recorded at all; nothing is logged while adaptive
+    /// routing is off, unless requests are kept for training
+    pub fn is_recording(&self) -> bool {
+        self.config.enabled || self.config.record_requests
+    }
+
+    /// All recorded outcomes, oldest first
+    pub fn outcomes(&self) -> &[Outcome] {
+        &self.outcomes
+    }
+
+    /// Record an outcome in memory and append it to the log
+    pub fn record(&mut self, outcome: Outcome) -> Result<()> {
+        let line = serde_json::to_string(&outcome)?;
+        self.outcomes.push(outcome);
+
+        if self.outcomes.len() > MAX_OUTCOMES {
+            return self.persist();
+        }
+        if let Some(ref path) = self.path {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Rate the most recent outcome; returns it, or `None` if there is none
+    pub fn rate_last(&mut self, rating: Rating) -> Result<Option<&Outcome>> {
+        let Some(last) = self.outcomes.last_mut() else {
+            return Ok(None);
+        };
+        last.rating = Some(rating);
+        self.persist()?;
+        Ok(self.outcomes.last())
+    }
+
+    /// Forget outcomes for one task type, or all of them
+    ///
+    /// Returns how many outcomes were removed.
+    pub fn reset(&mut self, task_type: Option<TaskType>) -> Result<usize> {
+        let before = self.outcomes.len();
+        self.outcomes.retain(|o| task_type.is_some_and(|t| t != o.task_type));
+        self.persist()?;
+        Ok(before - self.outcomes.len())
+    }
+
+    /// Rewrite the log from memory, keeping the newest outcomes
+    fn persist(&mut self) -> Result<()> {
+        if self.outcomes.len() > MAX_OUTCOMES {
+            let excess = self.outcomes.len() - MAX_OUTCOMES;
+            self.outcomes.drain(..excess);
+        }
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut content = String::new();
+        for outcome in &self.outcomes {
+            content.push_str(&serde_json::to_string(outcome)?);
+            content.push('\n');
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Reward for one outcome, from 0 to 1
+    ///
+    /// A rating overrides everything; otherwise failures score 0 and slow
+    /// successes lose up to `latency_weight`.
+    fn reward(&self, outcome: &Outcome) -> f32 {
+        match outcome.rating {
+            Some(Rating::Good) => 1.0,
+            Some(Rating::Bad) => 0.0,
+            None if !outcome.success => 0.0,
+            None => {
+                let target = self.config.latency_target_ms.max(1) as f32;
+                let slowness = (outcome.latency_ms as f32 / target).min(1.0);
+                1.0 - self.config.latency_weight * slowness
+            }
+        }
+    }
+
+    /// Recent stats for each candidate provider on a task type
+    pub fn arms(&self, task_type: TaskType, candidates: &[String]) -> Vec<ArmStats> {
+        let recent: Vec<&Outcome> = self
+            .outcomes
+            .iter()
+            .rev()
+            .filter(|o| o.task_type == task_type)
+            .take(self.config.window)
+            .collect();
+
+        candidates
+            .iter()
+            .map(|provider| {
+                let mine: Vec<&&Outcome> =
+                    recent.iter().filter(|o| &o.provider == provider).collect();
+                let total: f32 = mine.iter().map(|o| self.reward(o)).sum();
+                ArmStats {
+                    provider: provider.clone(),
+                    samples: mine.len(),
+                    failures: mine.iter().filter(|o| !o.success).count(),
+                    reward: (!mine.is_empty()).then(|| total / mine.len() as f32),
+                }
+            })
+            .collect()
+    }
+
+    /// Pick a provider for a task type
+    ///
+    /// `roll` is a uniform draw in `[0, 1)`; below `exploration` the
+    /// least-sampled candidate is tried. Otherwise the best-rewarded provider
+    /// with enough samples wins, and `fallback` (the static choice) is kept
+    /// until some provider has enough samples or when it is as good as the best.
+    pub fn choose(
+        &self,
+        task_type: TaskType,
+        fallback: &str,
+        candidates: &[String],
+        roll: f32,
+    ) -> AdaptiveDecision {
+        let arms = self.arms(task_type, candidates);
+
+        if candidates.len() > 1 && roll < self.config.exploration {
+            if let Some(least) = arms.iter().min_by_key(|a| a.samples) {
+                return AdaptiveDecision {
+                    provider: least.provider.clone(),
+                    explored: true,
+                    arms,
+                };
+            }
+        }
+
+        let proven = |arm: &&ArmStats| arm.samples >= self.config.min_samples.max(1);
+        let best = arms.iter().filter(proven).max_by(|a, b| {
+            a.reward
+                .partial_cmp(&b.reward)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                // Prefer the static choice on ties
+                .then((a.provider == fallback).cmp(&(b.provider == fallback)))
+        });
+        let provider = best
+            .map(|arm| arm.provider.clone())
+            .unwrap_or_else(|| fallback.to_string());
+
+        AdaptiveDecision {
+            provider,
+            explored: false,
+            arms,
+        }
+    }
+}
+
+impl SupportTeam {
+    /// Record how a task went, warning rather than failing if the log can't be written
+    pub(super) fn record_outcome(&mut self, mut outcome: Outcome) {
+        if !self.adaptive.is_recording() {
+            return;
+        }
+        if !self.adaptive.config.record_requests {
+            outcome.request = None;
+        }
+        if let Err(e) = self.adaptive.record(outcome) {
+            eprintln!("Warning: Could not record routing outcome: {}", e);
+        }
+    }
+
+    /// Rate the last answer (`/good`, `/bad`); returns the rated outcome
+    pub fn rate_last(&mut self, rating: Rating) -> Result<Option<Outcome>> {
+        if !self.adaptive.is_recording() {
+            return Ok(None);
+        }
+        Ok(self.adaptive.rate_last(rating)?.cloned())
+    }
+
+    /// Forget recorded outcomes for one task type, or all of them
+    pub fn reset_outcomes(&mut self, task_type: Option<TaskType>) -> Result<usize> {
+        self.adaptive.reset(task_type)
+    }
+
+    /// Let the bandit reconsider a statically chosen member
+    ///
+    /// Returns the member to use instead, if any, and the bandit's decision.
+    pub(super) fn adaptive_pick(
+        &self,
+        task_type: TaskType,
+        chosen: &TeamMember,
+    ) -> Option<(Option<&TeamMember>, AdaptiveDecision)> {
+        if !self.adaptive.is_enabled() {
+            return None;
+        }
+
+        let candidates = self.available_providers();
+        let decision = self.adaptive.choose(
+            task_type,
+            &chosen.provider_type,
+            &candidates,
+            exploration_roll(),
+        );
+        let replacement = (decision.provider != chosen.provider_type)
+            .then(|| self.member_on(&decision.provider, task_type))
+            .flatten();
+        Some((replacement, decision))
+    }
+}
+
+/// A uniform draw in `[0, 1)` for exploration, from the clock
+pub fn exploration_roll() -> f32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    // Scramble the low bits so consecutive calls don't track each other
+    let mixed = nanos.wrapping_mul(2_654_435_761) >> 8;
+    (mixed % 10_000) as f32 / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(outcomes: Vec<Outcome>) -> AdaptiveRouter {
+        AdaptiveRouter {
+            config: AdaptiveRoutingConfig {
+                enabled: true,
+                min_samples: 3,
+                ..AdaptiveRoutingConfig::default()
+            },
+            path: None,
+            outcomes,
+        }
+    }
+
+    fn outcomes(provider: &str, task_type: TaskType, results: &[bool]) -> Vec<Outcome> {
+        results
+            .iter()
+            .map(|&success| Outcome::new(provider, "Member", task_type, 2_000, success))
+            .collect()
+    }
+
+    fn candidates() -> Vec<String> {
+        vec!["gemini-cli".to_string(), "codex-cli".to_string()]
+    }
+
+    #[test]
+    fn test_choose_shifts_away_from_failing_provider() {
+        let mut log = outcomes("gemini-cli", TaskType::Research, &[true, false, false, true]);
+        log.extend(outcomes("codex-cli", TaskType::Research, &[true, true, true]));
+        let router = router(log);
+
+        let decision = router.choose(TaskType::Research, "gemini-cli", &candidates(), 0.99);
+        assert_eq!(decision.provider, "codex-cli");
+        assert!(!decision.explored);
+        assert_eq!(decision.arms[0].failures, 2);
+
+        // Outcomes for other task types don't count
+        let decision = router.choose(TaskType::Write, "gemini-cli", &candidates(), 0.99);
+        assert_eq!(decision.provider, "gemini-cli");
+    }
+
+    #[test]
+    fn test_choose_keeps_static_choice_until_proven() {
+        let router = router(outcomes("codex-cli", TaskType::Research, &[true, true]));
+        let decision = router.choose(TaskType::Research, "gemini-cli", &candidates(), 0.99);
+        assert_eq!(decision.provider, "gemini-cli");
+    }
+
+    #[test]
+    fn test_choose_explores_least_sampled() {
+        let router = router(outcomes("gemini-cli", TaskType::Research, &[true; 4]));
+        let decision = router.choose(TaskType::Research, "gemini-cli", &candidates(), 0.01);
+        assert_eq!(decision.provider, "codex-cli");
+        assert!(decision.explored);
+    }
+
+    #[test]
+    fn test_outcomes_recorded_only_when_enabled() {
+        let mut config = crate::config::Config::default();
+        let mut team = SupportTeam::for_test(&["codex-cli"], &config);
+        team.record_outcome(Outcome::new("codex-cli", "Cody", TaskType::Solve, 100, true));
+        assert!(team.adaptive.outcomes().is_empty());
+        assert!(team.rate_last(Rating::Good).unwrap().is_none());
+
+        config.routing.adaptive.enabled = true;
+        let mut team = SupportTeam::for_test(&["codex-cli"], &config);
+        let outcome = Outcome::new("codex-cli", "Cody", TaskType::Solve, 100, true);
+        team.record_outcome(outcome.clone().with_request("fix @src/secret.rs"));
+        assert_eq!(team.adaptive.outcomes().len(), 1);
+        // Request text is only kept when asked for
+        assert_eq!(team.adaptive.outcomes()[0].request, None);
+
+        config.routing.adaptive.enabled = false;
+        config.routing.adaptive.record_requests = true;
+        let mut team = SupportTeam::for_test(&["codex-cli"], &config);
+        team.record_outcome(outcome.with_request("fix @src/secret.rs"));
+        assert_eq!(team.adaptive.outcomes()[0].request.as_deref(), Some("fix @src/secret.rs"));
+    }
+
+    #[test]
+    fn test_ratings_and_reset() {
+        let mut router = router(outcomes("gemini-cli", TaskType::Research, &[true, true]));
+        router.record(Outcome::new("codex-cli", "Cody", TaskType::Solve, 100, true)).unwrap();
+
+        let rated = router.rate_last(Rating::Bad).unwrap().unwrap();
+        assert_eq!(rated.provider, "codex-cli");
+        let arms = router.arms(TaskType::Solve, &["codex-cli".to_string()]);
+        assert_eq!(arms[0].reward, Some(0.0));
+
+        assert_eq!(router.reset(Some(TaskType::Solve)).unwrap(), 1);
+        assert_eq!(router.reset(None).unwrap(), 2);
+        assert!(router.rate_last(Rating::Good).unwrap().is_none());
+    }
+}
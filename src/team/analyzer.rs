@@ -1,122 +1,360 @@
 //! Request analyzer - categorizes user requests
 
+use serde::Serialize;
+
 use super::TaskType;
 
-/// Keywords that indicate different task types
+/// Keywords that indicate different task types, with their weights
+///
+/// Keywords match whole words (with simple inflections, so "write" also
+/// matches "writing"); multi-word keywords match as a phrase.
 pub struct TaskKeywords {
-    pub write: Vec<&'static str>,
-    pub research: Vec<&'static str>,
-    pub analyze: Vec<&'static str>,
-    pub create: Vec<&'static str>,
-    pub edit: Vec<&'static str>,
-    pub explain: Vec<&'static str>,
-    pub solve: Vec<&'static str>,
+    pub write: Vec<(&'static str, f32)>,
+    pub research: Vec<(&'static str, f32)>,
+    pub analyze: Vec<(&'static str, f32)>,
+    pub create: Vec<(&'static str, f32)>,
+    pub edit: Vec<(&'static str, f32)>,
+    pub explain: Vec<(&'static str, f32)>,
+    pub solve: Vec<(&'static str, f32)>,
+    pub summarize: Vec<(&'static str, f32)>,
+    pub translate: Vec<(&'static str, f32)>,
+    pub code: Vec<(&'static str, f32)>,
+    pub review: Vec<(&'static str, f32)>,
 }
 
 impl Default for TaskKeywords {
     fn default() -> Self {
         Self {
             write: vec![
-                "write", "draft", "compose", "author", "blog", "article",
-                "email", "letter", "document", "report", "essay", "story",
-                "script", "copy", "content", "post", "message", "text",
+                ("write", 0.5), ("draft", 0.5), ("compose", 0.5), ("author", 0.3),
+                ("blog", 0.4), ("article", 0.4), ("email", 0.4), ("letter", 0.3),
+                ("essay", 0.4), ("story", 0.4), ("poem", 0.4), ("tweet", 0.3),
+                ("document", 0.2), ("report", 0.2), ("script", 0.2), ("post", 0.2),
+                ("copy", 0.15), ("content", 0.15), ("message", 0.15), ("text", 0.15),
             ],
             research: vec![
-                "research", "find", "search", "look up", "discover", "learn about",
-                "what is", "who is", "where is", "when did", "how many",
-                "statistics", "facts", "information", "sources", "reference",
+                ("research", 0.5), ("look up", 0.4), ("who is", 0.4), ("when did", 0.4),
+                ("find", 0.3), ("search", 0.3), ("discover", 0.3), ("learn about", 0.3),
+                ("what is", 0.3), ("where is", 0.3), ("how many", 0.3), ("latest", 0.3),
+                ("news", 0.3), ("statistics", 0.3), ("facts", 0.3), ("sources", 0.3),
+                ("information", 0.25), ("reference", 0.2),
             ],
             analyze: vec![
-                "analyze", "review", "examine", "inspect", "assess", "evaluate",
-                "code", "debug", "data", "compare", "contrast", "check",
-                "audit", "test", "verify", "validate", "diagnose",
+                ("analyze", 0.5), ("debug", 0.5), ("review", 0.4), ("examine", 0.4),
+                ("inspect", 0.4), ("assess", 0.4), ("evaluate", 0.4), ("compare", 0.4),
+                ("audit", 0.4), ("diagnose", 0.4), ("data", 0.3),
+                ("contrast", 0.3), ("verify", 0.3), ("validate", 0.3), ("check", 0.2),
+                ("test", 0.2), ("performance", 0.2),
             ],
             create: vec![
-                "create", "brainstorm", "ideas", "design", "imagine", "invent",
-                "generate", "come up with", "think of", "suggest", "propose",
-                "innovate", "concept", "vision", "plan",
+                ("brainstorm", 0.5), ("create", 0.4), ("idea", 0.4), ("design", 0.4),
+                ("imagine", 0.4), ("invent", 0.4), ("come up with", 0.4), ("innovate", 0.4),
+                ("generate", 0.3), ("think of", 0.3), ("suggest", 0.3), ("propose", 0.3),
+                ("concept", 0.2), ("vision", 0.2), ("plan", 0.2),
             ],
             edit: vec![
-                "edit", "proofread", "improve", "fix", "rewrite", "polish",
-                "refine", "revise", "correct", "enhance", "clean up",
-                "format", "restructure", "reorganize",
+                ("edit", 0.5), ("proofread", 0.5), ("rewrite", 0.5), ("polish", 0.4),
+                ("refine", 0.4), ("revise", 0.4), ("typo", 0.4), ("grammar", 0.4),
+                ("improve", 0.3), ("fix", 0.3), ("correct", 0.3), ("enhance", 0.3),
+                ("clean up", 0.3), ("restructure", 0.3), ("reorganize", 0.3),
+                ("shorten", 0.3), ("format", 0.2),
             ],
             explain: vec![
-                "explain", "how does", "why does", "teach", "help me understand",
-                "clarify", "describe", "what does", "meaning of", "define",
-                "elaborate", "break down", "simplify", "tutorial",
+                ("explain", 0.5), ("help me understand", 0.5), ("how does", 0.4),
+                ("why does", 0.4), ("teach", 0.4), ("clarify", 0.4), ("meaning of", 0.4),
+                ("define", 0.4), ("how do", 0.3), ("describe", 0.3), ("what does", 0.3),
+                ("elaborate", 0.3), ("break down", 0.3), ("simplify", 0.3),
+                ("tutorial", 0.3), ("difference between", 0.3),
             ],
             solve: vec![
-                "solve", "problem", "issue", "error", "broken", "not working",
-                "fix", "troubleshoot", "resolve", "help with", "stuck",
-                "can't", "won't", "failing", "crashed",
+                ("solve", 0.5), ("troubleshoot", 0.5), ("not working", 0.5),
+                ("error", 0.4), ("broken", 0.4), ("resolve", 0.4), ("stuck", 0.4),
+                ("failing", 0.4), ("crash", 0.4), ("problem", 0.3), ("issue", 0.3),
+                ("fix", 0.3), ("bug", 0.3), ("exception", 0.3), ("help with", 0.2),
+                ("can't", 0.2), ("won't", 0.2),
+            ],
+            summarize: vec![
+                ("summarize", 0.6), ("sum up", 0.6), ("summary", 0.5), ("tldr", 0.5),
+                ("recap", 0.5), ("condense", 0.4), ("key points", 0.4), ("gist", 0.4),
+                ("takeaways", 0.4), ("digest", 0.3), ("overview", 0.3), ("in short", 0.3),
+                ("highlights", 0.3), ("brief", 0.2),
+            ],
+            translate: vec![
+                ("translate", 0.6), ("translation", 0.6), ("how do you say", 0.6),
+                ("localize", 0.4),
+                ("into english", 0.5), ("into spanish", 0.5), ("into french", 0.5),
+                ("into german", 0.5), ("into italian", 0.5), ("into portuguese", 0.5),
+                ("into japanese", 0.5), ("into chinese", 0.5), ("into korean", 0.5),
+                ("in spanish", 0.3), ("in french", 0.3), ("in german", 0.3),
+                ("language", 0.2),
+            ],
+            code: vec![
+                ("implement", 0.5), ("refactor", 0.5), ("write a function", 0.6),
+                ("function", 0.3), ("code", 0.3), ("program", 0.3), ("script", 0.3),
+                ("regex", 0.4), ("sql query", 0.4), ("unit test", 0.4), ("endpoint", 0.3),
+                ("class", 0.2), ("method", 0.2), ("compile", 0.3), ("rust", 0.3),
+                ("python", 0.3), ("javascript", 0.3), ("typescript", 0.3), ("golang", 0.3),
+            ],
+            review: vec![
+                ("code review", 0.6), ("pull request", 0.5), ("diff", 0.5), ("review", 0.3),
+                ("critique", 0.4), ("patch", 0.4), ("changeset", 0.4), ("feedback on", 0.4),
+                ("lgtm", 0.4), ("pr", 0.3), ("commit", 0.3), ("nitpick", 0.3),
             ],
         }
     }
 }
 
+impl TaskKeywords {
+    /// Keyword lists paired with their task type, in tie-break order
+    pub fn by_type(&self) -> [(TaskType, &[(&'static str, f32)]); 11] {
+        [
+            (TaskType::Write, &self.write),
+            (TaskType::Research, &self.research),
+            (TaskType::Analyze, &self.analyze),
+            (TaskType::Create, &self.create),
+            (TaskType::Edit, &self.edit),
+            (TaskType::Explain, &self.explain),
+            (TaskType::Solve, &self.solve),
+            (TaskType::Summarize, &self.summarize),
+            (TaskType::Translate, &self.translate),
+            (TaskType::Code, &self.code),
+            (TaskType::Review, &self.review),
+        ]
+    }
+}
+
+/// Words that negate a keyword shortly after them ("don't write code")
+const NEGATIONS: &[&str] = &[
+    "don't", "dont", "do not", "not", "no", "never", "without", "instead of", "rather than",
+    "avoid",
+];
+
+/// Keywords whose inflections are other words ("differ" isn't a diff);
+/// only the plural counts
+const UNINFLECTED: &[&str] = &["diff"];
+
+/// How many words after a negation are negated
+const NEGATION_WINDOW: usize = 3;
+
+/// Words that start a new clause, ending any negation ("don't write, just explain")
+const CLAUSE_BREAKS: &[&str] = &["but", "just"];
+
+/// Politeness words skipped when finding the request's leading verb
+const FILLER: &[&str] = &[
+    "please", "can", "could", "would", "will", "you", "i", "i'd", "like", "want", "need",
+    "to",
+];
+
+/// Weight multiplier for a keyword that opens the request ("Explain ...")
+const LEADING_BONUS: f32 = 1.5;
+
+/// Minimum score for a task type to win over General
+const MIN_CONFIDENCE: f32 = 0.2;
+
+/// Score at which a task type counts towards a complex request
+const SIGNIFICANT_SCORE: f32 = 0.3;
+
+/// How strongly a request matched one task type
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeScore {
+    pub task_type: TaskType,
+    pub score: f32,
+    pub keywords: Vec<String>,
+}
+
 /// Confidence score for task type detection
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TaskAnalysis {
     pub primary_type: TaskType,
     pub confidence: f32,
     pub keywords_found: Vec<String>,
     pub is_complex: bool,
+    /// Score for every task type, in `TaskKeywords::by_type` order
+    pub scores: Vec<TypeScore>,
+    /// Keywords that matched but were negated
+    pub negated: Vec<String>,
+    /// Which classifier produced the verdict
+    pub classifier: &'static str,
+}
+
+/// A word of the request and the clause it belongs to
+#[derive(Debug)]
+struct Token {
+    word: String,
+    clause: usize,
+}
+
+/// Analyze a request to determine its task type
+pub fn analyze_request(request: &str) -> TaskType {
+    analyze_request_detailed(request).primary_type
 }
 
 /// Analyze a request with detailed scoring
 pub fn analyze_request_detailed(request: &str) -> TaskAnalysis {
-    let lower = request.to_lowercase();
+    let tokens = tokenize(request);
+    let negated_at = negated_positions(&tokens);
+    let lead = tokens
+        .iter()
+        .position(|t| !FILLER.contains(&t.word.as_str()))
+        .unwrap_or(0);
+
     let keywords = TaskKeywords::default();
-    let mut scores: Vec<(TaskType, f32, Vec<String>)> = Vec::new();
+    let mut scores: Vec<TypeScore> = Vec::new();
+    let mut negated = Vec::new();
+
+    for (task_type, list) in keywords.by_type() {
+        let mut score = 0.0;
+        let mut found = Vec::new();
 
-    // Score each task type
-    scores.push(score_keywords(&lower, &keywords.write, TaskType::Write));
-    scores.push(score_keywords(&lower, &keywords.research, TaskType::Research));
-    scores.push(score_keywords(&lower, &keywords.analyze, TaskType::Analyze));
-    scores.push(score_keywords(&lower, &keywords.create, TaskType::Create));
-    scores.push(score_keywords(&lower, &keywords.edit, TaskType::Edit));
-    scores.push(score_keywords(&lower, &keywords.explain, TaskType::Explain));
-    scores.push(score_keywords(&lower, &keywords.solve, TaskType::Solve));
+        for &(keyword, weight) in list {
+            let starts = find_phrase(&tokens, keyword);
+            let live: Vec<usize> = starts.iter().copied().filter(|&i| !negated_at[i]).collect();
 
-    // Find highest scoring type
-    scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            if live.is_empty() {
+                if !starts.is_empty() && !negated.iter().any(|k| k == keyword) {
+                    negated.push(keyword.to_string());
+                }
+                continue;
+            }
 
-    let (primary_type, confidence, keywords_found) = scores
-        .first()
-        .cloned()
-        .unwrap_or((TaskType::General, 0.0, Vec::new()));
+            found.push(keyword.to_string());
+            score += if live.contains(&lead) { weight * LEADING_BONUS } else { weight };
+        }
+
+        scores.push(TypeScore {
+            task_type,
+            score: f32::min(score, 1.0),
+            keywords: found,
+        });
+    }
+
+    // Highest score wins; ties go to the earlier task type
+    let best = (0..scores.len())
+        .fold(0, |best, i| if scores[i].score > scores[best].score { i } else { best });
+    let (primary_type, confidence) = (scores[best].task_type, scores[best].score);
 
     // Determine if request is complex (multiple task types detected)
-    let significant_scores = scores.iter().filter(|(_, s, _)| *s > 0.3).count();
+    let significant_scores = scores.iter().filter(|s| s.score >= SIGNIFICANT_SCORE).count();
     let is_complex = significant_scores > 1 || request.len() > 200;
 
     TaskAnalysis {
-        primary_type: if confidence > 0.2 { primary_type } else { TaskType::General },
+        primary_type: if confidence >= MIN_CONFIDENCE { primary_type } else { TaskType::General },
         confidence,
-        keywords_found,
+        keywords_found: scores[best].keywords.clone(),
         is_complex,
+        scores,
+        negated,
+        classifier: "keywords",
     }
 }
 
-/// Score how well a request matches a set of keywords
-fn score_keywords(text: &str, keywords: &[&str], task_type: TaskType) -> (TaskType, f32, Vec<String>) {
-    let mut found = Vec::new();
-    let mut score = 0.0;
+/// Whether `text` contains a word or phrase, matched as the classifier does
+pub fn contains_phrase(text: &str, phrase: &str) -> bool {
+    !find_phrase(&tokenize(text), &phrase.to_lowercase()).is_empty()
+}
+
+/// Split a request into lowercase words, tracking clause boundaries
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut clause = 0;
+    let mut word = String::new();
 
-    for keyword in keywords {
-        if text.contains(keyword) {
-            found.push(keyword.to_string());
-            // Earlier keywords in the list are more important
-            let position_bonus = 1.0 - (keywords.iter().position(|k| k == keyword).unwrap_or(0) as f32 / keywords.len() as f32) * 0.5;
-            score += 0.2 * position_bonus;
+    let mut flush = |word: &mut String, clause: &mut usize| {
+        if word.is_empty() {
+            return;
+        }
+        let finished = std::mem::take(word);
+        if CLAUSE_BREAKS.contains(&finished.as_str()) {
+            *clause += 1;
+        } else {
+            tokens.push(Token { word: finished, clause: *clause });
+        }
+    };
+
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '\'' || c == '\u{2019}' {
+            word.extend(c.to_lowercase().map(|c| if c == '\u{2019}' { '\'' } else { c }));
+            continue;
+        }
+        flush(&mut word, &mut clause);
+        if ",;.!?:\n".contains(c) {
+            clause += 1;
         }
     }
+    flush(&mut word, &mut clause);
 
-    // Cap at 1.0
-    score = score.min(1.0);
+    tokens
+}
+
+/// Start positions of every match of a (possibly multi-word) keyword
+fn find_phrase(tokens: &[Token], phrase: &str) -> Vec<usize> {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    if words.is_empty() || tokens.len() < words.len() {
+        return Vec::new();
+    }
 
-    (task_type, score, found)
+    (0..=tokens.len() - words.len())
+        .filter(|&start| {
+            words.iter().enumerate().all(|(offset, word)| {
+                let token = &tokens[start + offset];
+                token.clause == tokens[start].clause && word_matches(&token.word, word)
+            })
+        })
+        .collect()
+}
+
+/// Whether `token` is `word` or a simple inflection of it
+///
+/// Handles plurals, -ed, -ing and -er forms, including a dropped final "e"
+/// ("writing") and a doubled final consonant ("debugging").
+fn word_matches(token: &str, word: &str) -> bool {
+    if token == word {
+        return true;
+    }
+    if UNINFLECTED.contains(&word) {
+        return token.strip_suffix('s') == Some(word);
+    }
+
+    let stem = word.strip_suffix('e').unwrap_or(word);
+    ["s", "es", "ed", "ing", "er", "ers"].iter().any(|suffix| {
+        token.strip_suffix(suffix).is_some_and(|base| {
+            base == word
+                || base == stem
+                || (base.len() == word.len() + 1
+                    && base.starts_with(word)
+                    && base.ends_with(word.chars().last().unwrap_or_default()))
+        })
+    })
+}
+
+/// For each token, whether a negation shortly before it in the same clause covers it
+fn negated_positions(tokens: &[Token]) -> Vec<bool> {
+    let mut negated = vec![false; tokens.len()];
+
+    for negation in NEGATIONS {
+        let length = negation.split_whitespace().count();
+        for start in find_exact(tokens, negation) {
+            let clause = tokens[start].clause;
+            let scope = start + length..(start + length + NEGATION_WINDOW).min(tokens.len());
+            for i in scope {
+                if tokens[i].clause == clause {
+                    negated[i] = true;
+                }
+            }
+        }
+    }
+    negated
+}
+
+/// Start positions of a phrase matched word for word, without inflections
+fn find_exact(tokens: &[Token], phrase: &str) -> Vec<usize> {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    if tokens.len() < words.len() {
+        return Vec::new();
+    }
+    (0..=tokens.len() - words.len())
+        .filter(|&start| words.iter().enumerate().all(|(i, w)| tokens[start + i].word == *w))
+        .collect()
 }
 
 /// Break a complex request into subtasks
@@ -154,6 +392,109 @@ pub fn decompose_request(request: &str) -> Vec<(String, TaskType)> {
 mod tests {
     use super::*;
 
+    /// Labelled requests the classifier must route correctly
+    ///
+    /// Add a line here for every routing bug fixed, so changes to keywords
+    /// or weights can be measured against the whole corpus.
+    const CORPUS: &[(&str, TaskType)] = &[
+        ("write a blog post about cooking", TaskType::Write),
+        ("draft an email to my landlord about the broken heater", TaskType::Write),
+        ("compose a short poem about autumn", TaskType::Write),
+        ("can you write me a cover letter", TaskType::Write),
+        ("research the history of the printing press", TaskType::Research),
+        ("what is the population of Norway", TaskType::Research),
+        ("find sources on microplastics in rivers", TaskType::Research),
+        ("latest news on fusion energy", TaskType::Research),
+        ("I don't need an article, just the key facts about Mars", TaskType::Research),
+        ("analyze this code for race conditions", TaskType::Analyze),
+        ("review the data from last quarter", TaskType::Analyze),
+        ("debug this function", TaskType::Analyze),
+        ("compare postgres and mysql for analytics", TaskType::Analyze),
+        ("compare how these two configs differ", TaskType::Analyze),
+        ("audit the dependencies for vulnerabilities", TaskType::Analyze),
+        ("brainstorm names for a coffee shop", TaskType::Create),
+        ("come up with ideas for a team offsite", TaskType::Create),
+        ("design a logo concept for a bakery", TaskType::Create),
+        ("proofread my cover letter", TaskType::Edit),
+        ("rewrite this paragraph to be more concise", TaskType::Edit),
+        ("fix the typos in this paragraph", TaskType::Edit),
+        ("polish the introduction of my essay", TaskType::Edit),
+        ("no need to review it, just rewrite the intro", TaskType::Edit),
+        ("explain how garbage collection works", TaskType::Explain),
+        ("how does TCP congestion control work", TaskType::Explain),
+        ("help me understand monads", TaskType::Explain),
+        ("define entropy in simple terms", TaskType::Explain),
+        ("don't write code, just explain how recursion works", TaskType::Explain),
+        ("my build is broken and I'm stuck", TaskType::Solve),
+        ("the app crashes on startup with a null pointer error", TaskType::Solve),
+        ("troubleshoot my wifi, it's not working", TaskType::Solve),
+        ("solve this equation: 3x + 5 = 20", TaskType::Solve),
+        ("the test fixture is failing", TaskType::Solve),
+        ("summarize these server logs", TaskType::Summarize),
+        ("give me the key points of this RFC", TaskType::Summarize),
+        ("tldr of the meeting notes", TaskType::Summarize),
+        ("translate this paragraph into german", TaskType::Translate),
+        ("how do you say good morning in spanish", TaskType::Translate),
+        ("implement a binary search in rust", TaskType::Code),
+        ("write a function that parses ISO dates", TaskType::Code),
+        ("refactor this class to use dependency injection", TaskType::Code),
+        ("review this diff before I merge it", TaskType::Review),
+        ("can you critique my pull request", TaskType::Review),
+        ("review my PR before I merge", TaskType::Review),
+        ("update the postgres connection settings", TaskType::General),
+        ("hello", TaskType::General),
+        ("thanks!", TaskType::General),
+        ("tell me a joke", TaskType::General),
+    ];
+
+    #[test]
+    fn test_routing_corpus() {
+        let misrouted: Vec<String> = CORPUS
+            .iter()
+            .filter_map(|&(request, expected)| {
+                let actual = analyze_request(request);
+                (actual != expected)
+                    .then(|| format!("{:?} -> {:?} (expected {:?})", request, actual, expected))
+            })
+            .collect();
+
+        assert!(
+            misrouted.is_empty(),
+            "{}/{} requests misrouted:\n{}",
+            misrouted.len(),
+            CORPUS.len(),
+            misrouted.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_word_boundaries() {
+        // "postgres" is not "post", "fixture" is not "fix"
+        let analysis = analyze_request_detailed("postgres fixture");
+        assert!(analysis.keywords_found.is_empty());
+        assert!(analysis.scores.iter().all(|s| s.score == 0.0));
+
+        assert!(word_matches("writing", "write"));
+        assert!(word_matches("debugging", "debug"));
+        assert!(word_matches("diffs", "diff"));
+        assert!(!word_matches("differ", "diff"));
+        assert!(word_matches("crashes", "crash"));
+        assert!(!word_matches("writer's", "write"));
+        assert!(!word_matches("posture", "post"));
+    }
+
+    #[test]
+    fn test_negation() {
+        let analysis = analyze_request_detailed("don't write code, just explain recursion");
+        assert_eq!(analysis.primary_type, TaskType::Explain);
+        assert_eq!(analysis.negated, vec!["write", "code"]);
+
+        // A negation doesn't reach past the end of its clause
+        let analysis = analyze_request_detailed("not now. write the report");
+        assert_eq!(analysis.primary_type, TaskType::Write);
+        assert!(analysis.negated.is_empty());
+    }
+
     #[test]
     fn test_detailed_analysis() {
         let analysis = analyze_request_detailed("write a blog post about cooking");
@@ -0,0 +1,475 @@
+//! Trainable task classifier - naive Bayes over n-grams
+//!
+//! The keyword lists in `TaskKeywords` only know English and general
+//! vocabulary. This model learns from labelled examples (and from past
+//! sessions), so it picks up other languages and domain jargon. Features are
+//! word unigrams and bigrams plus character trigrams, which lets it cope with
+//! inflections and languages that don't split words with spaces.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{
+    analyze_request_detailed, Outcome, Rating, SupportTeam, TaskAnalysis, TaskType, TypeScore,
+};
+
+/// Model file format version; bumped when features change
+const MODEL_VERSION: u32 = 1;
+
+/// Posterior below which the keyword classifier's answer is kept
+const MIN_POSTERIOR: f32 = 0.5;
+
+/// Features listed as "keywords" when the model explains a decision
+const TOP_FEATURES: usize = 5;
+
+/// One labelled request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Example {
+    pub text: String,
+    pub task: TaskType,
+}
+
+/// Multinomial naive Bayes over request n-grams
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskClassifier {
+    version: u32,
+    classes: Vec<TaskType>,
+    /// Training examples per class
+    class_docs: Vec<u32>,
+    /// Feature occurrences per class, in `classes` order
+    feature_counts: HashMap<String, Vec<u32>>,
+    /// Total feature occurrences per class
+    class_totals: Vec<u64>,
+}
+
+impl TaskClassifier {
+    /// Train a model from labelled examples
+    pub fn train(examples: &[Example]) -> Result<Self> {
+        let mut classes: Vec<TaskType> = Vec::new();
+        for example in examples {
+            if !classes.contains(&example.task) {
+                classes.push(example.task);
+            }
+        }
+        if classes.len() < 2 {
+            anyhow::bail!("Need examples of at least two task types to train");
+        }
+
+        let mut model = Self {
+            version: MODEL_VERSION,
+            class_docs: vec![0; classes.len()],
+            class_totals: vec![0; classes.len()],
+            feature_counts: HashMap::new(),
+            classes,
+        };
+
+        for example in examples {
+            let class = model.class_index(example.task).unwrap_or_default();
+            model.class_docs[class] += 1;
+            for feature in features(&example.text) {
+                let counts = model
+                    .feature_counts
+                    .entry(feature)
+                    .or_insert_with(|| vec![0; model.classes.len()]);
+                counts[class] += 1;
+                model.class_totals[class] += 1;
+            }
+        }
+
+        Ok(model)
+    }
+
+    fn class_index(&self, task: TaskType) -> Option<usize> {
+        self.classes.iter().position(|&c| c == task)
+    }
+
+    /// Posterior probability of each class, in `classes` order
+    pub fn posteriors(&self, text: &str) -> Vec<(TaskType, f32)> {
+        let total_docs: u32 = self.class_docs.iter().sum();
+        let vocabulary = self.feature_counts.len() as f64;
+        let features = features(text);
+
+        let log_scores: Vec<f64> = (0..self.classes.len())
+            .map(|class| {
+                let prior = (self.class_docs[class] as f64 / total_docs.max(1) as f64).ln();
+                let denominator = self.class_totals[class] as f64 + vocabulary;
+                features
+                    .iter()
+                    // Unseen features say nothing about any class
+                    .filter_map(|f| self.feature_counts.get(f))
+                    .map(|counts| ((counts[class] as f64 + 1.0) / denominator).ln())
+                    .sum::<f64>()
+                    + prior
+            })
+            .collect();
+
+        // Softmax, shifted by the max for numerical stability
+        let max = log_scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exps: Vec<f64> = log_scores.iter().map(|s| (s - max).exp()).collect();
+        let sum: f64 = exps.iter().sum();
+
+        self.classes
+            .iter()
+            .zip(exps)
+            .map(|(&class, e)| (class, (e / sum) as f32))
+            .collect()
+    }
+
+    /// Most likely class and its posterior
+    pub fn predict(&self, text: &str) -> (TaskType, f32) {
+        self.posteriors(text)
+            .into_iter()
+            .fold((TaskType::General, 0.0), |best, (class, p)| {
+                if p > best.1 { (class, p) } else { best }
+            })
+    }
+
+    /// Analyze a request, in the same shape as the keyword classifier
+    ///
+    /// Complexity and negation still come from the keyword analysis. When the
+    /// model is unsure, the keyword classifier's verdict stands.
+    pub fn analyze(&self, request: &str) -> TaskAnalysis {
+        let mut analysis = analyze_request_detailed(request);
+        let (task, confidence) = self.predict(request);
+
+        // When unsure, the keyword decision stands along with its own scores
+        if confidence < MIN_POSTERIOR {
+            analysis.classifier = "naive-bayes (unsure, used keywords)";
+            return analysis;
+        }
+
+        analysis.classifier = "naive-bayes";
+        analysis.scores = self
+            .posteriors(request)
+            .iter()
+            .map(|&(task_type, score)| TypeScore {
+                task_type,
+                score,
+                keywords: Vec::new(),
+            })
+            .collect();
+        analysis.primary_type = task;
+        analysis.confidence = confidence;
+        analysis.keywords_found = self.top_features(request, task);
+        analysis
+    }
+
+    /// Features of `text` that most favour `task` over the other classes
+    fn top_features(&self, text: &str, task: TaskType) -> Vec<String> {
+        let Some(class) = self.class_index(task) else {
+            return Vec::new();
+        };
+        let vocabulary = self.feature_counts.len() as f64;
+        let rate = |counts: &[u32], c: usize| {
+            (counts[c] as f64 + 1.0) / (self.class_totals[c] as f64 + vocabulary)
+        };
+
+        let mut scored: Vec<(String, f64)> = features(text)
+            .into_iter()
+            .filter(|f| !f.starts_with('#'))
+            .filter_map(|f| {
+                let counts = self.feature_counts.get(&f)?;
+                let others = (0..self.classes.len())
+                    .filter(|&c| c != class)
+                    .map(|c| rate(counts, c))
+                    .fold(0.0, f64::max);
+                let lift = (rate(counts, class) / others).ln();
+                (lift > 0.0).then_some((f, lift))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.dedup_by(|a, b| a.0 == b.0);
+        scored.into_iter().take(TOP_FEATURES).map(|(f, _)| f).collect()
+    }
+
+    /// Model location in the config directory
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("workyterm")
+            .join("classifier.json")
+    }
+
+    /// Load a trained model, if one exists and is current
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        match serde_json::from_str::<Self>(&content) {
+            Ok(model) if model.version == MODEL_VERSION => Some(model),
+            Ok(_) => {
+                eprintln!(
+                    "Warning: Classifier at {} is out of date; \
+                    retrain with `workyterm router train`",
+                    path.display()
+                );
+                None
+            }
+            Err(e) => {
+                eprintln!("Warning: Could not read classifier {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn classes(&self) -> &[TaskType] {
+        &self.classes
+    }
+}
+
+impl SupportTeam {
+    /// Classify a request with the trained model if there is one, else keywords
+    pub fn analyze(&self, request: &str) -> TaskAnalysis {
+        match self.classifier {
+            Some(ref model) => model.analyze(request),
+            None => analyze_request_detailed(request),
+        }
+    }
+}
+
+/// Word unigrams and bigrams plus character trigrams of each word
+///
+/// Character trigrams are prefixed with `#` so they never collide with words.
+fn features(text: &str) -> Vec<String> {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut features: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+    features.extend(words.windows(2).map(|pair| format!("{} {}", pair[0], pair[1])));
+
+    for word in &words {
+        let padded: Vec<char> = format!("<{}>", word).chars().collect();
+        features.extend(
+            padded
+                .windows(3)
+                .map(|tri| format!("#{}", tri.iter().collect::<String>())),
+        );
+    }
+    features
+}
+
+/// Read labelled examples from a JSONL file (`{"text": ..., "task": ...}` per line)
+pub fn read_examples(path: &Path) -> Result<Vec<Example>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!("{}:{}: not a labelled example", path.display(), number + 1)
+            })
+        })
+        .collect()
+}
+
+/// Examples from past sessions: requests that succeeded and weren't rated bad
+pub fn history_examples(outcomes: &[Outcome]) -> Vec<Example> {
+    outcomes
+        .iter()
+        .filter(|o| o.success && o.rating != Some(Rating::Bad))
+        .filter_map(|o| {
+            Some(Example {
+                text: o.request.clone()?,
+                task: o.task_type,
+            })
+        })
+        .collect()
+}
+
+/// Split examples into training and held-out sets
+///
+/// Every `1 / holdout`-th example of each class is held out, so the split is
+/// stable between runs and every class appears in both sets.
+pub fn split_holdout(examples: &[Example], holdout: f32) -> (Vec<Example>, Vec<Example>) {
+    if holdout <= 0.0 {
+        return (examples.to_vec(), Vec::new());
+    }
+    let stride = (1.0 / holdout.min(0.5)).round().max(2.0) as usize;
+
+    let mut seen: HashMap<TaskType, usize> = HashMap::new();
+    let mut train = Vec::new();
+    let mut held = Vec::new();
+    for example in examples {
+        let count = seen.entry(example.task).or_insert(0);
+        *count += 1;
+        if count.is_multiple_of(stride) {
+            held.push(example.clone());
+        } else {
+            train.push(example.clone());
+        }
+    }
+    (train, held)
+}
+
+/// Predicted-versus-actual counts over a labelled set
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfusionMatrix {
+    pub labels: Vec<TaskType>,
+    /// `counts[actual][predicted]`, in `labels` order
+    pub counts: Vec<Vec<usize>>,
+}
+
+impl ConfusionMatrix {
+    /// Evaluate a classifier function against labelled examples
+    pub fn evaluate(examples: &[Example], classify: impl Fn(&str) -> TaskType) -> Self {
+        let mut labels: Vec<TaskType> = Vec::new();
+        let predictions: Vec<(TaskType, TaskType)> = examples
+            .iter()
+            .map(|e| (e.task, classify(&e.text)))
+            .collect();
+        for &(actual, predicted) in &predictions {
+            for label in [actual, predicted] {
+                if !labels.contains(&label) {
+                    labels.push(label);
+                }
+            }
+        }
+
+        let index = |t: TaskType| labels.iter().position(|&l| l == t).unwrap_or_default();
+        let mut counts = vec![vec![0; labels.len()]; labels.len()];
+        for (actual, predicted) in predictions {
+            counts[index(actual)][index(predicted)] += 1;
+        }
+        Self { labels, counts }
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        let total: usize = self.counts.iter().flatten().sum();
+        let correct: usize = (0..self.labels.len()).map(|i| self.counts[i][i]).sum();
+        if total == 0 { 0.0 } else { correct as f32 / total as f32 }
+    }
+
+    /// Table with actual classes as rows and predictions as columns
+    pub fn render(&self) -> String {
+        let short = |t: &TaskType| format!("{:?}", t).chars().take(8).collect::<String>();
+        let mut out = format!("{:>10} |", "actual");
+        for label in &self.labels {
+            out.push_str(&format!(" {:>8}", short(label)));
+        }
+        out.push('\n');
+        out.push_str(&"-".repeat(12 + 9 * self.labels.len()));
+        out.push('\n');
+        for (label, row) in self.labels.iter().zip(&self.counts) {
+            out.push_str(&format!("{:>10} |", short(label)));
+            for count in row {
+                out.push_str(&format!(" {:>8}", count));
+            }
+            out.push('\n');
+        }
+        out.push_str(&format!("accuracy: {:.1}%", self.accuracy() * 100.0));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(text: &str, task: TaskType) -> Example {
+        Example {
+            text: text.to_string(),
+            task,
+        }
+    }
+
+    fn training_set() -> Vec<Example> {
+        vec![
+            example("escribe un artículo sobre el clima", TaskType::Write),
+            example("escribe un correo para mi jefe", TaskType::Write),
+            example("redacta una carta de presentación", TaskType::Write),
+            example("the sharding migration stalls on the replica", TaskType::Solve),
+            example("sharding job stalls again after failover", TaskType::Solve),
+            example("replica lag stalls the migration", TaskType::Solve),
+            example("summarize the sla breaches in the p99 dashboard", TaskType::Analyze),
+            example("look at p99 latency on the dashboard", TaskType::Analyze),
+            example("check the sla dashboard for outliers", TaskType::Analyze),
+        ]
+    }
+
+    #[test]
+    fn test_learns_other_languages_and_jargon() {
+        let model = TaskClassifier::train(&training_set()).unwrap();
+
+        assert_eq!(model.predict("escribe un poema").0, TaskType::Write);
+        assert_eq!(model.predict("failover stalls the replica").0, TaskType::Solve);
+        assert_eq!(model.predict("p99 dashboard").0, TaskType::Analyze);
+
+        let analysis = model.analyze("escribe un artículo");
+        assert_eq!(analysis.primary_type, TaskType::Write);
+        assert_eq!(analysis.classifier, "naive-bayes");
+        assert!(analysis.keywords_found.contains(&"escribe".to_string()));
+    }
+
+    #[test]
+    fn test_unsure_model_keeps_keyword_scores() {
+        let model = TaskClassifier::train(&training_set()).unwrap();
+        let request = "brainstorm ideas";
+        let keywords = analyze_request_detailed(request);
+
+        let analysis = model.analyze(request);
+        assert_eq!(analysis.classifier, "naive-bayes (unsure, used keywords)");
+        assert_eq!(analysis.primary_type, keywords.primary_type);
+        let scores = |a: &TaskAnalysis| {
+            a.scores.iter().map(|s| (s.task_type, s.score)).collect::<Vec<_>>()
+        };
+        assert_eq!(scores(&analysis), scores(&keywords));
+        assert!(analysis.scores.iter().any(|s| !s.keywords.is_empty()));
+    }
+
+    #[test]
+    fn test_train_needs_two_classes() {
+        assert!(TaskClassifier::train(&[example("write", TaskType::Write)]).is_err());
+    }
+
+    #[test]
+    fn test_split_holdout_is_stratified() {
+        let (train, held) = split_holdout(&training_set(), 0.34);
+        assert_eq!(held.len(), 3);
+        assert_eq!(train.len(), 6);
+        for task in [TaskType::Write, TaskType::Solve, TaskType::Analyze] {
+            assert!(held.iter().any(|e| e.task == task));
+        }
+    }
+
+    #[test]
+    fn test_confusion_matrix() {
+        let examples = vec![
+            example("a", TaskType::Write),
+            example("b", TaskType::Write),
+            example("c", TaskType::Solve),
+        ];
+        let matrix = ConfusionMatrix::evaluate(&examples, |_| TaskType::Write);
+        assert_eq!(matrix.labels, vec![TaskType::Write, TaskType::Solve]);
+        assert_eq!(matrix.counts, vec![vec![2, 0], vec![1, 0]]);
+        assert!((matrix.accuracy() - 2.0 / 3.0).abs() < 0.001);
+        assert!(matrix.render().ends_with("accuracy: 66.7%"));
+    }
+
+    #[test]
+    fn test_history_examples_skip_bad_and_failed() {
+        let mut good = Outcome::new("codex-cli", "Cody", TaskType::Solve, 10, true);
+        good.request = Some("fix the flaky test".to_string());
+        let mut bad = good.clone();
+        bad.rating = Some(Rating::Bad);
+        let mut failed = good.clone();
+        failed.success = false;
+        let unlabelled = Outcome::new("codex-cli", "Cody", TaskType::Solve, 10, true);
+
+        let examples = history_examples(&[good, bad, failed, unlabelled]);
+        assert_eq!(examples, vec![example("fix the flaky test", TaskType::Solve)]);
+    }
+}
@@ -1,6 +1,7 @@
 //! Team member definitions and provider mappings
 
-use super::TaskType;
+use super::{SupportTeam, TaskType, TeamMember};
+use crate::config::PersonalityOverride;
 
 /// Model preference for a task type
 #[derive(Debug, Clone)]
@@ -48,6 +49,26 @@ pub fn get_provider_preference(task_type: TaskType) -> ModelPreference {
             preferred_providers: vec!["codex-cli", "claude-cli"],
             fallback_providers: vec!["gemini-cli", "ollama"],
         },
+        TaskType::Summarize => ModelPreference {
+            task_type,
+            preferred_providers: vec!["gemini-cli", "claude-cli"],
+            fallback_providers: vec!["ollama", "codex-cli"],
+        },
+        TaskType::Translate => ModelPreference {
+            task_type,
+            preferred_providers: vec!["gemini-cli", "claude-cli"],
+            fallback_providers: vec!["ollama", "codex-cli"],
+        },
+        TaskType::Code => ModelPreference {
+            task_type,
+            preferred_providers: vec!["codex-cli", "claude-cli"],
+            fallback_providers: vec!["gemini-cli", "ollama"],
+        },
+        TaskType::Review => ModelPreference {
+            task_type,
+            preferred_providers: vec!["claude-cli", "codex-cli"],
+            fallback_providers: vec!["gemini-cli", "ollama"],
+        },
         TaskType::General => ModelPreference {
             task_type,
             preferred_providers: vec!["claude-cli", "gemini-cli", "codex-cli"],
@@ -59,66 +80,123 @@ pub fn get_provider_preference(task_type: TaskType) -> ModelPreference {
 /// Team member personality traits
 #[derive(Debug, Clone)]
 pub struct MemberPersonality {
-    pub greeting: &'static str,
-    pub working_message: &'static str,
-    pub success_message: &'static str,
-    pub style_hint: &'static str,
+    pub greeting: String,
+    pub working_message: String,
+    pub success_message: String,
+    pub style_hint: String,
+}
+
+impl MemberPersonality {
+    /// Replace any traits set in a config override
+    pub fn with_overrides(mut self, overrides: &PersonalityOverride) -> Self {
+        let fields = [
+            (&mut self.greeting, &overrides.greeting),
+            (&mut self.working_message, &overrides.working_message),
+            (&mut self.success_message, &overrides.success_message),
+            (&mut self.style_hint, &overrides.style_hint),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        self
+    }
 }
 
 /// Get personality for a team member role
 pub fn get_member_personality(role: &str) -> MemberPersonality {
     match role {
         "Writer" => MemberPersonality {
-            greeting: "I'll craft that for you!",
-            working_message: "Writing...",
-            success_message: "Here's what I've written:",
-            style_hint: "Be creative but clear",
+            greeting: "I'll craft that for you!".into(),
+            working_message: "Writing...".into(),
+            success_message: "Here's what I've written:".into(),
+            style_hint: "Be creative but clear".into(),
         },
         "Researcher" => MemberPersonality {
-            greeting: "Let me look into that!",
-            working_message: "Researching...",
-            success_message: "Here's what I found:",
-            style_hint: "Be thorough and cite sources",
+            greeting: "Let me look into that!".into(),
+            working_message: "Researching...".into(),
+            success_message: "Here's what I found:".into(),
+            style_hint: "Be thorough and cite sources".into(),
         },
         "Analyst" => MemberPersonality {
-            greeting: "I'll analyze this carefully.",
-            working_message: "Analyzing...",
-            success_message: "Here's my analysis:",
-            style_hint: "Be logical and detailed",
+            greeting: "I'll analyze this carefully.".into(),
+            working_message: "Analyzing...".into(),
+            success_message: "Here's my analysis:".into(),
+            style_hint: "Be logical and detailed".into(),
         },
         "Creative" => MemberPersonality {
-            greeting: "Let's get creative!",
-            working_message: "Brainstorming...",
-            success_message: "Here are my ideas:",
-            style_hint: "Think outside the box",
+            greeting: "Let's get creative!".into(),
+            working_message: "Brainstorming...".into(),
+            success_message: "Here are my ideas:".into(),
+            style_hint: "Think outside the box".into(),
         },
         "Editor" => MemberPersonality {
-            greeting: "I'll polish that up!",
-            working_message: "Editing...",
-            success_message: "Here's the improved version:",
-            style_hint: "Focus on clarity and flow",
+            greeting: "I'll polish that up!".into(),
+            working_message: "Editing...".into(),
+            success_message: "Here's the improved version:".into(),
+            style_hint: "Focus on clarity and flow".into(),
         },
         "Explainer" => MemberPersonality {
-            greeting: "Let me break that down for you.",
-            working_message: "Explaining...",
-            success_message: "Here's the explanation:",
-            style_hint: "Be simple and patient",
+            greeting: "Let me break that down for you.".into(),
+            working_message: "Explaining...".into(),
+            success_message: "Here's the explanation:".into(),
+            style_hint: "Be simple and patient".into(),
         },
         "Problem Solver" => MemberPersonality {
-            greeting: "I'll help you solve this!",
-            working_message: "Problem solving...",
-            success_message: "Here's the solution:",
-            style_hint: "Be practical and step-by-step",
+            greeting: "I'll help you solve this!".into(),
+            working_message: "Problem solving...".into(),
+            success_message: "Here's the solution:".into(),
+            style_hint: "Be practical and step-by-step".into(),
+        },
+        "Summarizer" => MemberPersonality {
+            greeting: "I'll boil that down for you.".into(),
+            working_message: "Summarizing...".into(),
+            success_message: "Here's the short version:".into(),
+            style_hint: "Be brief and keep what matters".into(),
+        },
+        "Translator" => MemberPersonality {
+            greeting: "I'll translate that for you.".into(),
+            working_message: "Translating...".into(),
+            success_message: "Here's the translation:".into(),
+            style_hint: "Be faithful to meaning and tone".into(),
+        },
+        "Coder" => MemberPersonality {
+            greeting: "Let's build it!".into(),
+            working_message: "Coding...".into(),
+            success_message: "Here's the code:".into(),
+            style_hint: "Be correct and idiomatic, and explain briefly".into(),
+        },
+        "Reviewer" => MemberPersonality {
+            greeting: "I'll take a close look.".into(),
+            working_message: "Reviewing...".into(),
+            success_message: "Here's my review:".into(),
+            style_hint: "Be specific and rank issues by severity".into(),
         },
         _ => MemberPersonality {
-            greeting: "I'm here to help!",
-            working_message: "Working on it...",
-            success_message: "Here you go:",
-            style_hint: "Be helpful and friendly",
+            greeting: "I'm here to help!".into(),
+            working_message: "Working on it...".into(),
+            success_message: "Here you go:".into(),
+            style_hint: "Be helpful and friendly".into(),
         },
     }
 }
 
+impl SupportTeam {
+    /// A member's personality, with any `[personalities.<name>]` overrides applied
+    pub fn personality(&self, member: &TeamMember) -> MemberPersonality {
+        let personality = get_member_personality(&member.role);
+        match self
+            .personalities
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&member.name))
+        {
+            Some((_, overrides)) => personality.with_overrides(overrides),
+            None => personality,
+        }
+    }
+}
+
 /// Team member icons for UI display
 pub fn get_member_icon(role: &str) -> &'static str {
     match role {
@@ -129,6 +207,10 @@ pub fn get_member_icon(role: &str) -> &'static str {
         "Editor" => "[E]",
         "Explainer" => "[X]",
         "Problem Solver" => "[S]",
+        "Summarizer" => "[U]",
+        "Translator" => "[T]",
+        "Coder" => "[K]",
+        "Reviewer" => "[V]",
         "General Assistant" => "[G]",
         _ => "[?]",
     }
@@ -145,6 +227,12 @@ mod tests {
 
         let analyze_pref = get_provider_preference(TaskType::Analyze);
         assert!(analyze_pref.preferred_providers.contains(&"codex-cli"));
+
+        let code_pref = get_provider_preference(TaskType::Code);
+        assert_eq!(code_pref.preferred_providers[0], "codex-cli");
+
+        let review_pref = get_provider_preference(TaskType::Review);
+        assert_eq!(review_pref.preferred_providers[0], "claude-cli");
     }
 
     #[test]
@@ -155,4 +243,17 @@ mod tests {
         let researcher = get_member_personality("Researcher");
         assert!(researcher.greeting.contains("look"));
     }
+
+    #[test]
+    fn test_personality_overrides() {
+        let overrides = PersonalityOverride {
+            greeting: Some("On it.".to_string()),
+            style_hint: Some("Terse, code first".to_string()),
+            ..Default::default()
+        };
+        let coder = get_member_personality("Coder").with_overrides(&overrides);
+        assert_eq!(coder.greeting, "On it.");
+        assert_eq!(coder.style_hint, "Terse, code first");
+        assert_eq!(coder.working_message, "Coding...");
+    }
 }
@@ -3,20 +3,37 @@
 //! The Support Team analyzes user requests, breaks them into tasks,
 //! and assigns the best team member (model) for each task.
 
+mod adaptive;
 mod analyzer;
+mod classifier;
 mod members;
+mod prompts;
+mod routing;
+mod sampling;
+mod tiers;
+mod voting;
 mod workflow;
 
+pub use adaptive::*;
 pub use analyzer::*;
+pub use classifier::*;
 pub use members::*;
+pub use prompts::*;
+pub use routing::*;
+pub use sampling::*;
+pub use tiers::*;
+pub use voting::*;
 pub use workflow::*;
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
 use crate::llm::{
     ClaudeCliProvider, CodexCliProvider, GeminiCliProvider,
     OllamaProvider, LlmProvider, detect_available_providers, detect_available_providers_async, StreamCallback,
 };
-use crate::config::Config;
+use crate::cache::CacheKey;
+use crate::config::{Config, PersonalityOverride, SamplingConfig, StickyConfig, TierConfig};
 
 /// A task in the workflow
 #[derive(Debug, Clone)]
@@ -40,7 +57,8 @@ pub enum TaskProgress {
 }
 
 /// Types of tasks the team can handle
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TaskType {
     /// Writing content (blog posts, emails, documents)
     Write,
@@ -56,6 +74,14 @@ pub enum TaskType {
     Explain,
     /// Problem solving
     Solve,
+    /// Condensing logs, documents or threads
+    Summarize,
+    /// Translating text between languages
+    Translate,
+    /// Writing or changing code
+    Code,
+    /// Reviewing diffs, pull requests or drafts
+    Review,
     /// General assistance
     General,
 }
@@ -71,6 +97,10 @@ impl TaskType {
             TaskType::Edit => "Editing",
             TaskType::Explain => "Explaining",
             TaskType::Solve => "Problem Solving",
+            TaskType::Summarize => "Summarizing",
+            TaskType::Translate => "Translation",
+            TaskType::Code => "Coding",
+            TaskType::Review => "Reviewing",
             TaskType::General => "General Help",
         }
     }
@@ -92,6 +122,43 @@ pub struct SupportTeam {
     providers: std::collections::HashMap<String, Box<dyn LlmProvider>>,
     tasks: Vec<Task>,
     next_task_id: usize,
+    sampling: SamplingConfig,
+    router: Router,
+    adaptive: AdaptiveRouter,
+    /// Trained task classifier, when one has been trained
+    classifier: Option<TaskClassifier>,
+    tiers: TierConfig,
+    sticky: StickyConfig,
+    personalities: std::collections::HashMap<String, PersonalityOverride>,
+    prompts: PromptLibrary,
+    /// Extra template variables from `--var`
+    prompt_vars: std::collections::HashMap<String, String>,
+    /// Recent REPL turns, for `{{history}}`
+    history: Vec<(String, String)>,
+    /// Cost units charged this session (see `TierConfig::session_budget`)
+    spent: f32,
+}
+
+/// Provider that answers with a fixed text, or fails when it has none
+#[cfg(test)]
+pub(crate) struct FixedProvider(pub Option<&'static str>);
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl LlmProvider for FixedProvider {
+    async fn generate(&self, _prompt: &str) -> Result<String> {
+        self.0
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("provider offline"))
+    }
+
+    fn name(&self) -> &str {
+        "fixed"
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
 }
 
 /// Helper to create team members and providers from available provider list
@@ -129,6 +196,20 @@ fn create_team_members_and_providers(
             provider_type: "gemini-cli".to_string(),
             available: true,
         });
+        members.push(TeamMember {
+            name: "Sumi".to_string(),
+            role: "Summarizer".to_string(),
+            specialty: TaskType::Summarize,
+            provider_type: "gemini-cli".to_string(),
+            available: true,
+        });
+        members.push(TeamMember {
+            name: "Lin".to_string(),
+            role: "Translator".to_string(),
+            specialty: TaskType::Translate,
+            provider_type: "gemini-cli".to_string(),
+            available: true,
+        });
         providers.insert("gemini-cli".to_string(), Box::new(GeminiCliProvider::new()));
     }
 
@@ -147,6 +228,13 @@ fn create_team_members_and_providers(
             provider_type: "codex-cli".to_string(),
             available: true,
         });
+        members.push(TeamMember {
+            name: "Ada".to_string(),
+            role: "Coder".to_string(),
+            specialty: TaskType::Code,
+            provider_type: "codex-cli".to_string(),
+            available: true,
+        });
         providers.insert("codex-cli".to_string(), Box::new(CodexCliProvider::new()));
     }
 
@@ -165,6 +253,13 @@ fn create_team_members_and_providers(
             provider_type: "claude-cli".to_string(),
             available: true,
         });
+        members.push(TeamMember {
+            name: "Rex".to_string(),
+            role: "Reviewer".to_string(),
+            specialty: TaskType::Review,
+            provider_type: "claude-cli".to_string(),
+            available: true,
+        });
         providers.insert("claude-cli".to_string(), Box::new(ClaudeCliProvider::new()));
     }
 
@@ -206,6 +301,43 @@ impl SupportTeam {
             providers,
             tasks: Vec::new(),
             next_task_id: 1,
+            sampling: config.sampling.clone(),
+            router: Router::new(&config.routing),
+            adaptive: AdaptiveRouter::load(&config.routing.adaptive),
+            classifier: TaskClassifier::load(&TaskClassifier::default_path()),
+            tiers: config.routing.tiers.clone(),
+            sticky: config.routing.sticky.clone(),
+            personalities: config.personalities.clone(),
+            prompts: PromptLibrary::load(),
+            prompt_vars: Default::default(),
+            history: Vec::new(),
+            spent: 0.0,
+        }
+    }
+
+    /// A team on the given providers, without detecting what is installed
+    /// or loading anything from disk
+    #[cfg(test)]
+    pub(crate) fn for_test(available: &[&str], config: &Config) -> Self {
+        let available: Vec<String> = available.iter().map(|p| p.to_string()).collect();
+        let (members, providers) = create_team_members_and_providers(&available, config);
+
+        Self {
+            members,
+            providers,
+            tasks: Vec::new(),
+            next_task_id: 1,
+            sampling: config.sampling.clone(),
+            router: Router::new(&config.routing),
+            adaptive: AdaptiveRouter::in_memory(&config.routing.adaptive),
+            classifier: None,
+            tiers: config.routing.tiers.clone(),
+            sticky: config.routing.sticky.clone(),
+            personalities: config.personalities.clone(),
+            prompts: Default::default(),
+            prompt_vars: Default::default(),
+            history: Vec::new(),
+            spent: 0.0,
         }
     }
 
@@ -219,6 +351,17 @@ impl SupportTeam {
             providers,
             tasks: Vec::new(),
             next_task_id: 1,
+            sampling: config.sampling.clone(),
+            router: Router::new(&config.routing),
+            adaptive: AdaptiveRouter::load(&config.routing.adaptive),
+            classifier: TaskClassifier::load(&TaskClassifier::default_path()),
+            tiers: config.routing.tiers.clone(),
+            sticky: config.routing.sticky.clone(),
+            personalities: config.personalities.clone(),
+            prompts: PromptLibrary::load(),
+            prompt_vars: Default::default(),
+            history: Vec::new(),
+            spent: 0.0,
         }
     }
 
@@ -232,14 +375,35 @@ impl SupportTeam {
         &self.tasks
     }
 
+    /// Providers with an available member, in team order
+    pub(super) fn available_providers(&self) -> Vec<String> {
+        let mut providers: Vec<String> = Vec::new();
+        for member in self.members.iter().filter(|m| m.available) {
+            if self.providers.contains_key(&member.provider_type)
+                && !providers.contains(&member.provider_type)
+            {
+                providers.push(member.provider_type.clone());
+            }
+        }
+        providers
+    }
+
     /// Find the best team member for a task type
     pub fn find_member_for_task(&self, task_type: TaskType) -> Option<&TeamMember> {
+        self.select_member_for_task(task_type).0
+    }
+
+    /// Find the best team member for a task type, and which rule picked it
+    pub fn select_member_for_task(
+        &self,
+        task_type: TaskType,
+    ) -> (Option<&TeamMember>, SelectionBranch) {
         // First try to find exact specialty match (prefer CLI providers)
         if let Some(member) = self.members.iter()
             .filter(|m| m.specialty == task_type && m.available)
             .find(|m| m.provider_type.ends_with("-cli"))
         {
-            return Some(member);
+            return (Some(member), SelectionBranch::SpecialistCli);
         }
 
         // For General tasks, prefer CLI providers first
@@ -248,13 +412,13 @@ impl SupportTeam {
                 .filter(|m| m.available)
                 .find(|m| m.provider_type.ends_with("-cli"))
             {
-                return Some(member);
+                return (Some(member), SelectionBranch::GeneralCli);
             }
         }
 
         // Then any exact specialty match
         if let Some(member) = self.members.iter().find(|m| m.specialty == task_type && m.available) {
-            return Some(member);
+            return (Some(member), SelectionBranch::Specialist);
         }
 
         // Fall back to any available CLI provider
@@ -262,26 +426,33 @@ impl SupportTeam {
             .filter(|m| m.available)
             .find(|m| m.provider_type.ends_with("-cli"))
         {
-            return Some(member);
+            return (Some(member), SelectionBranch::AnyCli);
         }
 
         // Finally, any available member
-        self.members.iter().find(|m| m.available)
+        match self.members.iter().find(|m| m.available) {
+            Some(member) => (Some(member), SelectionBranch::AnyMember),
+            None => (None, SelectionBranch::NoMember),
+        }
     }
 
     /// Analyze request and create tasks
     pub fn plan_request(&mut self, request: &str) -> Vec<Task> {
-        let task_type = analyze_request(request);
+        let route = self.explain_route(request, None, None).route();
+        self.plan_route(request, &route)
+    }
 
+    /// Create tasks for a request that has already been routed
+    pub fn plan_route(&mut self, request: &str, route: &Route) -> Vec<Task> {
         // For simple requests, create single task
         // For complex requests, could break into subtasks
         let task = Task {
             id: self.next_task_id,
-            title: format!("{} task", task_type.display_name()),
+            title: format!("{} task", route.task_type.display_name()),
             description: request.to_string(),
-            task_type,
+            task_type: route.task_type,
             status: TaskProgress::Pending,
-            assigned_to: self.find_member_for_task(task_type).map(|m| m.name.clone()),
+            assigned_to: route.member.clone(),
             result: None,
         };
 
@@ -297,19 +468,33 @@ impl SupportTeam {
             .ok_or_else(|| anyhow::anyhow!("Task not found"))?;
 
         task.status = TaskProgress::InProgress;
+        let (description, task_type, assigned_to) =
+            (task.description.clone(), task.task_type, task.assigned_to.clone());
 
         // Find the provider for this task
         let member = self.members.iter()
-            .find(|m| Some(m.name.clone()) == task.assigned_to)
+            .find(|m| Some(&m.name) == assigned_to.as_ref())
             .ok_or_else(|| anyhow::anyhow!("No team member assigned"))?;
 
         let provider = self.providers.get(&member.provider_type)
             .ok_or_else(|| anyhow::anyhow!("Provider not available"))?;
 
-        // Create a prompt based on task type
-        let prompt = create_task_prompt(&task.description, task.task_type, &member.role);
+        // Create a prompt based on task type and the member's style
+        let prompt = self.member_prompt(&description, task_type, member);
+        let (provider_name, member_name) = (member.provider_type.clone(), member.name.clone());
+
+        let started = Instant::now();
+        let result = provider.generate(&prompt).await;
+        self.charge(&provider_name);
+        self.record_outcome(Outcome::new(
+            &provider_name,
+            &member_name,
+            task_type,
+            started.elapsed().as_millis() as u64,
+            result.is_ok(),
+        ).with_request(&description));
 
-        match provider.generate(&prompt).await {
+        match result {
             Ok(response) => {
                 // Update task in tasks list
                 if let Some(t) = self.tasks.iter_mut().find(|t| t.id == task_id) {
@@ -329,8 +514,55 @@ impl SupportTeam {
 
     /// Process a user request end-to-end
     pub async fn handle_request(&mut self, request: &str) -> Result<(String, Vec<Task>)> {
+        let route = self.explain_route(request, None, None).route();
+        self.handle_route(request, &route).await
+    }
+
+    /// The cache key for answering a routed request with `provider`
+    ///
+    /// Covers the prompt the assigned member would be sent, the provider's
+    /// model settings, the template and the sampling `strategy`.
+    pub fn cache_key(
+        &self,
+        request: &str,
+        route: &Route,
+        provider: &str,
+        strategy: &str,
+    ) -> CacheKey {
+        let member = route
+            .member
+            .as_ref()
+            .and_then(|name| self.members.iter().find(|m| &m.name == name));
+        let (prompt, template) = match member {
+            Some(member) => (
+                self.member_prompt(request, route.task_type, member),
+                self.prompts.template_for(route.task_type, &member.role),
+            ),
+            None => (request.to_string(), Template::builtin()),
+        };
+        CacheKey {
+            prompt,
+            provider: provider.to_string(),
+            settings: self
+                .providers
+                .get(provider)
+                .map(|p| p.settings())
+                .unwrap_or_default(),
+            task_type: PromptLibrary::task_name(route.task_type),
+            template: template.version(),
+            strategy: strategy.to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Process a request end-to-end along an already decided route
+    pub async fn handle_route(
+        &mut self,
+        request: &str,
+        route: &Route,
+    ) -> Result<(String, Vec<Task>)> {
         // Plan the request into tasks
-        let tasks = self.plan_request(request);
+        let tasks = self.plan_route(request, route);
 
         if tasks.is_empty() {
             return Err(anyhow::anyhow!("Could not create tasks for this request"));
@@ -362,19 +594,33 @@ impl SupportTeam {
             .ok_or_else(|| anyhow::anyhow!("Task not found"))?;
 
         task.status = TaskProgress::InProgress;
+        let (description, task_type, assigned_to) =
+            (task.description.clone(), task.task_type, task.assigned_to.clone());
 
         // Find the provider for this task
         let member = self.members.iter()
-            .find(|m| Some(m.name.clone()) == task.assigned_to)
+            .find(|m| Some(&m.name) == assigned_to.as_ref())
             .ok_or_else(|| anyhow::anyhow!("No team member assigned"))?;
 
         let provider = self.providers.get(&member.provider_type)
             .ok_or_else(|| anyhow::anyhow!("Provider not available"))?;
 
-        // Create a prompt based on task type
-        let prompt = create_task_prompt(&task.description, task.task_type, &member.role);
+        // Create a prompt based on task type and the member's style
+        let prompt = self.member_prompt(&description, task_type, member);
+        let (provider_name, member_name) = (member.provider_type.clone(), member.name.clone());
 
-        match provider.generate_streaming(&prompt, callback).await {
+        let started = Instant::now();
+        let result = provider.generate_streaming(&prompt, callback).await;
+        self.charge(&provider_name);
+        self.record_outcome(Outcome::new(
+            &provider_name,
+            &member_name,
+            task_type,
+            started.elapsed().as_millis() as u64,
+            result.is_ok(),
+        ).with_request(&description));
+
+        match result {
             Ok(response) => {
                 // Update task in tasks list
                 if let Some(t) = self.tasks.iter_mut().find(|t| t.id == task_id) {
@@ -397,9 +643,20 @@ impl SupportTeam {
         &mut self,
         request: &str,
         callback: StreamCallback,
+    ) -> Result<(String, Vec<Task>)> {
+        let route = self.explain_route(request, None, None).route();
+        self.handle_route_streaming(request, &route, callback).await
+    }
+
+    /// Process a request with streaming output along an already decided route
+    pub async fn handle_route_streaming(
+        &mut self,
+        request: &str,
+        route: &Route,
+        callback: StreamCallback,
     ) -> Result<(String, Vec<Task>)> {
         // Plan the request into tasks
-        let tasks = self.plan_request(request);
+        let tasks = self.plan_route(request, route);
 
         if tasks.is_empty() {
             return Err(anyhow::anyhow!("Could not create tasks for this request"));
@@ -443,61 +700,14 @@ impl SupportTeam {
     }
 }
 
-/// Analyze a request to determine task type
-fn analyze_request(request: &str) -> TaskType {
-    let lower = request.to_lowercase();
-
-    // Check for writing indicators
-    if lower.contains("write") || lower.contains("draft") || lower.contains("compose")
-        || lower.contains("blog") || lower.contains("email") || lower.contains("letter")
-        || lower.contains("article") || lower.contains("story") {
-        return TaskType::Write;
-    }
-
-    // Check for research indicators
-    if lower.contains("research") || lower.contains("find") || lower.contains("search")
-        || lower.contains("look up") || lower.contains("what is") || lower.contains("who is")
-        || lower.contains("where is") || lower.contains("when did") {
-        return TaskType::Research;
-    }
-
-    // Check for analysis indicators
-    if lower.contains("analyze") || lower.contains("review") || lower.contains("code")
-        || lower.contains("debug") || lower.contains("data") || lower.contains("compare") {
-        return TaskType::Analyze;
-    }
-
-    // Check for creative indicators
-    if lower.contains("create") || lower.contains("brainstorm") || lower.contains("ideas")
-        || lower.contains("design") || lower.contains("imagine") || lower.contains("invent") {
-        return TaskType::Create;
-    }
-
-    // Check for editing indicators
-    if lower.contains("edit") || lower.contains("proofread") || lower.contains("improve")
-        || lower.contains("fix") || lower.contains("rewrite") || lower.contains("polish") {
-        return TaskType::Edit;
-    }
-
-    // Check for explanation indicators
-    if lower.contains("explain") || lower.contains("how does") || lower.contains("why does")
-        || lower.contains("teach") || lower.contains("help me understand") {
-        return TaskType::Explain;
-    }
-
-    // Check for problem solving indicators
-    if lower.contains("solve") || lower.contains("problem") || lower.contains("issue")
-        || lower.contains("error") || lower.contains("broken") || lower.contains("not working") {
-        return TaskType::Solve;
-    }
-
-    // Default to general
-    TaskType::General
+/// Wrap text in a translation request for `--to <language>`
+pub fn translation_request(text: &str, language: &str) -> String {
+    format!("Translate the following into {}:\n\n{}", language, text)
 }
 
-/// Create a prompt tailored to the task type and role
-fn create_task_prompt(request: &str, task_type: TaskType, role: &str) -> String {
-    let context = match task_type {
+/// The built-in context sentence for a task type (`{{context}}` in templates)
+pub fn task_context(task_type: TaskType) -> &'static str {
+    match task_type {
         TaskType::Write => "You are a skilled writer. Create clear, engaging content.",
         TaskType::Research => "You are a thorough researcher. Find accurate, relevant information.",
         TaskType::Analyze => "You are an analytical expert. Provide detailed, logical analysis.",
@@ -505,13 +715,16 @@ fn create_task_prompt(request: &str, task_type: TaskType, role: &str) -> String
         TaskType::Edit => "You are a meticulous editor. Improve clarity and quality.",
         TaskType::Explain => "You are a patient teacher. Explain concepts simply and clearly.",
         TaskType::Solve => "You are a problem solver. Find practical, effective solutions.",
+        TaskType::Summarize => "You are a concise summarizer. Keep the key points, drop the rest.",
+        TaskType::Translate => {
+            "You are a careful translator. Preserve meaning, tone and formatting."
+        }
+        TaskType::Code => "You are a senior engineer. Write correct, idiomatic, tested code.",
+        TaskType::Review => {
+            "You are a thorough reviewer. Flag bugs and risks first, then improvements."
+        }
         TaskType::General => "You are a helpful assistant. Provide useful, friendly assistance.",
-    };
-
-    format!(
-        "{}\n\nAs the team's {}, please help with this request:\n\n{}",
-        context, role, request
-    )
+    }
 }
 
 #[cfg(test)]
@@ -539,6 +752,27 @@ mod tests {
         assert_eq!(analyze_request("debug this function"), TaskType::Analyze);
     }
 
+    #[test]
+    fn test_translation_prompt() {
+        let request = translation_request("Bonjour à tous", "English");
+        assert_eq!(analyze_request(&request), TaskType::Translate);
+
+        let vars = [
+            ("context", task_context(TaskType::Translate)),
+            ("role", "Translator"),
+            ("style", "be exact"),
+            ("request", request.as_str()),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        let (prompt, missing) = Template::builtin().render(&vars);
+        assert!(missing.is_empty());
+        assert!(prompt.starts_with("You are a careful translator."));
+        assert!(prompt.contains("As the team's Translator (be exact)"));
+        assert!(prompt.ends_with("Translate the following into English:\n\nBonjour à tous"));
+    }
+
     #[test]
     fn test_analyze_request_general() {
         assert_eq!(analyze_request("hello"), TaskType::General);
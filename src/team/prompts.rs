@@ -0,0 +1,326 @@
+//! Prompt templates - user and project overrides for what members are sent
+//!
+//! Templates are plain text files with `{{placeholder}}`s. A role template
+//! (`roles/coder.md`) wins over a task template (`code.md`), which wins over
+//! the built-in prompt. Project templates in `.workyterm/prompts/` shadow the
+//! user's in `~/.workyterm/prompts/`.
+
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use super::routing::EXPANDED_BLOCK;
+use super::{task_context, SupportTeam, TaskType, TeamMember};
+use crate::cache::hex;
+
+/// `{{name}}` placeholders
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([\w.-]+)\s*\}\}").unwrap());
+
+/// The prompt used when no template file applies
+pub const DEFAULT_TEMPLATE: &str = "{{context}}\n\n\
+    As the team's {{role}} ({{style}}), please help with this request:\n\n{{request}}";
+
+/// Turns of conversation kept for `{{history}}`
+const HISTORY_TURNS: usize = 3;
+
+/// Characters of each past request or answer kept for `{{history}}`
+const HISTORY_CHARS: usize = 1_000;
+
+/// Placeholders filled by WorkyTerm itself; `--var` can't override them
+pub const BUILTIN_VARS: &[&str] =
+    &["context", "role", "style", "task", "request", "files", "history"];
+
+/// A prompt template, from a file or built in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// `code`, `roles/coder`, or `builtin`
+    pub name: String,
+    /// File the template came from; `None` for the built-in prompt
+    pub path: Option<PathBuf>,
+    pub text: String,
+}
+
+impl Template {
+    pub fn builtin() -> Self {
+        Self {
+            name: "builtin".to_string(),
+            path: None,
+            text: DEFAULT_TEMPLATE.to_string(),
+        }
+    }
+
+    /// Name and a short content hash (`code@1a2b3c4d5e6f`), for cache keys
+    pub fn version(&self) -> String {
+        let digest = hex(&Sha256::digest(self.text.as_bytes()));
+        format!("{}@{}", self.name, &digest[..12])
+    }
+
+    /// Whether the template uses `{{name}}`
+    pub fn uses(&self, name: &str) -> bool {
+        PLACEHOLDER.captures_iter(&self.text).any(|c| &c[1] == name)
+    }
+
+    /// Fill in placeholders; returns the prompt and any names left unfilled
+    pub fn render(&self, vars: &HashMap<String, String>) -> (String, Vec<String>) {
+        let mut missing = Vec::new();
+        let rendered = PLACEHOLDER.replace_all(&self.text, |caps: &regex::Captures| {
+            match vars.get(&caps[1]) {
+                Some(value) => value.clone(),
+                None => {
+                    if !missing.iter().any(|m| m == &caps[1]) {
+                        missing.push(caps[1].to_string());
+                    }
+                    caps[0].to_string()
+                }
+            }
+        });
+        (rendered.into_owned(), missing)
+    }
+}
+
+/// A template file found on disk
+#[derive(Debug, Clone)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub path: PathBuf,
+    /// A higher-priority directory has a template of the same name
+    pub shadowed: bool,
+}
+
+/// Template directories, highest priority first
+#[derive(Debug, Clone, Default)]
+pub struct PromptLibrary {
+    dirs: Vec<PathBuf>,
+}
+
+impl PromptLibrary {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self { dirs }
+    }
+
+    /// `.workyterm/prompts/` in the current directory, then `~/.workyterm/prompts/`
+    pub fn load() -> Self {
+        let mut dirs = vec![PathBuf::from(".workyterm/prompts")];
+        if let Some(home) = dirs::home_dir() {
+            dirs.push(home.join(".workyterm/prompts"));
+        }
+        Self::new(dirs)
+    }
+
+    /// Template name for a task type (`code`)
+    pub fn task_name(task_type: TaskType) -> String {
+        serde_json::to_value(task_type)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_else(|| "general".to_string())
+    }
+
+    /// Template name for a role (`roles/problem-solver`)
+    pub fn role_name(role: &str) -> String {
+        format!("roles/{}", role.to_lowercase().replace(' ', "-"))
+    }
+
+    /// The highest-priority template file called `name`
+    pub fn find(&self, name: &str) -> Option<Template> {
+        self.dirs.iter().find_map(|dir| {
+            let path = dir.join(format!("{}.md", name));
+            let text = std::fs::read_to_string(&path).ok()?;
+            Some(Template {
+                name: name.to_string(),
+                path: Some(path),
+                text,
+            })
+        })
+    }
+
+    /// The template a member gets: role, then task, then built-in
+    pub fn template_for(&self, task_type: TaskType, role: &str) -> Template {
+        self.find(&Self::role_name(role))
+            .or_else(|| self.find(&Self::task_name(task_type)))
+            .unwrap_or_else(Template::builtin)
+    }
+
+    /// Every template file, in priority order
+    pub fn list(&self) -> Vec<TemplateInfo> {
+        let mut found: Vec<TemplateInfo> = Vec::new();
+        for dir in &self.dirs {
+            let mut names = template_names(dir, "");
+            names.extend(template_names(&dir.join("roles"), "roles/"));
+            names.sort();
+            for (name, path) in names {
+                let shadowed = found.iter().any(|t| t.name == name);
+                found.push(TemplateInfo { name, path, shadowed });
+            }
+        }
+        found
+    }
+}
+
+/// `(name, path)` of the `.md` files directly in `dir`
+fn template_names(dir: &Path, prefix: &str) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?.to_string();
+            Some((format!("{}{}", prefix, stem), path))
+        })
+        .collect()
+}
+
+/// Split expanded `@file` blocks out of a request for `{{files}}`
+///
+/// The blocks are replaced by their `@path` in the request.
+pub fn split_files(request: &str) -> (String, String) {
+    let files: Vec<&str> = EXPANDED_BLOCK
+        .find_iter(request)
+        .map(|m| m.as_str().trim_start_matches('\n'))
+        .collect();
+    let text = EXPANDED_BLOCK.replace_all(request, "@$1");
+    (text.trim().to_string(), files.join("\n\n"))
+}
+
+impl SupportTeam {
+    /// Extra `{{name}}` values from `--var name=value`
+    pub fn set_prompt_vars(&mut self, vars: Vec<(String, String)>) {
+        for (name, _) in vars.iter().filter(|(name, _)| BUILTIN_VARS.contains(&name.as_str())) {
+            eprintln!("Warning: --var {} is set by WorkyTerm and will be ignored", name);
+        }
+        self.prompt_vars = vars.into_iter().collect();
+    }
+
+    /// Keep a finished REPL turn for `{{history}}`
+    pub fn remember_turn(&mut self, request: &str, response: &str) {
+        let clip = |text: &str| text.chars().take(HISTORY_CHARS).collect::<String>();
+        self.history.push((clip(request), clip(response)));
+        if self.history.len() > HISTORY_TURNS {
+            self.history.remove(0);
+        }
+    }
+
+    /// Forget the conversation kept for `{{history}}`
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// The template files this team reads prompts from
+    pub fn prompt_library(&self) -> &PromptLibrary {
+        &self.prompts
+    }
+
+    /// The prompt `member` gets for a request, from its template and style
+    pub fn member_prompt(&self, request: &str, task_type: TaskType, member: &TeamMember) -> String {
+        self.render_prompt(request, task_type, member).0
+    }
+
+    /// Like `member_prompt`, also returning placeholders nothing filled
+    pub fn render_prompt(
+        &self,
+        request: &str,
+        task_type: TaskType,
+        member: &TeamMember,
+    ) -> (String, Vec<String>) {
+        let template = self.prompts.template_for(task_type, &member.role);
+        let (text, files) = if template.uses("files") {
+            split_files(request)
+        } else {
+            (request.to_string(), String::new())
+        };
+        let history = self
+            .history
+            .iter()
+            .map(|(request, response)| format!("User: {}\nAssistant: {}", request, response))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut vars = self.prompt_vars.clone();
+        let style = self.personality(member).style_hint;
+        let builtins = [
+            ("context", task_context(task_type).to_string()),
+            ("role", member.role.clone()),
+            ("style", style.trim_end_matches('.').to_lowercase()),
+            ("task", task_type.display_name().to_string()),
+            ("request", text),
+            ("files", files),
+            ("history", history),
+        ];
+        vars.extend(builtins.map(|(name, value)| (name.to_string(), value)));
+
+        template.render(&vars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render_placeholders() {
+        let template = Template {
+            name: "code".to_string(),
+            path: None,
+            text: "{{ role }} for {{team}}: {{request}} {{unknown}}".to_string(),
+        };
+        let (prompt, missing) = template.render(&vars(&[
+            ("role", "Coder"),
+            ("team", "payments"),
+            ("request", "add retries"),
+        ]));
+        assert_eq!(prompt, "Coder for payments: add retries {{unknown}}");
+        assert_eq!(missing, vec!["unknown"]);
+        assert!(template.uses("team"));
+        assert!(!template.uses("files"));
+
+        let edited = Template { text: format!("{} ", template.text), ..template.clone() };
+        assert!(template.version().starts_with("code@"));
+        assert_ne!(template.version(), edited.version());
+    }
+
+    #[test]
+    fn test_split_files() {
+        let request =
+            "review this \n--- src/lib.rs ---\nfn a() {}\n---\nand this \n--- b.rs ---\nx\n---";
+        let (text, files) = split_files(request);
+        assert_eq!(text, "review this @src/lib.rs\nand this @b.rs");
+        assert_eq!(files, "--- src/lib.rs ---\nfn a() {}\n---\n\n--- b.rs ---\nx\n---");
+    }
+
+    #[test]
+    fn test_library_precedence() {
+        let root = tempfile::tempdir().unwrap();
+        let (project, user) = (root.path().join("project"), root.path().join("user"));
+        std::fs::create_dir_all(project.join("roles")).unwrap();
+        std::fs::create_dir_all(&user).unwrap();
+        std::fs::write(user.join("code.md"), "user code {{request}}").unwrap();
+        std::fs::write(project.join("code.md"), "project code {{request}}").unwrap();
+        std::fs::write(project.join("roles/problem-solver.md"), "solver {{request}}").unwrap();
+
+        let library = PromptLibrary::new(vec![project.clone(), user.clone()]);
+        assert_eq!(library.template_for(TaskType::Code, "Coder").text, "project code {{request}}");
+        let solver = library.template_for(TaskType::Code, "Problem Solver");
+        assert_eq!(solver.name, "roles/problem-solver");
+        assert_eq!(library.template_for(TaskType::Write, "Writer"), Template::builtin());
+
+        let listed: Vec<(String, bool)> =
+            library.list().into_iter().map(|t| (t.name, t.shadowed)).collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("code".to_string(), false),
+                ("roles/problem-solver".to_string(), false),
+                ("code".to_string(), true),
+            ]
+        );
+    }
+}
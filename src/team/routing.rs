@@ -0,0 +1,813 @@
+//! Request routing - config rules first, then the classifier
+//!
+//! `[[routing.rules]]` entries let users override where requests go without
+//! recompiling. The first rule whose conditions all match decides the task
+//! type, member or provider; anything it leaves unset falls through to the
+//! classifier and the usual member lookup.
+
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+use super::{
+    contains_phrase, AdaptiveDecision, SupportTeam, TaskAnalysis,
+    TaskType, TeamMember, TierDecision,
+};
+use crate::config::{RoutingConfig, RoutingRule};
+
+/// `@path` references, before expansion
+static FILE_REF: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"@([\w./\-]+)").unwrap());
+
+/// `--- path ---` headers left by expanded `@path` references
+static EXPANDED_REF: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^--- (\S+) ---$").unwrap());
+
+/// Expanded `@path` blocks, which don't count towards a follow-up's length
+pub(super) static EXPANDED_BLOCK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)\n--- (\S+) ---\n.*?\n---").unwrap());
+
+/// Openers that mark a request as continuing the previous turn
+const FOLLOW_UP_OPENERS: &[&str] = &[
+    "and", "also", "now", "then", "but", "same", "again", "what about", "how about",
+];
+
+/// A routing rule that matched a request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleMatch {
+    /// Rule name, or "rule N" (1-based) when it has none
+    pub name: String,
+    pub task: Option<TaskType>,
+    pub member: Option<String>,
+    pub provider: Option<String>,
+}
+
+/// Where a request goes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub task_type: TaskType,
+    /// Assigned member's name, if any member can take it
+    pub member: Option<String>,
+    pub provider: Option<String>,
+    /// The config rule that decided the route, if one matched
+    pub rule: Option<RuleMatch>,
+}
+
+/// Where the task type came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSource {
+    /// The `-t` flag
+    Hint,
+    /// A config routing rule
+    Rule,
+    /// The request classifier
+    Classifier,
+    /// A follow-up, kept on the previous turn's task type
+    FollowUp,
+}
+
+/// Which branch of member selection picked the member
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionBranch {
+    /// A routing rule named the member
+    RuleMember,
+    /// `-m` or a routing rule named the provider
+    Provider,
+    /// A specialist for the task type on a CLI provider
+    SpecialistCli,
+    /// General task: the first CLI provider
+    GeneralCli,
+    /// Tiered routing matched the request's complexity to a provider tier
+    Tier,
+    /// Adaptive routing moved the task to a better-performing provider
+    Adaptive,
+    /// A specialist for the task type on any provider
+    Specialist,
+    /// Fallback: any available CLI provider
+    AnyCli,
+    /// Fallback: any available member
+    AnyMember,
+    /// A follow-up stayed with the previous turn's member
+    FollowUp,
+    /// `/switch` pinned the conversation to this member
+    Pinned,
+    /// Nobody was available
+    NoMember,
+}
+
+impl SelectionBranch {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SelectionBranch::RuleMember => "named by a routing rule",
+            SelectionBranch::Provider => "on the requested provider",
+            SelectionBranch::SpecialistCli => "specialist on a CLI provider",
+            SelectionBranch::GeneralCli => "general task, first CLI provider",
+            SelectionBranch::Tier => "tiered: provider tier for the request's complexity",
+            SelectionBranch::Adaptive => "adaptive: best recent outcomes",
+            SelectionBranch::Specialist => "specialist on a non-CLI provider",
+            SelectionBranch::AnyCli => "fallback: no specialist, any CLI provider",
+            SelectionBranch::AnyMember => "fallback: any available member",
+            SelectionBranch::FollowUp => "follow-up: stayed with the previous member",
+            SelectionBranch::Pinned => "pinned with /switch",
+            SelectionBranch::NoMember => "no member available",
+        }
+    }
+}
+
+/// A team member as seen by member selection
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberCandidate {
+    pub name: String,
+    pub role: String,
+    pub specialty: TaskType,
+    pub provider: String,
+    pub available: bool,
+    pub chosen: bool,
+}
+
+/// The member a REPL conversation is with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thread {
+    pub member: String,
+    pub task_type: TaskType,
+    /// Set by `/switch`: every turn goes to `member` until unpinned
+    pub pinned: bool,
+}
+
+/// How a turn related to the conversation so far
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThreadCheck {
+    /// Member of the previous turn
+    pub previous: String,
+    /// The request looked like a follow-up (short, or opened with "and", "now", ...)
+    pub follow_up: bool,
+    /// The classifier was confident enough of another task type to leave the thread
+    pub topic_change: bool,
+    pub pinned: bool,
+}
+
+/// Everything that went into a routing decision
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingExplanation {
+    pub task_type: TaskType,
+    pub task_source: TaskSource,
+    pub analysis: TaskAnalysis,
+    pub rule: Option<RuleMatch>,
+    /// A matching rule set aside because its provider isn't available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unavailable_rule: Option<RuleMatch>,
+    pub branch: SelectionBranch,
+    pub candidates: Vec<MemberCandidate>,
+    /// Tier and budget, when tiered routing was consulted
+    pub tier: Option<TierDecision>,
+    /// The bandit's view, when adaptive routing was consulted
+    pub adaptive: Option<AdaptiveDecision>,
+    /// Relation to the previous turn, in a conversation
+    pub thread: Option<ThreadCheck>,
+    pub member: Option<String>,
+    pub provider: Option<String>,
+}
+
+impl RoutingExplanation {
+    /// The route this explanation describes
+    pub fn route(&self) -> Route {
+        Route {
+            task_type: self.task_type,
+            member: self.member.clone(),
+            provider: self.provider.clone(),
+            rule: self.rule.clone(),
+        }
+    }
+
+    /// Human-readable report, one fact per line
+    pub fn render(&self) -> String {
+        let mut lines = Vec::new();
+
+        let source = match (self.task_source, &self.rule) {
+            (TaskSource::Hint, _) => "from -t".to_string(),
+            (TaskSource::Rule, Some(rule)) => format!("from rule '{}'", rule.name),
+            (TaskSource::FollowUp, _) => "follow-up to the previous turn".to_string(),
+            _ => format!(
+                "{}, confidence {:.2}",
+                self.analysis.classifier, self.analysis.confidence
+            ),
+        };
+        lines.push(format!("Task type: {} ({})", self.task_type.display_name(), source));
+
+        lines.push(match (&self.rule, &self.unavailable_rule) {
+            (Some(rule), _) => format!("Rule: {} matched", rule.name),
+            (None, Some(rule)) => format!(
+                "Rule: {} matched, but {} is not available; classifier route used",
+                rule.name,
+                rule.provider.as_deref().unwrap_or_default()
+            ),
+            (None, None) => "Rule: none matched".to_string(),
+        });
+
+        lines.push("Scores:".to_string());
+        for score in &self.analysis.scores {
+            let keywords = if score.keywords.is_empty() {
+                String::new()
+            } else {
+                format!("  [{}]", score.keywords.join(", "))
+            };
+            lines.push(format!(
+                "  {:16} {:.2}{}",
+                score.task_type.display_name(),
+                score.score,
+                keywords
+            ));
+        }
+        if !self.analysis.negated.is_empty() {
+            lines.push(format!("Negated: {}", self.analysis.negated.join(", ")));
+        }
+        lines.push(format!(
+            "Complex: {}",
+            if self.analysis.is_complex { "yes" } else { "no" }
+        ));
+
+        lines.push(match (&self.member, &self.provider) {
+            (Some(member), Some(provider)) => format!(
+                "Member: {} on {} ({})",
+                member,
+                provider,
+                self.branch.describe()
+            ),
+            _ => format!("Member: none ({})", self.branch.describe()),
+        });
+
+        if let Some(ref check) = self.thread {
+            let verdict = if check.pinned {
+                format!("pinned to {}", check.previous)
+            } else if check.follow_up && !check.topic_change {
+                format!("follow-up, stayed with {}", check.previous)
+            } else if check.follow_up {
+                format!("topic change, left {}", check.previous)
+            } else {
+                format!("new request after {}", check.previous)
+            };
+            lines.push(format!("Thread: {}", verdict));
+        }
+
+        if let Some(ref decision) = self.tier {
+            lines.push(format!("Tier: {}", decision.describe()));
+        }
+
+        if let Some(ref decision) = self.adaptive {
+            let arms: Vec<String> = decision
+                .arms
+                .iter()
+                .map(|arm| match arm.reward {
+                    Some(reward) => format!("{} {:.2} ({})", arm.provider, reward, arm.samples),
+                    None => format!("{} - (0)", arm.provider),
+                })
+                .collect();
+            let pick = if decision.explored { "explored" } else { "picked" };
+            lines.push(format!(
+                "Adaptive: {} {} | {}",
+                pick,
+                decision.provider,
+                arms.join(", ")
+            ));
+        }
+
+        lines.push("Candidates:".to_string());
+        for candidate in &self.candidates {
+            let marker = if candidate.chosen { "*" } else { " " };
+            let status = if candidate.available { "" } else { "  (unavailable)" };
+            lines.push(format!(
+                "  {} {:8} {:16} {:16} {}{}",
+                marker,
+                candidate.name,
+                candidate.role,
+                candidate.specialty.display_name(),
+                candidate.provider,
+                status
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// A rule with its pattern compiled
+struct CompiledRule {
+    name: String,
+    pattern: Option<Regex>,
+    rule: RoutingRule,
+}
+
+/// Config routing rules, ready to match
+#[derive(Default)]
+pub struct Router {
+    rules: Vec<CompiledRule>,
+}
+
+impl Router {
+    /// Compile the configured rules, skipping any that can never match
+    pub fn new(config: &RoutingConfig) -> Self {
+        let mut rules = Vec::new();
+
+        for (index, rule) in config.rules.iter().enumerate() {
+            let name = rule
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("rule {}", index + 1));
+
+            let pattern = match rule.pattern.as_deref().map(Regex::new).transpose() {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    eprintln!("Warning: Routing {} has an invalid pattern, skipping: {}", name, e);
+                    continue;
+                }
+            };
+
+            if pattern.is_none() && rule.keywords.is_empty() && rule.files.is_empty() {
+                eprintln!("Warning: Routing {} has no conditions, skipping", name);
+                continue;
+            }
+
+            rules.push(CompiledRule {
+                name,
+                pattern,
+                rule: rule.clone(),
+            });
+        }
+
+        Self { rules }
+    }
+
+    /// First rule whose conditions all match the request
+    pub fn match_rule(&self, request: &str) -> Option<RuleMatch> {
+        let files = file_refs(request);
+
+        self.rules
+            .iter()
+            .find(|compiled| {
+                let rule = &compiled.rule;
+                compiled.pattern.as_ref().is_none_or(|p| p.is_match(request))
+                    && (rule.keywords.is_empty()
+                        || rule.keywords.iter().any(|k| contains_phrase(request, k)))
+                    && (rule.files.is_empty()
+                        || files.iter().any(|f| rule.files.iter().any(|g| glob_matches(g, f))))
+            })
+            .map(|compiled| RuleMatch {
+                name: compiled.name.clone(),
+                task: compiled.rule.task,
+                member: compiled.rule.member.clone(),
+                provider: compiled.rule.provider.clone(),
+            })
+    }
+}
+
+impl SupportTeam {
+    /// Decide the task type, member and provider for a request, keeping
+    /// every input to the decision
+    ///
+    /// Explicit choices (`hint` from `-t`, `provider` from `-m`) win over
+    /// config rules, which win over the classifier. A rule whose provider
+    /// isn't available is set aside; only `-m` fails outright.
+    pub fn explain_route(
+        &self,
+        request: &str,
+        hint: Option<TaskType>,
+        provider: Option<&str>,
+    ) -> RoutingExplanation {
+        let mut rule = self.router.match_rule(request);
+        let mut unavailable_rule = None;
+        if provider.is_none() && rule.as_ref().is_some_and(|r| !self.rule_available(r)) {
+            unavailable_rule = rule.take();
+        }
+        let rule_member = rule.as_ref().and_then(|r| r.member.as_deref());
+        let rule_provider = rule.as_ref().and_then(|r| r.provider.as_deref());
+
+        let analysis = self.analyze(request);
+        let (task_type, task_source) = match (hint, rule.as_ref().and_then(|r| r.task)) {
+            (Some(hint), _) => (hint, TaskSource::Hint),
+            (None, Some(task)) => (task, TaskSource::Rule),
+            (None, None) => (analysis.primary_type, TaskSource::Classifier),
+        };
+
+        let forced_member = match provider {
+            Some(_) => None,
+            None => rule_member.and_then(|name| {
+                self.members
+                    .iter()
+                    .find(|m| m.available && m.name.eq_ignore_ascii_case(name))
+            }),
+        };
+
+        let mut tier = None;
+        let mut adaptive = None;
+        let (member, branch): (Option<&TeamMember>, _) =
+            match (forced_member, provider.or(rule_provider)) {
+                (Some(member), _) => (Some(member), SelectionBranch::RuleMember),
+                (None, Some(provider)) => match self.member_on(provider, task_type) {
+                    Some(member) => (Some(member), SelectionBranch::Provider),
+                    None => (None, SelectionBranch::NoMember),
+                },
+                (None, None) => {
+                    let (member, branch) = self.select_member_for_task(task_type);
+                    // Tiering decides on cost and complexity; the bandit only
+                    // second-guesses members it didn't place
+                    if let Some((replacement, decision)) =
+                        member.and_then(|m| self.tier_pick(&analysis, task_type, m))
+                    {
+                        tier = Some(decision);
+                        match replacement {
+                            Some(cheaper) => (Some(cheaper), SelectionBranch::Tier),
+                            None => (member, branch),
+                        }
+                    } else {
+                        match member.and_then(|m| self.adaptive_pick(task_type, m)) {
+                            Some((replacement, decision)) => {
+                                adaptive = Some(decision);
+                                match replacement {
+                                    Some(better) => (Some(better), SelectionBranch::Adaptive),
+                                    None => (member, branch),
+                                }
+                            }
+                            None => (member, branch),
+                        }
+                    }
+                }
+            };
+
+        RoutingExplanation {
+            task_type,
+            task_source,
+            analysis,
+            rule,
+            unavailable_rule,
+            branch,
+            candidates: self.candidates(member),
+            tier,
+            adaptive,
+            thread: None,
+            member: member.map(|m| m.name.clone()),
+            provider: member
+                .map(|m| m.provider_type.clone())
+                .or_else(|| provider.map(str::to_string)),
+        }
+    }
+
+    /// Whether a rule's provider can be used: it has an available member,
+    /// or the rule names an available member itself
+    fn rule_available(&self, rule: &RuleMatch) -> bool {
+        let Some(provider) = rule.provider.as_deref() else {
+            return true;
+        };
+        self.members.iter().filter(|m| m.available).any(|m| {
+            m.provider_type == provider
+                || rule.member.as_deref().is_some_and(|name| m.name.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Route a REPL turn, keeping follow-ups with the previous turn's member
+    ///
+    /// A short follow-up ("and add tests") stays in the thread unless the
+    /// classifier is confident it is about something else. Rules still win,
+    /// and a thread pinned with `/switch` takes every turn.
+    pub fn explain_turn(&self, request: &str, thread: Option<&Thread>) -> RoutingExplanation {
+        let mut explained = self.explain_route(request, None, None);
+        let Some(thread) = thread else {
+            return explained;
+        };
+        let Some(previous) = self
+            .members
+            .iter()
+            .find(|m| m.available && m.name == thread.member)
+        else {
+            return explained;
+        };
+
+        let follow_up = is_follow_up(request, self.sticky.max_words);
+        let topic_change = explained.task_type != thread.task_type
+            && explained.analysis.confidence >= self.sticky.switch_confidence;
+        explained.thread = Some(ThreadCheck {
+            previous: previous.name.clone(),
+            follow_up,
+            topic_change,
+            pinned: thread.pinned,
+        });
+
+        let branch = if thread.pinned {
+            SelectionBranch::Pinned
+        } else if self.sticky.enabled
+            && explained.task_source == TaskSource::Classifier
+            && follow_up
+            && !topic_change
+        {
+            explained.task_type = thread.task_type;
+            explained.task_source = TaskSource::FollowUp;
+            SelectionBranch::FollowUp
+        } else {
+            return explained;
+        };
+
+        explained.branch = branch;
+        explained.tier = None;
+        explained.adaptive = None;
+        explained.candidates = self.candidates(Some(previous));
+        explained.member = Some(previous.name.clone());
+        explained.provider = Some(previous.provider_type.clone());
+        explained
+    }
+
+    /// Every member, marking the chosen one
+    fn candidates(&self, chosen: Option<&TeamMember>) -> Vec<MemberCandidate> {
+        self.members
+            .iter()
+            .map(|m| MemberCandidate {
+                name: m.name.clone(),
+                role: m.role.clone(),
+                specialty: m.specialty,
+                provider: m.provider_type.clone(),
+                available: m.available,
+                chosen: chosen.is_some_and(|chosen| chosen.name == m.name),
+            })
+            .collect()
+    }
+}
+
+impl Thread {
+    /// The thread after a turn along `route`; a pin carries over
+    pub fn after(route: &Route, previous: Option<&Thread>) -> Option<Thread> {
+        Some(Thread {
+            member: route.member.clone()?,
+            task_type: route.task_type,
+            pinned: previous.is_some_and(|t| t.pinned),
+        })
+    }
+}
+
+/// Whether a request reads like a continuation of the previous turn
+///
+/// Short requests and ones opening with "and", "now", "what about", ... are
+/// follow-ups. Expanded `@file` contents don't count towards the length.
+fn is_follow_up(request: &str, max_words: usize) -> bool {
+    let text = EXPANDED_BLOCK.replace_all(request, " $1");
+    let words = text.split_whitespace().count();
+    let lower = text.trim_start().to_lowercase();
+    words <= max_words
+        || FOLLOW_UP_OPENERS.iter().any(|opener| {
+            lower
+                .strip_prefix(opener)
+                .is_some_and(|rest| rest.starts_with(|c: char| !c.is_alphanumeric()))
+        })
+}
+
+/// Paths referenced by the request, as `@path` or expanded `--- path ---`
+fn file_refs(request: &str) -> Vec<&str> {
+    FILE_REF
+        .captures_iter(request)
+        .chain(EXPANDED_REF.captures_iter(request))
+        .filter_map(|c| c.get(1).map(|m| m.as_str()))
+        .collect()
+}
+
+/// Match a path against a glob with `*` and `?`
+///
+/// Globs without a `/` match the file name alone, so "*.rs" matches
+/// "src/main.rs".
+fn glob_matches(glob: &str, path: &str) -> bool {
+    let target = if glob.contains('/') {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = target.chars().collect();
+
+    // matched[j]: glob[..i] matches text[..j]
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+    for &g in &glob {
+        let mut next = vec![false; text.len() + 1];
+        if g == '*' {
+            let mut any = false;
+            for j in 0..=text.len() {
+                any |= matched[j];
+                next[j] = any;
+            }
+        } else {
+            for j in 1..=text.len() {
+                next[j] = matched[j - 1] && (g == '?' || g == text[j - 1]);
+            }
+        }
+        matched = next;
+    }
+    matched[text.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(toml: &str) -> Router {
+        Router::new(&toml::from_str(toml).unwrap())
+    }
+
+    fn team(providers: &[&str], rules: &str) -> SupportTeam {
+        let config = crate::config::Config {
+            routing: toml::from_str(rules).unwrap(),
+            ..Default::default()
+        };
+        SupportTeam::for_test(providers, &config)
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("*.rs", "src/main.rs"));
+        assert!(glob_matches("src/*.rs", "src/main.rs"));
+        assert!(glob_matches("Cargo.?oml", "Cargo.toml"));
+        assert!(!glob_matches("*.rs", "notes.rst"));
+        assert!(!glob_matches("src/*.rs", "main.rs"));
+    }
+
+    #[test]
+    fn test_match_rule_conditions() {
+        let router = router(
+            r#"
+            [[rules]]
+            name = "rust"
+            files = ["*.rs"]
+            task = "analyze"
+            provider = "codex-cli"
+
+            [[rules]]
+            pattern = "(?i)^translate"
+            keywords = ["french", "german"]
+            member = "Iris"
+            "#,
+        );
+
+        let rust = router.match_rule("why does @src/main.rs panic?").unwrap();
+        assert_eq!(rust.name, "rust");
+        assert_eq!(rust.task, Some(TaskType::Analyze));
+
+        // Expanded references still count
+        assert!(router.match_rule("look at\n--- src/lib.rs ---\nfn x() {}\n---").is_some());
+
+        // Both the pattern and a keyword are needed
+        assert_eq!(router.match_rule("Translate this to French").unwrap().name, "rule 2");
+        assert!(router.match_rule("Translate this to Spanish").is_none());
+        assert!(router.match_rule("French toast recipe").is_none());
+    }
+
+    #[test]
+    fn test_explain_route_sources_and_branches() {
+        let rules = r#"
+            [[rules]]
+            name = "rust"
+            files = ["*.rs"]
+            task = "analyze"
+            "#;
+        let team = team(&["gemini-cli", "codex-cli"], rules);
+
+        let explained = team.explain_route("write a blog post", None, None);
+        assert_eq!(explained.task_source, TaskSource::Classifier);
+        assert_eq!(explained.branch, SelectionBranch::SpecialistCli);
+        assert_eq!(explained.member.as_deref(), Some("Iris"));
+        assert_eq!(explained.candidates.iter().filter(|c| c.chosen).count(), 1);
+
+        let explained = team.explain_route("tidy up @src/lib.rs", None, None);
+        assert_eq!(explained.task_source, TaskSource::Rule);
+        assert_eq!(explained.task_type, TaskType::Analyze);
+
+        let explained = team.explain_route("tidy up @src/lib.rs", Some(TaskType::Edit), None);
+        assert_eq!(explained.task_source, TaskSource::Hint);
+
+        let explained = team.explain_route("write a blog post", None, Some("codex-cli"));
+        assert_eq!(explained.branch, SelectionBranch::Provider);
+        assert_eq!(explained.provider.as_deref(), Some("codex-cli"));
+    }
+
+    #[test]
+    fn test_rule_with_unavailable_provider_falls_back() {
+        let rules = r#"
+            [[rules]]
+            name = "docs"
+            keywords = ["blog"]
+            task = "analyze"
+            provider = "anthropic"
+            "#;
+        let team = team(&["gemini-cli", "codex-cli"], rules);
+
+        let explained = team.explain_route("write a blog post", None, None);
+        assert_eq!(explained.rule, None);
+        assert_eq!(explained.unavailable_rule.as_ref().unwrap().name, "docs");
+        assert_eq!(explained.task_source, TaskSource::Classifier);
+        assert_eq!(explained.member.as_deref(), Some("Iris"));
+        assert!(explained.render().contains("Rule: docs matched, but anthropic is not available"));
+
+        // An explicit -m still fails when the provider is missing
+        let explained = team.explain_route("write a blog post", None, Some("anthropic"));
+        assert_eq!(explained.branch, SelectionBranch::NoMember);
+    }
+
+    #[test]
+    fn test_explain_route_fallback_and_render() {
+        // No creative specialist on gemini alone: falls back to any CLI member
+        let team = team(&["gemini-cli"], "");
+        let explained = team.explain_route("brainstorm names for a bakery", None, None);
+        assert_eq!(explained.task_type, TaskType::Create);
+        assert_eq!(explained.branch, SelectionBranch::AnyCli);
+
+        let report = explained.render();
+        assert!(report.contains("Task type: Creative (keywords, confidence"));
+        assert!(report.contains("[brainstorm]"));
+        assert!(report.contains(SelectionBranch::AnyCli.describe()));
+        let scores = report
+            .lines()
+            .skip_while(|l| *l != "Scores:")
+            .skip(1)
+            .take_while(|l| l.starts_with("  "));
+        assert_eq!(scores.count(), 11);
+    }
+
+    #[test]
+    fn test_explain_route_tiers() {
+        let mut team = team(&["gemini-cli", "codex-cli", "ollama"], "");
+        team.tiers.enabled = true;
+        team.tiers.session_budget = 8.0;
+
+        let simple = team.explain_route("what's the date format for ISO 8601", None, None);
+        assert_eq!(simple.branch, SelectionBranch::Tier);
+        assert_eq!(simple.member.as_deref(), Some("Local"));
+        assert!(simple.render().contains("Tier: simple request → ollama"));
+
+        // A specialist already on the right tier keeps the task
+        let complex = "analyze this code and research the best fix, then write it up";
+        let explained = team.explain_route(complex, None, None);
+        assert!(explained.analysis.is_complex);
+        assert_eq!(explained.provider.as_deref(), Some("codex-cli"));
+        assert!(explained.tier.is_some());
+
+        team.charge("codex-cli");
+        team.charge("codex-cli");
+        let explained = team.explain_route(complex, None, None);
+        assert_eq!(explained.provider.as_deref(), Some("gemini-cli"));
+        assert_eq!(team.budget().map(|b| b.remaining()), Some(4.0));
+    }
+
+    #[test]
+    fn test_explain_turn_follow_ups() {
+        let team = team(&["gemini-cli", "codex-cli"], "");
+        let thread = Thread {
+            member: "Dev".to_string(),
+            task_type: TaskType::Analyze,
+            pinned: false,
+        };
+
+        let explained = team.explain_turn("and add tests", Some(&thread));
+        assert_eq!(explained.branch, SelectionBranch::FollowUp);
+        assert_eq!(explained.task_source, TaskSource::FollowUp);
+        assert_eq!(explained.task_type, TaskType::Analyze);
+        assert_eq!(explained.member.as_deref(), Some("Dev"));
+        assert!(explained.render().contains("Thread: follow-up, stayed with Dev"));
+
+        // Confident topic change leaves the thread
+        let explained = team.explain_turn("now write a blog post about it", Some(&thread));
+        assert_eq!(explained.task_type, TaskType::Write);
+        assert_eq!(explained.member.as_deref(), Some("Iris"));
+        assert!(explained.thread.as_ref().is_some_and(|t| t.topic_change));
+
+        // A long, fresh request is routed from scratch
+        let fresh = "give me a summary of the main arguments for and against remote work";
+        assert_ne!(team.explain_turn(fresh, Some(&thread)).branch, SelectionBranch::FollowUp);
+
+        let pinned = Thread { pinned: true, ..thread };
+        let explained = team.explain_turn("write a blog post", Some(&pinned));
+        assert_eq!(explained.branch, SelectionBranch::Pinned);
+        assert_eq!(explained.member.as_deref(), Some("Dev"));
+
+        let route = explained.route();
+        assert_eq!(Thread::after(&route, Some(&pinned)).map(|t| t.pinned), Some(true));
+    }
+
+    #[test]
+    fn test_is_follow_up() {
+        assert!(is_follow_up("and add tests", 8));
+        assert!(is_follow_up("Also, handle the empty case and the overflow case in the parser", 8));
+        assert!(!is_follow_up("android apps need a different build setup for release signing", 8));
+        assert!(is_follow_up("fix this\n--- src/lib.rs ---\nfn a() {}\nfn b() {}\n---", 8));
+    }
+
+    #[test]
+    fn test_router_skips_broken_rules() {
+        let router = router(
+            r#"
+            [[rules]]
+            pattern = "(unclosed"
+            task = "write"
+
+            [[rules]]
+            task = "write"
+            "#,
+        );
+        assert!(router.rules.is_empty());
+    }
+}
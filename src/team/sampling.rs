@@ -0,0 +1,379 @@
+//! Best-of-N sampling - several candidate answers, one judge
+//!
+//! Lighter than council deliberation: candidates are generated concurrently,
+//! then a single judge call scores them and picks or merges the best.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+use super::{get_provider_preference, SupportTeam, TaskType, TeamMember};
+use crate::config::ContextBudgetConfig;
+use crate::llm::{generate_concurrently, ContextBuilder, LlmProvider};
+
+/// One candidate answer in a best-of-N run
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub member: String,
+    pub provider: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    /// Judge's score out of 10, if the judge gave one
+    pub score: Option<f32>,
+}
+
+/// Outcome of a best-of-N run
+#[derive(Debug, Clone, Serialize)]
+pub struct BestOf {
+    pub answer: String,
+    pub candidates: Vec<Candidate>,
+    /// Provider that judged the candidates, if a judge was needed
+    pub judge: Option<String>,
+    /// Index of the chosen candidate; `None` when the judge merged them
+    pub chosen: Option<usize>,
+}
+
+/// What the judge decided, in terms of the candidates it was shown
+#[derive(Debug, Default, PartialEq)]
+struct Verdict {
+    scores: Vec<Option<f32>>,
+    /// 1-based pick; 0 means the judge merged the candidates
+    best: Option<usize>,
+    merged: Option<String>,
+}
+
+static SCORES_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?im)^\s*\**scores?\**\s*:\s*(.+)$").unwrap());
+static BEST_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?im)^\s*\**best\**\s*:\s*(\d+)").unwrap());
+static ANSWER_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?im)^\s*\**answer\**\s*:[ \t]*").unwrap());
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)?").unwrap());
+
+impl SupportTeam {
+    /// Number of candidates configured for a task type
+    pub fn best_of_for(&self, task_type: TaskType) -> u32 {
+        self.sampling.best_of.get(&task_type).copied().unwrap_or(1)
+    }
+
+    /// Generate `n` candidate answers and have a judge pick or merge the best
+    ///
+    /// Candidates come from `provider` alone, or are spread across the
+    /// available providers in preference order when sampling is set to spread.
+    pub async fn best_of(
+        &self,
+        request: &str,
+        task_type: TaskType,
+        provider: &str,
+        n: u32,
+    ) -> Result<BestOf> {
+        let lineup = self.candidate_providers(task_type, provider, n);
+        if lineup.is_empty() {
+            return Err(anyhow::anyhow!("Provider not available: {}", provider));
+        }
+
+        let prompts: Vec<(String, &TeamMember, String)> = lineup
+            .iter()
+            .filter_map(|name| {
+                let member = self.member_on(name, task_type)?;
+                let prompt = self.member_prompt(request, task_type, member);
+                Some((name.clone(), member, prompt))
+            })
+            .collect();
+
+        let calls: Vec<(&dyn LlmProvider, &str)> = prompts
+            .iter()
+            .filter_map(|(name, _, prompt)| {
+                Some((self.providers.get(name)?.as_ref(), prompt.as_str()))
+            })
+            .collect();
+        let results = generate_concurrently(&calls).await;
+
+        let mut candidates: Vec<Candidate> = prompts
+            .iter()
+            .zip(results)
+            .map(|((name, member, _), timed)| {
+                let (response, error) = match timed.result {
+                    Ok(response) => (Some(response), None),
+                    Err(e) => (None, Some(e.to_string())),
+                };
+                Candidate {
+                    member: member.name.clone(),
+                    provider: name.clone(),
+                    response,
+                    error,
+                    latency_ms: timed.latency.as_millis() as u64,
+                    score: None,
+                }
+            })
+            .collect();
+
+        let answered: Vec<usize> = (0..candidates.len())
+            .filter(|&i| candidates[i].response.is_some())
+            .collect();
+
+        match answered.len() {
+            0 => Err(anyhow::anyhow!(
+                "All {} candidates failed: {}",
+                candidates.len(),
+                candidates
+                    .iter()
+                    .filter_map(|c| c.error.as_deref())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )),
+            1 => Ok(BestOf {
+                answer: candidates[answered[0]].response.clone().unwrap_or_default(),
+                candidates,
+                judge: None,
+                chosen: Some(answered[0]),
+            }),
+            _ => {
+                let judge_name = self.judge_provider(provider);
+                let texts: Vec<&str> = answered
+                    .iter()
+                    .filter_map(|&i| candidates[i].response.as_deref())
+                    .collect();
+                let reply = match self.providers.get(&judge_name) {
+                    Some(judge) => {
+                        let prompt = judge_prompt(request, &texts, judge.as_ref()).await;
+                        judge.generate(&prompt).await.ok()
+                    }
+                    None => None,
+                };
+                let Some(reply) = reply else {
+                    // Without a verdict the candidates are still good answers
+                    return Ok(BestOf {
+                        answer: candidates[answered[0]].response.clone().unwrap_or_default(),
+                        candidates,
+                        judge: None,
+                        chosen: Some(answered[0]),
+                    });
+                };
+                let verdict = parse_verdict(&reply, texts.len());
+
+                for (position, &index) in answered.iter().enumerate() {
+                    candidates[index].score = verdict.scores.get(position).copied().flatten();
+                }
+
+                let (answer, chosen) = match (verdict.best, verdict.merged) {
+                    (Some(0), Some(merged)) => (merged, None),
+                    (Some(best), _) if best >= 1 && best <= answered.len() => {
+                        let index = answered[best - 1];
+                        (candidates[index].response.clone().unwrap_or_default(), Some(index))
+                    }
+                    _ => {
+                        // No usable pick: fall back to the highest score, then the first answer
+                        let index = answered
+                            .iter()
+                            .copied()
+                            .max_by(|&a, &b| {
+                                let score = |i: usize| candidates[i].score.unwrap_or(0.0);
+                                score(a)
+                                    .partial_cmp(&score(b))
+                                    .unwrap_or(std::cmp::Ordering::Equal)
+                                    .then(b.cmp(&a))
+                            })
+                            .unwrap_or(answered[0]);
+                        (candidates[index].response.clone().unwrap_or_default(), Some(index))
+                    }
+                };
+
+                Ok(BestOf {
+                    answer,
+                    candidates,
+                    judge: Some(judge_name),
+                    chosen,
+                })
+            }
+        }
+    }
+
+    /// Providers to draw `n` candidates from
+    pub(super) fn candidate_providers(
+        &self,
+        task_type: TaskType,
+        provider: &str,
+        n: u32,
+    ) -> Vec<String> {
+        if !self.providers.contains_key(provider) {
+            return Vec::new();
+        }
+        if !self.sampling.spread {
+            return vec![provider.to_string(); n as usize];
+        }
+
+        // The routed provider first, then the rest in task preference order
+        let preference = get_provider_preference(task_type);
+        let mut pool = vec![provider.to_string()];
+        for name in preference
+            .preferred_providers
+            .iter()
+            .chain(preference.fallback_providers.iter())
+        {
+            if self.providers.contains_key(*name) && !pool.iter().any(|p| p == name) {
+                pool.push(name.to_string());
+            }
+        }
+
+        pool.iter().cycle().take(n as usize).cloned().collect()
+    }
+
+    /// Member to speak for a provider, preferring the task's specialist
+    pub(super) fn member_on(&self, provider: &str, task_type: TaskType) -> Option<&TeamMember> {
+        let on_provider = || {
+            self.members
+                .iter()
+                .filter(move |m| m.available && m.provider_type == provider)
+        };
+        on_provider()
+            .find(|m| m.specialty == task_type)
+            .or_else(|| on_provider().next())
+    }
+
+    /// Configured judge if it is available, otherwise the task's own provider
+    fn judge_provider(&self, provider: &str) -> String {
+        self.sampling
+            .judge
+            .as_deref()
+            .filter(|judge| self.providers.contains_key(*judge))
+            .unwrap_or(provider)
+            .to_string()
+    }
+}
+
+/// Build the judge prompt, fitting the candidates to the judge's context window
+async fn judge_prompt(request: &str, candidates: &[&str], judge: &dyn LlmProvider) -> String {
+    let template = |body: &str| {
+        format!(
+            "You are judging {} candidate answers to the same request.\n\n\
+            Request:\n{}\n\n{}\n\n\
+            Score each candidate from 0 to 10 for correctness, completeness and clarity. \
+            Then pick the best candidate, or answer 0 if merging them gives a clearly \
+            better answer.\n\n\
+            Reply in exactly this format:\n\
+            SCORES: <score for candidate 1>, <score for candidate 2>, ...\n\
+            BEST: <candidate number, or 0 to merge>\n\
+            ANSWER:\n<the merged answer, only when BEST is 0>",
+            candidates.len(),
+            request,
+            body
+        )
+    };
+
+    let builder = ContextBuilder::new(ContextBudgetConfig::default(), None);
+    let budget = builder.budget_for(judge, &template(""));
+    let fitted = builder.assemble(candidates, budget).await;
+
+    template(
+        &fitted
+            .iter()
+            .enumerate()
+            .map(|(i, text)| format!("=== Candidate {} ===\n{}\n", i + 1, text))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// Parse the judge's reply; anything missing is left empty
+fn parse_verdict(reply: &str, count: usize) -> Verdict {
+    let mut verdict = Verdict::default();
+
+    if let Some(line) = SCORES_LINE.captures(reply) {
+        verdict.scores = line[1]
+            .split(',')
+            .map(|part| {
+                NUMBER
+                    .find(part)
+                    .and_then(|n| n.as_str().parse::<f32>().ok())
+                    .map(|score| score.clamp(0.0, 10.0))
+            })
+            .take(count)
+            .collect();
+    }
+
+    verdict.best = BEST_LINE
+        .captures(reply)
+        .and_then(|c| c[1].parse::<usize>().ok());
+
+    if let Some(found) = ANSWER_LINE.find(reply) {
+        let merged = reply[found.end()..].trim();
+        if !merged.is_empty() {
+            verdict.merged = Some(merged.to_string());
+        }
+    }
+
+    verdict
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::team::FixedProvider;
+
+    fn team(spread: bool) -> SupportTeam {
+        let mut config = Config::default();
+        config.sampling.spread = spread;
+        SupportTeam::for_test(&["gemini-cli", "codex-cli", "claude-cli"], &config)
+    }
+
+    #[tokio::test]
+    async fn test_best_of_survives_failed_judge() {
+        let mut config = Config::default();
+        config.sampling.judge = Some("claude-cli".to_string());
+        let mut team = SupportTeam::for_test(&["codex-cli", "claude-cli"], &config);
+        team.providers.insert("codex-cli".to_string(), Box::new(FixedProvider(Some("42"))));
+        team.providers.insert("claude-cli".to_string(), Box::new(FixedProvider(None)));
+
+        let best = team.best_of("what is 6 * 7?", TaskType::Solve, "codex-cli", 3).await.unwrap();
+        assert_eq!(best.answer, "42");
+        assert_eq!(best.chosen, Some(0));
+        assert_eq!(best.judge, None);
+        assert!(best.candidates.iter().all(|c| c.score.is_none()));
+    }
+
+    #[test]
+    fn test_candidate_providers() {
+        assert_eq!(
+            team(false).candidate_providers(TaskType::Analyze, "codex-cli", 3),
+            vec!["codex-cli", "codex-cli", "codex-cli"]
+        );
+        assert_eq!(
+            team(true).candidate_providers(TaskType::Analyze, "codex-cli", 4),
+            vec!["codex-cli", "claude-cli", "gemini-cli", "codex-cli"]
+        );
+        assert!(team(true).candidate_providers(TaskType::Analyze, "ollama", 2).is_empty());
+    }
+
+    #[test]
+    fn test_member_on_prefers_specialist() {
+        let team = team(false);
+        assert_eq!(team.member_on("codex-cli", TaskType::Solve).unwrap().name, "Cody");
+        assert_eq!(team.member_on("codex-cli", TaskType::Write).unwrap().name, "Dev");
+    }
+
+    #[test]
+    fn test_parse_verdict_pick() {
+        let verdict = parse_verdict("SCORES: 6/10, 9, 7.5\nBEST: 2\nANSWER:\n", 3);
+        assert_eq!(verdict.scores, vec![Some(6.0), Some(9.0), Some(7.5)]);
+        assert_eq!(verdict.best, Some(2));
+        assert_eq!(verdict.merged, None);
+    }
+
+    #[test]
+    fn test_parse_verdict_merge() {
+        let reply = "**Scores**: 7, 8\n**Best**: 0\n**Answer**:\nUse both:\n- first\n- second";
+        let verdict = parse_verdict(reply, 2);
+        assert_eq!(verdict.best, Some(0));
+        assert_eq!(verdict.merged.as_deref(), Some("Use both:\n- first\n- second"));
+    }
+
+    #[test]
+    fn test_parse_verdict_garbage() {
+        let verdict = parse_verdict("I like the second one.", 2);
+        assert_eq!(verdict, Verdict::default());
+    }
+}
@@ -0,0 +1,293 @@
+//! Tiered routing - cheap models for easy questions, strong ones for hard work
+//!
+//! Each provider has a cost and a quality tier in config. Simple requests go
+//! to the cheapest provider that is good enough; complex ones go to the
+//! strongest provider the session budget still allows. As the budget runs
+//! out, the best tier on offer shrinks with it.
+
+use serde::Serialize;
+
+use super::{SupportTeam, TaskAnalysis, TaskType, TeamMember};
+use crate::config::TierConfig;
+
+/// How demanding a request looks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Complexity {
+    Simple,
+    Complex,
+}
+
+impl Complexity {
+    pub fn of(analysis: &TaskAnalysis) -> Self {
+        if analysis.is_complex {
+            Complexity::Complex
+        } else {
+            Complexity::Simple
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Complexity::Simple => "simple",
+            Complexity::Complex => "complex",
+        }
+    }
+}
+
+/// Spending so far against the session budget
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Budget {
+    pub spent: f32,
+    pub limit: f32,
+}
+
+impl Budget {
+    pub fn remaining(&self) -> f32 {
+        (self.limit - self.spent).max(0.0)
+    }
+}
+
+/// What tiered routing decided for a request
+#[derive(Debug, Clone, Serialize)]
+pub struct TierDecision {
+    pub complexity: Complexity,
+    pub provider: String,
+    pub cost: f32,
+    pub quality: u8,
+    /// Best quality tier the remaining budget allows
+    pub max_quality: u8,
+    /// The session budget, when one is set
+    pub budget: Option<Budget>,
+    /// Nothing fit the remaining budget, so the cheapest provider was used
+    pub over_budget: bool,
+}
+
+impl TierDecision {
+    /// One-line summary for `/why` and `--explain-routing`
+    pub fn describe(&self) -> String {
+        let mut summary = format!(
+            "{} request → {} (quality {}, cost {})",
+            self.complexity.label(),
+            self.provider,
+            self.quality,
+            self.cost
+        );
+        if let Some(budget) = self.budget {
+            summary.push_str(&format!(
+                " | budget {:.1} of {} left, up to quality {}",
+                budget.remaining(),
+                budget.limit,
+                self.max_quality
+            ));
+        }
+        if self.over_budget {
+            summary.push_str(" | over budget");
+        }
+        summary
+    }
+}
+
+impl SupportTeam {
+    /// Let tiered routing reconsider a statically chosen member
+    ///
+    /// Returns the member to use instead, if any, and the tier decision.
+    pub(super) fn tier_pick(
+        &self,
+        analysis: &TaskAnalysis,
+        task_type: TaskType,
+        chosen: &TeamMember,
+    ) -> Option<(Option<&TeamMember>, TierDecision)> {
+        if !self.tiers.enabled {
+            return None;
+        }
+
+        let decision = choose_tier(
+            &self.tiers,
+            Complexity::of(analysis),
+            &chosen.provider_type,
+            &self.available_providers(),
+            self.spent,
+        )?;
+        let replacement = (decision.provider != chosen.provider_type)
+            .then(|| self.member_on(&decision.provider, task_type))
+            .flatten();
+        Some((replacement, decision))
+    }
+
+    /// Charge a call to `provider` against the session budget
+    pub fn charge(&mut self, provider: &str) {
+        if let Some(tier) = self.tiers.providers.get(provider) {
+            self.spent += tier.cost;
+        }
+    }
+
+    /// The session budget, when tiered routing has one
+    pub fn budget(&self) -> Option<Budget> {
+        (self.tiers.enabled && self.tiers.session_budget > 0.0).then_some(Budget {
+            spent: self.spent,
+            limit: self.tiers.session_budget,
+        })
+    }
+}
+
+/// Pick a provider for a request of the given complexity
+///
+/// `available` lists providers in team order; those without a configured
+/// tier are ignored. Returns `None` when no available provider has a tier.
+fn choose_tier(
+    config: &TierConfig,
+    complexity: Complexity,
+    preferred: &str,
+    available: &[String],
+    spent: f32,
+) -> Option<TierDecision> {
+    let tiered: Vec<(&str, f32, u8)> = available
+        .iter()
+        .filter_map(|name| {
+            let tier = config.providers.get(name)?;
+            Some((name.as_str(), tier.cost, tier.quality))
+        })
+        .collect();
+    let top = tiered.iter().map(|&(_, _, quality)| quality).max()?;
+
+    let budget = (config.session_budget > 0.0).then_some(Budget {
+        spent,
+        limit: config.session_budget,
+    });
+    // The best tier on offer shrinks with the share of budget left
+    let max_quality = match budget {
+        Some(budget) => {
+            let left = budget.remaining() / budget.limit;
+            ((top as f32 * left).ceil() as u8).max(1)
+        }
+        None => top,
+    };
+
+    let affordable: Vec<(&str, f32, u8)> = tiered
+        .iter()
+        .copied()
+        .filter(|&(_, cost, quality)| {
+            quality <= max_quality && budget.is_none_or(|b| cost <= b.remaining())
+        })
+        .collect();
+
+    let not_preferred = |name: &str| name != preferred;
+    let (pick, over_budget) = if affordable.is_empty() {
+        let cheapest = tiered.iter().copied().min_by(|a, b| {
+            a.1.total_cmp(&b.1)
+                .then(b.2.cmp(&a.2))
+                .then(not_preferred(a.0).cmp(&not_preferred(b.0)))
+        })?;
+        (cheapest, true)
+    } else {
+        let pick = match complexity {
+            Complexity::Simple => {
+                let good_enough: Vec<_> = affordable
+                    .iter()
+                    .copied()
+                    .filter(|&(_, _, quality)| quality >= config.simple_quality)
+                    .collect();
+                let pool = if good_enough.is_empty() { &affordable } else { &good_enough };
+                pool.iter().copied().min_by(|a, b| {
+                    a.1.total_cmp(&b.1)
+                        .then(not_preferred(a.0).cmp(&not_preferred(b.0)))
+                        .then(b.2.cmp(&a.2))
+                })?
+            }
+            Complexity::Complex => affordable.iter().copied().min_by(|a, b| {
+                b.2.cmp(&a.2)
+                    .then(not_preferred(a.0).cmp(&not_preferred(b.0)))
+                    .then(a.1.total_cmp(&b.1))
+            })?,
+        };
+        (pick, false)
+    };
+
+    Some(TierDecision {
+        complexity,
+        provider: pick.0.to_string(),
+        cost: pick.1,
+        quality: pick.2,
+        max_quality,
+        budget,
+        over_budget,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn available() -> Vec<String> {
+        ["gemini-cli", "codex-cli", "claude-cli", "ollama"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn config(session_budget: f32) -> TierConfig {
+        TierConfig {
+            enabled: true,
+            session_budget,
+            ..TierConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_simple_goes_cheap_complex_goes_strong() {
+        let config = config(0.0);
+        let simple = choose_tier(&config, Complexity::Simple, "gemini-cli", &available(), 0.0)
+            .unwrap();
+        assert_eq!(simple.provider, "ollama");
+        assert_eq!(simple.budget, None);
+
+        // codex and claude share the top tier; the specialist's provider wins
+        let complex = choose_tier(&config, Complexity::Complex, "claude-cli", &available(), 0.0)
+            .unwrap();
+        assert_eq!(complex.provider, "claude-cli");
+        let complex = choose_tier(&config, Complexity::Complex, "gemini-cli", &available(), 0.0)
+            .unwrap();
+        assert_eq!(complex.provider, "codex-cli");
+        assert!(!complex.over_budget);
+
+        // Raising the bar for simple requests skips the local model
+        let picky = TierConfig { simple_quality: 2, ..config };
+        let simple = choose_tier(&picky, Complexity::Simple, "codex-cli", &available(), 0.0)
+            .unwrap();
+        assert_eq!(simple.provider, "gemini-cli");
+    }
+
+    #[test]
+    fn test_budget_downgrades_tiers() {
+        let config = config(20.0);
+        let fresh = choose_tier(&config, Complexity::Complex, "codex-cli", &available(), 0.0)
+            .unwrap();
+        assert_eq!((fresh.provider.as_str(), fresh.max_quality), ("codex-cli", 3));
+
+        // Half the budget left: at most quality 2
+        let half = choose_tier(&config, Complexity::Complex, "codex-cli", &available(), 10.0)
+            .unwrap();
+        assert_eq!((half.provider.as_str(), half.max_quality), ("gemini-cli", 2));
+
+        // Spent out: only the free local model
+        let broke = choose_tier(&config, Complexity::Complex, "codex-cli", &available(), 25.0)
+            .unwrap();
+        assert_eq!(broke.provider, "ollama");
+        assert!(!broke.over_budget);
+        assert!(broke.describe().contains("budget 0.0 of 20 left"));
+    }
+
+    #[test]
+    fn test_over_budget_and_untiered() {
+        let config = config(5.0);
+        let paid: Vec<String> = vec!["codex-cli".to_string(), "claude-cli".to_string()];
+        let decision = choose_tier(&config, Complexity::Complex, "claude-cli", &paid, 5.0)
+            .unwrap();
+        assert_eq!(decision.provider, "codex-cli");
+        assert!(decision.over_budget);
+
+        let untiered = vec!["mystery".to_string()];
+        assert!(choose_tier(&config, Complexity::Simple, "mystery", &untiered, 0.0).is_none());
+    }
+}
@@ -0,0 +1,377 @@
+//! Self-consistency voting - independent answers, majority wins
+//!
+//! For problems with a definite answer (math, "which option"), several
+//! independent samples are drawn, the final answer is pulled out of each, and
+//! the most common answer is returned. How often the samples disagree is a
+//! useful confidence signal.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+
+use super::{SupportTeam, TaskType, TeamMember};
+use crate::config::ContextBudgetConfig;
+use crate::llm::{fit_to_budget, generate_concurrently, ContextBuilder, LlmProvider};
+
+/// One independent sample in a vote
+#[derive(Debug, Clone, Serialize)]
+pub struct VoteSample {
+    pub member: String,
+    pub provider: String,
+    /// Final answer pulled out of the response, if one was found
+    pub answer: Option<String>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+}
+
+/// Votes for one distinct answer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tally {
+    pub answer: String,
+    pub votes: usize,
+}
+
+/// Outcome of a self-consistency vote
+#[derive(Debug, Clone, Serialize)]
+pub struct Vote {
+    /// The majority final answer; empty when no sample gave one
+    pub answer: String,
+    /// Full response from the first sample that gave the majority answer
+    /// (the first response at all when none did)
+    pub response: String,
+    /// Share of responding samples that gave the majority answer
+    pub vote_share: f32,
+    /// Share of responding samples that disagreed or gave no answer
+    pub disagreement: f32,
+    /// Distinct answers, most votes first
+    pub tally: Vec<Tally>,
+    pub samples: Vec<VoteSample>,
+    /// Provider used for answers the pattern couldn't find
+    pub extractor: Option<String>,
+}
+
+/// Instruction appended to each sample's prompt so the default pattern finds the answer
+const ANSWER_INSTRUCTION: &str = "Work through it independently, then finish with a final \
+    line of the form:\nANSWER: <your final answer only>";
+
+impl SupportTeam {
+    /// Number of voting samples configured for a task type
+    ///
+    /// Only problem-solving requests vote; everything else gets one sample.
+    pub fn vote_samples_for(&self, task_type: TaskType) -> u32 {
+        match task_type {
+            TaskType::Solve => self.sampling.vote.samples.max(1),
+            _ => 1,
+        }
+    }
+
+    /// Draw `n` independent answers and return the majority final answer
+    pub async fn vote(
+        &self,
+        request: &str,
+        task_type: TaskType,
+        provider: &str,
+        n: u32,
+    ) -> Result<Vote> {
+        let pattern = Regex::new(&self.sampling.vote.pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid vote pattern: {}", e))?;
+
+        let lineup = self.candidate_providers(task_type, provider, n);
+        if lineup.is_empty() {
+            return Err(anyhow::anyhow!("Provider not available: {}", provider));
+        }
+
+        let prompts: Vec<(String, &TeamMember, String)> = lineup
+            .iter()
+            .filter_map(|name| {
+                let member = self.member_on(name, task_type)?;
+                let prompt = format!(
+                    "{}\n\n{}",
+                    self.member_prompt(request, task_type, member),
+                    ANSWER_INSTRUCTION
+                );
+                Some((name.clone(), member, prompt))
+            })
+            .collect();
+
+        let calls: Vec<(&dyn LlmProvider, &str)> = prompts
+            .iter()
+            .filter_map(|(name, _, prompt)| {
+                Some((self.providers.get(name)?.as_ref(), prompt.as_str()))
+            })
+            .collect();
+        let results = generate_concurrently(&calls).await;
+
+        let mut samples: Vec<VoteSample> = prompts
+            .iter()
+            .zip(results)
+            .map(|((name, member, _), timed)| {
+                let (response, error) = match timed.result {
+                    Ok(response) => (Some(response), None),
+                    Err(e) => (None, Some(e.to_string())),
+                };
+                VoteSample {
+                    member: member.name.clone(),
+                    provider: name.clone(),
+                    answer: response.as_deref().and_then(|r| extract_answer(&pattern, r)),
+                    response,
+                    error,
+                    latency_ms: timed.latency.as_millis() as u64,
+                }
+            })
+            .collect();
+
+        if samples.iter().all(|s| s.response.is_none()) {
+            return Err(anyhow::anyhow!(
+                "All {} samples failed: {}",
+                samples.len(),
+                samples
+                    .iter()
+                    .filter_map(|s| s.error.as_deref())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ));
+        }
+
+        let extractor = self.extract_missing(request, &mut samples).await;
+
+        let answers: Vec<Option<String>> = samples
+            .iter()
+            .filter(|s| s.response.is_some())
+            .map(|s| s.answer.clone())
+            .collect();
+        let tally = count_votes(&answers);
+        let Some(winner) = tally.first() else {
+            // No sample gave a final answer; the responses are still usable
+            let response = samples.iter().find_map(|s| s.response.clone()).unwrap_or_default();
+            return Ok(Vote {
+                answer: String::new(),
+                response,
+                vote_share: 0.0,
+                disagreement: 1.0,
+                tally,
+                samples,
+                extractor,
+            });
+        };
+
+        let vote_share = winner.votes as f32 / answers.len() as f32;
+        let (answer, response) = samples
+            .iter()
+            .find(|s| {
+                let normalized = s.answer.as_deref().map(normalize_answer);
+                normalized.as_deref() == Some(winner.answer.as_str())
+            })
+            .map(|s| {
+                (
+                    s.answer.clone().unwrap_or_default(),
+                    s.response.clone().unwrap_or_default(),
+                )
+            })
+            .unwrap_or_default();
+
+        Ok(Vote {
+            answer,
+            response,
+            vote_share,
+            disagreement: 1.0 - vote_share,
+            tally,
+            samples,
+            extractor,
+        })
+    }
+
+    /// Ask the extractor model for answers the pattern couldn't find
+    ///
+    /// Returns the extractor's name if it was used.
+    async fn extract_missing(&self, request: &str, samples: &mut [VoteSample]) -> Option<String> {
+        let name = self
+            .sampling
+            .vote
+            .extractor
+            .as_deref()
+            .filter(|name| !name.is_empty())?;
+        let extractor = self.providers.get(name)?;
+
+        let missing: Vec<usize> = (0..samples.len())
+            .filter(|&i| samples[i].response.is_some() && samples[i].answer.is_none())
+            .collect();
+        if missing.is_empty() {
+            return None;
+        }
+
+        let template = |response: &str| {
+            format!(
+                "Question:\n{}\n\nResponse:\n{}\n\n\
+                Reply with only the final answer this response gives, as briefly as possible, \
+                or NONE if it gives no definite answer.",
+                request, response
+            )
+        };
+        let budget = ContextBuilder::new(ContextBudgetConfig::default(), None)
+            .budget_for(extractor.as_ref(), &template(""));
+        let prompts: Vec<String> = missing
+            .iter()
+            .map(|&i| {
+                let response = samples[i].response.as_deref().unwrap_or_default();
+                template(&fit_to_budget(response, budget))
+            })
+            .collect();
+
+        let calls: Vec<(&dyn LlmProvider, &str)> = prompts
+            .iter()
+            .map(|prompt| (extractor.as_ref(), prompt.as_str()))
+            .collect();
+        for (&index, timed) in missing.iter().zip(generate_concurrently(&calls).await) {
+            samples[index].answer = timed.result.ok().and_then(|reply| {
+                let reply = clean_answer(reply.lines().next().unwrap_or_default());
+                (!reply.is_empty() && !reply.eq_ignore_ascii_case("none")).then_some(reply)
+            });
+        }
+
+        Some(name.to_string())
+    }
+}
+
+/// Pull the final answer out of a response: the last match of `pattern`
+///
+/// The first capture group is used when the pattern has one.
+fn extract_answer(pattern: &Regex, response: &str) -> Option<String> {
+    let captures = pattern.captures_iter(response).last()?;
+    let found = captures.get(1).or_else(|| captures.get(0))?;
+    let answer = clean_answer(found.as_str());
+    (!answer.is_empty()).then_some(answer)
+}
+
+/// Strip markdown emphasis, quotes and trailing punctuation around an answer
+fn clean_answer(raw: &str) -> String {
+    raw.trim()
+        .trim_matches(|c: char| c.is_whitespace() || "*_`$\"'".contains(c))
+        .trim_end_matches(['.', ','])
+        .trim()
+        .to_string()
+}
+
+/// Canonical form used to compare answers
+///
+/// Case and spacing are ignored, "(B)" and "B" are the same option, and
+/// numbers compare by value ("1,000.0" equals "1000").
+fn normalize_answer(answer: &str) -> String {
+    let collapsed = clean_answer(answer)
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let unwrapped = collapsed
+        .strip_prefix("option ")
+        .unwrap_or(&collapsed)
+        .trim_matches(|c| c == '(' || c == ')');
+
+    match unwrapped.replace(',', "").parse::<f64>() {
+        Ok(value) if value.is_finite() => {
+            if value.fract() == 0.0 && value.abs() < 1e15 {
+                format!("{}", value as i64)
+            } else {
+                value.to_string()
+            }
+        }
+        _ => unwrapped.to_string(),
+    }
+}
+
+/// Count votes per distinct answer, most votes first
+///
+/// Samples without an answer count towards the total but vote for nothing.
+/// Ties go to the answer that appeared first.
+fn count_votes(answers: &[Option<String>]) -> Vec<Tally> {
+    let mut tally: Vec<Tally> = Vec::new();
+    for answer in answers.iter().flatten() {
+        let normalized = normalize_answer(answer);
+        match tally.iter_mut().find(|t| t.answer == normalized) {
+            Some(entry) => entry.votes += 1,
+            None => tally.push(Tally {
+                answer: normalized,
+                votes: 1,
+            }),
+        }
+    }
+    // Stable sort keeps first-seen order among equal counts
+    tally.sort_by_key(|t| std::cmp::Reverse(t.votes));
+    tally
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, VoteConfig};
+    use crate::team::FixedProvider;
+
+    fn default_pattern() -> Regex {
+        Regex::new(&VoteConfig::default().pattern).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_vote_without_final_answers() {
+        let mut team = SupportTeam::for_test(&["codex-cli"], &Config::default());
+        let reply = FixedProvider(Some("It depends on the workload."));
+        team.providers.insert("codex-cli".to_string(), Box::new(reply));
+
+        let vote = team.vote("which is faster?", TaskType::Solve, "codex-cli", 3).await.unwrap();
+        assert_eq!(vote.response, "It depends on the workload.");
+        assert_eq!(vote.answer, "");
+        assert_eq!(vote.vote_share, 0.0);
+        assert!(vote.tally.is_empty());
+    }
+
+    #[test]
+    fn test_extract_answer_default_pattern() {
+        let pattern = default_pattern();
+        let response = "First try: answer: 12\nActually that's wrong.\n**Final Answer:** 42.";
+        assert_eq!(extract_answer(&pattern, response).as_deref(), Some("42"));
+        assert_eq!(extract_answer(&pattern, "ANSWER: (B)").as_deref(), Some("(B)"));
+        assert_eq!(extract_answer(&pattern, "The answer is probably 7"), None);
+    }
+
+    #[test]
+    fn test_extract_answer_custom_pattern() {
+        let pattern = Regex::new(r"\\boxed\{([^}]*)\}").unwrap();
+        let response = "So x = \\boxed{3/4} in the end.";
+        assert_eq!(extract_answer(&pattern, response).as_deref(), Some("3/4"));
+
+        // Without a capture group the whole match is the answer
+        let pattern = Regex::new(r"\d+ apples").unwrap();
+        assert_eq!(extract_answer(&pattern, "I count 5 apples").as_deref(), Some("5 apples"));
+    }
+
+    #[test]
+    fn test_normalize_answer() {
+        assert_eq!(normalize_answer("1,000.0"), normalize_answer("1000"));
+        assert_eq!(normalize_answer("(B)"), normalize_answer("option b"));
+        assert_eq!(normalize_answer("  Paris. "), "paris");
+        assert_ne!(normalize_answer("0.5"), normalize_answer("5"));
+    }
+
+    #[test]
+    fn test_count_votes_majority_and_ties() {
+        let answers = vec![
+            Some("42".to_string()),
+            Some("41".to_string()),
+            None,
+            Some("42.0".to_string()),
+            Some("41".to_string()),
+            Some("42".to_string()),
+        ];
+        assert_eq!(
+            count_votes(&answers),
+            vec![
+                Tally { answer: "42".to_string(), votes: 3 },
+                Tally { answer: "41".to_string(), votes: 2 },
+            ]
+        );
+
+        let tied = vec![Some("b".to_string()), Some("a".to_string())];
+        assert_eq!(count_votes(&tied)[0].answer, "b");
+        assert!(count_votes(&[None, None]).is_empty());
+    }
+}